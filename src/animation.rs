@@ -0,0 +1,504 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+// what a clip does after its last frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoopMode {
+    // starts over from the first frame
+    #[default]
+    Loop,
+    // stays on the last frame
+    Once,
+    // plays backwards to the first frame, then forwards again
+    PingPong,
+}
+
+// a clip as written in the sidecar file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClipFile {
+    // indices into the sheet's frames, in the order they're played
+    pub frames: Vec<usize>,
+    // seconds, one per frame or a single one for all of them
+    pub durations: Vec<f32>,
+    #[serde(default)]
+    pub loop_mode: LoopMode,
+    // (index into frames, name), reported when playing reaches that frame
+    #[serde(default)]
+    pub events: Vec<(usize, String)>,
+}
+
+// the sidecar file next to a png, see sidecar_path
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpriteSheetFile {
+    // the png cut into this many frames of the same width, left to right
+    #[serde(default)]
+    pub frame_count: Option<usize>,
+    // (x, y, w, h) in pixels, instead of frame_count for frames that aren't in a single row
+    #[serde(default)]
+    pub frame_rects: Vec<(f32, f32, f32, f32)>,
+    pub clips: HashMap<String, ClipFile>,
+}
+
+#[derive(Debug)]
+pub enum AnimationFileError {
+    Parse(ron::Error),
+    // every value that didn't pass validation
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for AnimationFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnimationFileError::Parse(err) => write!(f, "animation file is not valid RON: {}", err),
+            AnimationFileError::Invalid(problems) => {
+                write!(f, "animation file has invalid values:")?;
+                for problem in problems.iter() {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// resources/player.png is described by resources/player.anim.ron
+pub fn sidecar_path(png_path: &str) -> String {
+    format!("{}.anim.ron", png_path.trim_end_matches(".png"))
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationFrame {
+    // the part of the texture to draw
+    pub rect: Rect,
+    pub duration: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>,
+    pub loop_mode: LoopMode,
+    // (index into frames, name)
+    events: Vec<(usize, String)>,
+}
+
+impl AnimationClip {
+    // the whole texture, shown forever
+    fn single_frame(texture_size: Vec2) -> Self {
+        AnimationClip {
+            frames: vec![AnimationFrame {
+                rect: Rect::new(0f32, 0f32, texture_size.x, texture_size.y),
+                duration: 1f32,
+            }],
+            loop_mode: LoopMode::Once,
+            events: Vec::new(),
+        }
+    }
+
+    // the order frames are shown in over one round of the clip, ping pong doesn't repeat the ends
+    fn sequence(&self) -> Vec<usize> {
+        let mut sequence: Vec<usize> = (0..self.frames.len()).collect();
+        if self.loop_mode == LoopMode::PingPong && self.frames.len() > 2 {
+            sequence.extend((1..self.frames.len() - 1).rev());
+        }
+        sequence
+    }
+
+    // seconds one round takes
+    pub fn duration(&self) -> f32 {
+        self.sequence()
+            .iter()
+            .map(|index| self.frames[*index].duration)
+            .sum()
+    }
+
+    // how many frames were started `time` seconds into playing, counting every round.
+    // -1 before it started
+    fn step_at(&self, time: f32) -> i64 {
+        if time < 0f32 {
+            return -1;
+        }
+        let sequence = self.sequence();
+        let duration = self.duration();
+        let (rounds, mut time_in_round) = match self.loop_mode {
+            LoopMode::Once => {
+                if time >= duration {
+                    return sequence.len() as i64 - 1;
+                }
+                (0, time)
+            }
+            LoopMode::Loop | LoopMode::PingPong => {
+                let rounds = (time / duration).floor();
+                (rounds as i64, time - rounds * duration)
+            }
+        };
+        let mut step = 0;
+        for index in sequence.iter() {
+            time_in_round -= self.frames[*index].duration;
+            if time_in_round < 0f32 {
+                break;
+            }
+            step += 1;
+        }
+        rounds * sequence.len() as i64 + step.min(sequence.len() - 1) as i64
+    }
+
+    fn frame_index_of_step(&self, step: i64) -> usize {
+        let sequence = self.sequence();
+        sequence[(step.max(0) as usize) % sequence.len()]
+    }
+
+    // the frame shown `time` seconds into playing
+    pub fn frame_at(&self, time: f32) -> &AnimationFrame {
+        &self.frames[self.frame_index_of_step(self.step_at(time))]
+    }
+
+    // for clips stretched over some other timer, 0 -> 1 plays it once and stays on the last frame after
+    pub fn frame_at_progress(&self, progress: f32) -> &AnimationFrame {
+        let duration: f32 = self.frames.iter().map(|frame| frame.duration).sum();
+        let mut time = progress.max(0f32) * duration;
+        for frame in self.frames.iter() {
+            time -= frame.duration;
+            if time < 0f32 {
+                return frame;
+            }
+        }
+        &self.frames[self.frames.len() - 1]
+    }
+
+    // frames picked by something other than time (like damage), past the last is the last
+    pub fn frame(&self, index: usize) -> &AnimationFrame {
+        &self.frames[index.min(self.frames.len() - 1)]
+    }
+
+    // names of the events of every frame reached after from_time, up to and including to_time
+    pub fn events_between(&self, from_time: f32, to_time: f32) -> Vec<&str> {
+        let mut names = Vec::new();
+        for step in self.step_at(from_time) + 1..=self.step_at(to_time) {
+            let frame_index = self.frame_index_of_step(step);
+            for (event_frame, name) in self.events.iter() {
+                if *event_frame == frame_index {
+                    names.push(name.as_str());
+                }
+            }
+        }
+        names
+    }
+}
+
+// every clip of one png
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    clips: HashMap<String, AnimationClip>,
+    // shown for clips the file doesn't have
+    fallback: AnimationClip,
+}
+
+impl SpriteSheet {
+    // for pngs without a sidecar file, every clip is the whole texture
+    pub fn single_frame(texture_size: Vec2) -> Self {
+        SpriteSheet {
+            clips: HashMap::new(),
+            fallback: AnimationClip::single_frame(texture_size),
+        }
+    }
+
+    pub fn from_ron_str(source: &str, texture_size: Vec2) -> Result<Self, AnimationFileError> {
+        let file: SpriteSheetFile = ron::de::from_str(source).map_err(AnimationFileError::Parse)?;
+        Self::from_file(&file, texture_size)
+    }
+
+    // frame_count is measured against texture_size, so the frames follow the png when it's resized
+    pub fn from_file(
+        file: &SpriteSheetFile,
+        texture_size: Vec2,
+    ) -> Result<Self, AnimationFileError> {
+        let mut problems = Vec::<String>::new();
+        let rects: Vec<Rect> = match (file.frame_count, file.frame_rects.is_empty()) {
+            (Some(frame_count), true) if frame_count > 0 => {
+                let frame_width = texture_size.x / frame_count as f32;
+                (0..frame_count)
+                    .map(|i| Rect::new(frame_width * i as f32, 0f32, frame_width, texture_size.y))
+                    .collect()
+            }
+            (Some(_), true) => {
+                problems.push("frame_count must be at least 1".to_owned());
+                Vec::new()
+            }
+            (None, false) => file
+                .frame_rects
+                .iter()
+                .map(|(x, y, w, h)| Rect::new(*x, *y, *w, *h))
+                .collect(),
+            _ => {
+                problems.push("give either frame_count or frame_rects".to_owned());
+                Vec::new()
+            }
+        };
+        for (i, rect) in rects.iter().enumerate() {
+            let inside = rect.x >= 0f32
+                && rect.y >= 0f32
+                && rect.w > 0f32
+                && rect.h > 0f32
+                && rect.right() <= texture_size.x
+                && rect.bottom() <= texture_size.y;
+            if !inside {
+                problems.push(format!(
+                    "frame {} must be inside the {}x{} png, got {:?}",
+                    i, texture_size.x, texture_size.y, rect
+                ));
+            }
+        }
+
+        let mut clips = HashMap::new();
+        for (name, clip_file) in file.clips.iter() {
+            if clip_file.frames.is_empty() {
+                problems.push(format!("clip {} has no frames", name));
+                continue;
+            }
+            if let Some(frame) = clip_file.frames.iter().find(|frame| **frame >= rects.len()) {
+                problems.push(format!(
+                    "clip {} uses frame {}, the sheet has {}",
+                    name,
+                    frame,
+                    rects.len()
+                ));
+                continue;
+            }
+            let durations_fit = clip_file.durations.len() == 1
+                || clip_file.durations.len() == clip_file.frames.len();
+            if !durations_fit {
+                problems.push(format!(
+                    "clip {} needs 1 duration or one per frame ({}), got {}",
+                    name,
+                    clip_file.frames.len(),
+                    clip_file.durations.len()
+                ));
+                continue;
+            }
+            if clip_file
+                .durations
+                .iter()
+                .any(|duration| duration.is_nan() || *duration <= 0f32)
+            {
+                problems.push(format!(
+                    "clip {} durations must be greater than 0, got {:?}",
+                    name, clip_file.durations
+                ));
+                continue;
+            }
+            if let Some((frame, event)) = clip_file
+                .events
+                .iter()
+                .find(|(frame, _)| *frame >= clip_file.frames.len())
+            {
+                problems.push(format!(
+                    "clip {} event {} is on frame {}, the clip has {}",
+                    name,
+                    event,
+                    frame,
+                    clip_file.frames.len()
+                ));
+                continue;
+            }
+            let frames = clip_file
+                .frames
+                .iter()
+                .enumerate()
+                .map(|(i, frame)| AnimationFrame {
+                    rect: rects[*frame],
+                    duration: clip_file.durations[i.min(clip_file.durations.len() - 1)],
+                })
+                .collect();
+            clips.insert(
+                name.clone(),
+                AnimationClip {
+                    frames,
+                    loop_mode: clip_file.loop_mode,
+                    events: clip_file.events.clone(),
+                },
+            );
+        }
+
+        if problems.is_empty() {
+            Ok(SpriteSheet {
+                clips,
+                fallback: AnimationClip::single_frame(texture_size),
+            })
+        } else {
+            Err(AnimationFileError::Invalid(problems))
+        }
+    }
+
+    // clips the file doesn't have are drawn as the whole texture
+    pub fn clip(&self, name: &str) -> &AnimationClip {
+        self.clips.get(name).unwrap_or(&self.fallback)
+    }
+}
+
+// reads the sidecar of the png, a png without one is a single frame.
+// A broken sidecar is reported and the png is drawn as a single frame too
+pub async fn load_sprite_sheet(png_path: &str, texture_size: Vec2) -> SpriteSheet {
+    let path = sidecar_path(png_path);
+    let source = match macroquad::file::load_string(&path).await {
+        Ok(source) => source,
+        Err(_) => return SpriteSheet::single_frame(texture_size),
+    };
+    SpriteSheet::from_ron_str(&source, texture_size).unwrap_or_else(|err| {
+        eprintln!("{}: {}\ndrawing {} as a single frame", path, err, png_path);
+        SpriteSheet::single_frame(texture_size)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // four 10 pixel wide frames
+    const TEXTURE_SIZE: Vec2 = const_vec2!([40f32, 10f32]);
+
+    fn sheet() -> SpriteSheet {
+        SpriteSheet::from_ron_str(
+            r#"(
+                frame_count: Some(4),
+                clips: {
+                    "loop": (frames: [0, 1, 2], durations: [0.1], events: [(0, "start"), (2, "end")]),
+                    "once": (frames: [0, 1, 2], durations: [0.1, 0.2, 0.3], loop_mode: Once),
+                    "ping": (frames: [0, 1, 2, 3], durations: [0.1], loop_mode: PingPong),
+                },
+            )"#,
+            TEXTURE_SIZE,
+        )
+        .unwrap()
+    }
+
+    // the frame shown at each time, by its x in the png
+    fn frames_at(clip: &AnimationClip, times: &[f32]) -> Vec<f32> {
+        times
+            .iter()
+            .map(|time| clip.frame_at(*time).rect.x)
+            .collect()
+    }
+
+    fn problems(source: &str) -> Vec<String> {
+        match SpriteSheet::from_ron_str(source, TEXTURE_SIZE) {
+            Err(AnimationFileError::Invalid(problems)) => problems,
+            other => panic!("expected invalid values, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sidecar_path_replaces_the_extension() {
+        assert_eq!(
+            sidecar_path("resources/player.png"),
+            "resources/player.anim.ron"
+        );
+    }
+
+    #[test]
+    fn loop_starts_over() {
+        let sheet = sheet();
+        let times = [-1f32, 0.05f32, 0.15f32, 0.25f32, 0.35f32, 0.45f32];
+        assert_eq!(
+            frames_at(sheet.clip("loop"), &times),
+            vec![0f32, 0f32, 10f32, 20f32, 0f32, 10f32]
+        );
+    }
+
+    #[test]
+    fn once_stays_on_the_last_frame() {
+        let sheet = sheet();
+        let times = [0.05f32, 0.15f32, 0.35f32, 0.55f32, 10f32];
+        assert_eq!(
+            frames_at(sheet.clip("once"), &times),
+            vec![0f32, 10f32, 20f32, 20f32, 20f32]
+        );
+    }
+
+    #[test]
+    fn ping_pong_plays_back_without_repeating_the_ends() {
+        let sheet = sheet();
+        let clip = sheet.clip("ping");
+        assert!((clip.duration() - 0.6f32).abs() < 0.0001f32);
+        let times = [0.05f32, 0.35f32, 0.45f32, 0.55f32, 0.65f32, 0.75f32];
+        assert_eq!(
+            frames_at(clip, &times),
+            vec![0f32, 30f32, 20f32, 10f32, 0f32, 10f32]
+        );
+    }
+
+    #[test]
+    fn missing_clip_is_the_whole_texture() {
+        let sheet = sheet();
+        let frame = sheet.clip("walk").frame_at(5f32);
+        assert_eq!(frame.rect, Rect::new(0f32, 0f32, 40f32, 10f32));
+    }
+
+    #[test]
+    fn events_between_reports_every_frame_reached() {
+        let sheet = sheet();
+        let clip = sheet.clip("loop");
+        assert_eq!(clip.events_between(-1f32, 0.05f32), vec!["start"]);
+        assert!(clip.events_between(0.05f32, 0.06f32).is_empty());
+        assert_eq!(clip.events_between(0.05f32, 0.25f32), vec!["end"]);
+        // more than a round at once still reports each one
+        assert_eq!(
+            clip.events_between(0.05f32, 0.65f32),
+            vec!["end", "start", "end", "start"]
+        );
+    }
+
+    #[test]
+    fn frame_rects_outside_the_png_are_reported() {
+        let problems = problems(
+            r#"(
+                frame_rects: [(0.0, 0.0, 10.0, 10.0), (35.0, 0.0, 10.0, 10.0)],
+                clips: {"idle": (frames: [0], durations: [0.1])},
+            )"#,
+        );
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("frame 1"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn frame_count_and_frame_rects_together_are_reported() {
+        let problems = problems(
+            r#"(
+                frame_count: Some(4),
+                frame_rects: [(0.0, 0.0, 10.0, 10.0)],
+                clips: {},
+            )"#,
+        );
+        assert_eq!(problems, vec!["give either frame_count or frame_rects"]);
+    }
+
+    #[test]
+    fn bad_durations_are_reported() {
+        let problems = problems(
+            r#"(
+                frame_count: Some(4),
+                clips: {
+                    "count": (frames: [0, 1, 2], durations: [0.1, 0.1]),
+                    "zero": (frames: [0, 1], durations: [0.0]),
+                },
+            )"#,
+        );
+        assert_eq!(problems.len(), 2);
+        assert!(
+            problems
+                .iter()
+                .any(|problem| problem.starts_with("clip count")),
+            "{:?}",
+            problems
+        );
+        assert!(
+            problems
+                .iter()
+                .any(|problem| problem.starts_with("clip zero")),
+            "{:?}",
+            problems
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+use crate::{constants::*, resources::TextureSlot};
+
+// the part of the atlas one png ended up in, drawn with draw_region_ex like a texture of its own
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasRegion {
+    pub texture: Texture2D,
+    // in atlas pixels
+    pub rect: Rect,
+}
+
+impl AtlasRegion {
+    pub fn width(&self) -> f32 {
+        self.rect.w
+    }
+
+    pub fn height(&self) -> f32 {
+        self.rect.h
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.rect.size()
+    }
+}
+
+// draw_texture_ex for a region, params.source is in the region's own pixels (like sprite sheet
+// frames) and is moved to where the region is in the atlas
+pub fn draw_region_ex(
+    region: AtlasRegion,
+    x: f32,
+    y: f32,
+    color: Color,
+    params: DrawTextureParams,
+) {
+    let source = params
+        .source
+        .unwrap_or_else(|| Rect::new(0f32, 0f32, region.rect.w, region.rect.h));
+    draw_texture_ex(
+        region.texture,
+        x,
+        y,
+        color,
+        DrawTextureParams {
+            source: Some(Rect::new(
+                region.rect.x + source.x,
+                region.rect.y + source.y,
+                source.w,
+                source.h,
+            )),
+            ..params
+        },
+    );
+}
+
+// every sprite png packed into one texture, so drawing them doesn't switch textures.
+// The images are kept so a single png can be swapped when it's hot reloaded
+pub struct Atlas {
+    pub texture: Texture2D,
+    regions: HashMap<TextureSlot, Rect>,
+    images: Vec<(TextureSlot, Image)>,
+}
+
+impl Atlas {
+    pub fn new(images: Vec<(TextureSlot, Image)>) -> Self {
+        let (atlas_image, regions) = pack(&images);
+        let texture = Texture2D::from_image(&atlas_image);
+        texture.set_filter(FilterMode::Nearest);
+        Atlas {
+            texture,
+            regions,
+            images,
+        }
+    }
+
+    pub fn region(&self, slot: TextureSlot) -> Option<AtlasRegion> {
+        self.regions.get(&slot).map(|rect| AtlasRegion {
+            texture: self.texture,
+            rect: *rect,
+        })
+    }
+
+    pub fn slots(&self) -> Vec<TextureSlot> {
+        self.images.iter().map(|(slot, _)| *slot).collect()
+    }
+
+    // packs everything again with the new image, the old texture is freed.
+    // Regions handed out before point into the old texture, get them again
+    pub fn replace_image(&mut self, slot: TextureSlot, image: Image) {
+        swap_image(&mut self.images, slot, image);
+        let old_texture = self.texture;
+        *self = Atlas::new(std::mem::take(&mut self.images));
+        old_texture.delete();
+    }
+}
+
+// the slot's image is replaced, a slot that had none gets it added
+fn swap_image(images: &mut Vec<(TextureSlot, Image)>, slot: TextureSlot, image: Image) {
+    match images
+        .iter_mut()
+        .find(|(image_slot, _)| *image_slot == slot)
+    {
+        Some((_, old_image)) => *old_image = image,
+        None => images.push((slot, image)),
+    }
+}
+
+// shelf packing: tallest images first, left to right in rows as high as their first image.
+// Every image gets its edge pixels repeated around it so neighbours don't bleed in when scaled
+fn pack(images: &[(TextureSlot, Image)]) -> (Image, HashMap<TextureSlot, Rect>) {
+    let padding = ATLAS_PADDING;
+    let mut order: Vec<&(TextureSlot, Image)> = images.iter().collect();
+    order.sort_by(|(_, a), (_, b)| b.height().cmp(&a.height()).then(b.width().cmp(&a.width())));
+
+    // powers of two for the GPUs and browsers that still want them
+    let widest = order
+        .iter()
+        .map(|(_, image)| image.width() + padding * 2)
+        .max()
+        .unwrap_or(0);
+    let atlas_width = widest.max(ATLAS_WIDTH_MIN).next_power_of_two();
+
+    let mut positions = Vec::new();
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for (slot, image) in order.iter() {
+        let padded_width = image.width() + padding * 2;
+        if x + padded_width > atlas_width {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+        positions.push((*slot, image, x + padding, y + padding));
+        x += padded_width;
+        shelf_height = shelf_height.max(image.height() + padding * 2);
+    }
+    let atlas_height = (y + shelf_height).max(1).next_power_of_two();
+
+    let mut atlas_image = Image::gen_image_color(
+        atlas_width as u16,
+        atlas_height as u16,
+        Color::new(0f32, 0f32, 0f32, 0f32),
+    );
+    let mut regions = HashMap::new();
+    for (slot, image, x, y) in positions {
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        for image_y in -(padding as i32)..height + padding as i32 {
+            for image_x in -(padding as i32)..width + padding as i32 {
+                let color = image.get_pixel(
+                    image_x.max(0).min(width - 1) as u32,
+                    image_y.max(0).min(height - 1) as u32,
+                );
+                atlas_image.set_pixel(
+                    (x as i32 + image_x) as u32,
+                    (y as i32 + image_y) as u32,
+                    color,
+                );
+            }
+        }
+        regions.insert(
+            slot,
+            Rect::new(x as f32, y as f32, width as f32, height as f32),
+        );
+    }
+    (atlas_image, regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u16, height: u16) -> Image {
+        Image::gen_image_color(width, height, Color::from_rgba(10, 20, 30, 255))
+    }
+
+    // the region grown by the padding, what no other region may reach into
+    fn padded(rect: &Rect) -> Rect {
+        let padding = ATLAS_PADDING as f32;
+        Rect::new(
+            rect.x - padding,
+            rect.y - padding,
+            rect.w + padding * 2f32,
+            rect.h + padding * 2f32,
+        )
+    }
+
+    fn overlap(a: &Rect, b: &Rect) -> bool {
+        a.x < b.right() && b.x < a.right() && a.y < b.bottom() && b.y < a.bottom()
+    }
+
+    fn sprites() -> Vec<(TextureSlot, Image)> {
+        vec![
+            (TextureSlot::Player, image(16, 8)),
+            (TextureSlot::PlayerExplotion, image(64, 8)),
+            (TextureSlot::PlayerMissile, image(1, 4)),
+            (TextureSlot::DemonMissile, image(2, 2)),
+            (TextureSlot::GroundBg, image(200, 24)),
+            (TextureSlot::Life, image(5, 5)),
+            (TextureSlot::Pickups, image(54, 9)),
+            (TextureSlot::Border, image(120, 90)),
+        ]
+    }
+
+    #[test]
+    fn regions_dont_overlap() {
+        let images = sprites();
+        let (atlas_image, regions) = pack(&images);
+        assert_eq!(regions.len(), images.len());
+        let atlas_rect = Rect::new(
+            0f32,
+            0f32,
+            atlas_image.width() as f32,
+            atlas_image.height() as f32,
+        );
+        for (slot, image) in images.iter() {
+            let rect = regions[slot];
+            assert_eq!(
+                rect.size(),
+                vec2(image.width() as f32, image.height() as f32)
+            );
+            let padded_rect = padded(&rect);
+            assert!(
+                padded_rect.x >= 0f32
+                    && padded_rect.y >= 0f32
+                    && padded_rect.right() <= atlas_rect.right()
+                    && padded_rect.bottom() <= atlas_rect.bottom(),
+                "a region is outside the atlas"
+            );
+            for (_, other_rect) in regions.iter().filter(|(other, _)| *other != slot) {
+                assert!(!overlap(&padded_rect, &padded(other_rect)));
+            }
+        }
+    }
+
+    #[test]
+    fn padding_repeats_the_edge_pixels() {
+        let mut image = image(2, 2);
+        image.set_pixel(0, 0, Color::from_rgba(255, 0, 0, 255));
+        image.set_pixel(1, 0, Color::from_rgba(0, 255, 0, 255));
+        image.set_pixel(0, 1, Color::from_rgba(0, 0, 255, 255));
+        image.set_pixel(1, 1, Color::from_rgba(255, 255, 255, 255));
+        let (atlas_image, regions) = pack(&[(TextureSlot::Life, image.clone())]);
+        let rect = regions[&TextureSlot::Life];
+        let (x, y) = (rect.x as u32, rect.y as u32);
+        let padding = ATLAS_PADDING as u32;
+        assert_eq!(atlas_image.get_pixel(x, y), image.get_pixel(0, 0));
+        // corners, above and to the sides
+        assert_eq!(
+            atlas_image.get_pixel(x - padding, y - padding),
+            image.get_pixel(0, 0)
+        );
+        assert_eq!(
+            atlas_image.get_pixel(x + 1 + padding, y - padding),
+            image.get_pixel(1, 0)
+        );
+        assert_eq!(
+            atlas_image.get_pixel(x - padding, y + 1),
+            image.get_pixel(0, 1)
+        );
+        assert_eq!(
+            atlas_image.get_pixel(x + 1 + padding, y + 1 + padding),
+            image.get_pixel(1, 1)
+        );
+    }
+
+    #[test]
+    fn atlas_is_at_least_the_minimum_width() {
+        let (atlas_image, _) = pack(&[(TextureSlot::Life, image(5, 5))]);
+        assert_eq!(atlas_image.width(), ATLAS_WIDTH_MIN.next_power_of_two());
+        assert!(atlas_image.height().is_power_of_two());
+    }
+
+    #[test]
+    fn wide_image_grows_the_atlas() {
+        let width = ATLAS_WIDTH_MIN as u16 + 44;
+        let (atlas_image, regions) = pack(&[
+            (TextureSlot::GroundBg, image(width, 24)),
+            (TextureSlot::Life, image(5, 5)),
+        ]);
+        assert_eq!(
+            atlas_image.width(),
+            (width as usize + ATLAS_PADDING * 2).next_power_of_two()
+        );
+        assert_eq!(regions[&TextureSlot::GroundBg].w, width as f32);
+    }
+
+    #[test]
+    fn swapped_image_is_packed_instead_of_the_old_one() {
+        let mut images = sprites();
+        swap_image(&mut images, TextureSlot::Life, image(40, 30));
+        swap_image(&mut images, TextureSlot::DemonCracks, image(12, 12));
+        assert_eq!(images.len(), sprites().len() + 1);
+        let (_, regions) = pack(&images);
+        assert_eq!(regions[&TextureSlot::Life].size(), vec2(40f32, 30f32));
+        assert_eq!(
+            regions[&TextureSlot::DemonCracks].size(),
+            vec2(12f32, 12f32)
+        );
+        for (slot, rect) in regions.iter() {
+            for (_, other_rect) in regions.iter().filter(|(other, _)| *other != slot) {
+                assert!(!overlap(&padded(rect), &padded(other_rect)));
+            }
+        }
+    }
+}
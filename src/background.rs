@@ -0,0 +1,261 @@
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::constants::*;
+
+// the colors of one look of the background, the game moves to the next one every wave
+#[derive(Clone, Copy)]
+pub struct BackgroundPalette {
+    pub sky_top: Color,
+    // the sky right above the horizon
+    pub sky_bottom: Color,
+    pub stars: Color,
+    pub planet: Color,
+    pub hills_far: Color,
+    pub hills_near: Color,
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::new(
+        r as f32 / 255f32,
+        g as f32 / 255f32,
+        b as f32 / 255f32,
+        1f32,
+    )
+}
+
+// wave 1 gets the first, after the last it starts over, like the colors changing on the Atari
+pub const BACKGROUND_PALETTES: &[BackgroundPalette] = &[
+    // night
+    BackgroundPalette {
+        sky_top: rgb(0, 0, 0),
+        sky_bottom: rgb(16, 12, 40),
+        stars: rgb(220, 220, 255),
+        planet: rgb(40, 34, 80),
+        hills_far: rgb(28, 22, 58),
+        hills_near: rgb(18, 14, 38),
+    },
+    // dusk
+    BackgroundPalette {
+        sky_top: rgb(10, 0, 24),
+        sky_bottom: rgb(72, 20, 60),
+        stars: rgb(255, 200, 220),
+        planet: rgb(110, 40, 70),
+        hills_far: rgb(60, 18, 50),
+        hills_near: rgb(36, 10, 32),
+    },
+    // toxic
+    BackgroundPalette {
+        sky_top: rgb(0, 8, 4),
+        sky_bottom: rgb(12, 48, 28),
+        stars: rgb(200, 255, 210),
+        planet: rgb(40, 90, 50),
+        hills_far: rgb(16, 52, 30),
+        hills_near: rgb(8, 32, 18),
+    },
+    // ember
+    BackgroundPalette {
+        sky_top: rgb(12, 0, 0),
+        sky_bottom: rgb(70, 24, 8),
+        stars: rgb(255, 220, 160),
+        planet: rgb(120, 50, 20),
+        hills_far: rgb(64, 24, 10),
+        hills_near: rgb(38, 14, 6),
+    },
+    // ice
+    BackgroundPalette {
+        sky_top: rgb(0, 4, 16),
+        sky_bottom: rgb(20, 50, 80),
+        stars: rgb(220, 250, 255),
+        planet: rgb(60, 100, 130),
+        hills_far: rgb(26, 56, 84),
+        hills_near: rgb(14, 34, 54),
+    },
+];
+
+fn palette_for_wave(wave: i32) -> BackgroundPalette {
+    let index = (wave - 1).max(0) as usize % BACKGROUND_PALETTES.len();
+    BACKGROUND_PALETTES[index]
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Color::new(
+        a.r + (b.r - a.r) * t,
+        a.g + (b.g - a.g) * t,
+        a.b + (b.b - a.b) * t,
+        a.a + (b.a - a.a) * t,
+    )
+}
+
+struct Star {
+    pos: Vec2,
+    // 0 is the far layer, the last is the nearest and fastest
+    layer: usize,
+    // so they don't all twinkle together
+    twinkle_phase: f32,
+}
+
+// the height of a ridge of hills for every column of the game, repeats every GAME_SIZE_X pixels
+// so it can scroll forever
+fn make_ridge(max_height: f32) -> Vec<f32> {
+    // whole cycles over the width, so both ends meet
+    let waves: Vec<(f32, f32, f32)> = (1..=3)
+        .map(|i| {
+            let cycles = gen_range(1, 4) * i;
+            let amplitude = max_height / (i as f32 * 2f32);
+            let phase = gen_range(0f32, std::f32::consts::PI * 2f32);
+            (cycles as f32, amplitude, phase)
+        })
+        .collect();
+    (0..GAME_SIZE_X)
+        .map(|x| {
+            let t = x as f32 / GAME_SIZE_X as f32 * std::f32::consts::PI * 2f32;
+            let height: f32 = waves
+                .iter()
+                .map(|(cycles, amplitude, phase)| (t * cycles + phase).sin() * amplitude)
+                .sum();
+            (max_height * 0.5f32 + height).max(1f32).round()
+        })
+        .collect()
+}
+
+// what's behind the demons: sky, twinkling stars scrolling in layers, a planet on the horizon and
+// two ridges of hills. Purely cosmetic like the particles, so it lives outside the world
+// and its randomness is macroquad's
+pub struct Background {
+    stars: Vec<Star>,
+    ridge_far: Vec<f32>,
+    ridge_near: Vec<f32>,
+    time: f32,
+    // the wave the palette is going to
+    wave: i32,
+    // the palette it comes from and how far along the change is, 0 -> 1
+    previous_palette: BackgroundPalette,
+    palette_blend: f32,
+}
+
+impl Background {
+    pub fn new() -> Self {
+        let stars = (0..BACKGROUND_STAR_COUNT)
+            .map(|i| Star {
+                pos: vec2(
+                    gen_range(0f32, GAME_SIZE_X as f32),
+                    gen_range(0f32, BACKGROUND_HORIZON_Y),
+                ),
+                layer: i % BACKGROUND_STAR_LAYER_SPEEDS.len(),
+                twinkle_phase: gen_range(0f32, std::f32::consts::PI * 2f32),
+            })
+            .collect();
+        Background {
+            stars,
+            ridge_far: make_ridge(BACKGROUND_RIDGE_FAR_HEIGHT),
+            ridge_near: make_ridge(BACKGROUND_RIDGE_NEAR_HEIGHT),
+            time: 0f32,
+            wave: 1,
+            previous_palette: palette_for_wave(1),
+            palette_blend: 1f32,
+        }
+    }
+
+    // a new game starts back on the first palette right away
+    pub fn reset(&mut self) {
+        self.wave = 1;
+        self.previous_palette = palette_for_wave(1);
+        self.palette_blend = 1f32;
+    }
+
+    pub fn update(&mut self, dt: f32, wave: i32) {
+        self.time += dt;
+        if wave != self.wave {
+            self.previous_palette = self.palette();
+            self.palette_blend = 0f32;
+            self.wave = wave;
+        }
+        self.palette_blend = (self.palette_blend + dt / BACKGROUND_PALETTE_BLEND_TIME).min(1f32);
+    }
+
+    fn palette(&self) -> BackgroundPalette {
+        let from = self.previous_palette;
+        let to = palette_for_wave(self.wave);
+        let t = self.palette_blend;
+        BackgroundPalette {
+            sky_top: lerp_color(from.sky_top, to.sky_top, t),
+            sky_bottom: lerp_color(from.sky_bottom, to.sky_bottom, t),
+            stars: lerp_color(from.stars, to.stars, t),
+            planet: lerp_color(from.planet, to.planet, t),
+            hills_far: lerp_color(from.hills_far, to.hills_far, t),
+            hills_near: lerp_color(from.hills_near, to.hills_near, t),
+        }
+    }
+
+    // game pixels scrolled by something moving at speed, wrapped to the game width
+    fn scroll(&self, speed: f32) -> f32 {
+        (self.time * speed).rem_euclid(GAME_SIZE_X as f32)
+    }
+
+    fn draw_ridge(&self, ridge: &[f32], speed: f32, color: Color) {
+        let scroll = self.scroll(speed) as usize;
+        for x in 0..GAME_SIZE_X as usize {
+            let height = ridge[(x + scroll) % ridge.len()];
+            draw_rectangle(x as f32, BACKGROUND_HORIZON_Y - height, 1f32, height, color);
+        }
+    }
+
+    // drawn first, everything else goes over it
+    pub fn draw(&self) {
+        let palette = self.palette();
+
+        // the sky in flat bands, a smooth gradient wouldn't fit the pixels
+        for band in 0..BACKGROUND_SKY_BANDS {
+            let t = band as f32 / (BACKGROUND_SKY_BANDS - 1) as f32;
+            let band_height = BACKGROUND_HORIZON_Y / BACKGROUND_SKY_BANDS as f32;
+            draw_rectangle(
+                0f32,
+                (band as f32 * band_height).floor(),
+                GAME_SIZE_X as f32,
+                band_height.ceil(),
+                lerp_color(palette.sky_top, palette.sky_bottom, t),
+            );
+        }
+
+        for star in self.stars.iter() {
+            let speed = BACKGROUND_STAR_LAYER_SPEEDS[star.layer];
+            let x = (star.pos.x - self.scroll(speed)).rem_euclid(GAME_SIZE_X as f32);
+            let twinkle = ((self.time * BACKGROUND_STAR_TWINKLE_SPEED + star.twinkle_phase).sin()
+                + 1f32)
+                * 0.5f32;
+            // far stars are dimmer
+            let layer_brightness =
+                (star.layer + 1) as f32 / BACKGROUND_STAR_LAYER_SPEEDS.len() as f32;
+            let mut color = palette.stars;
+            color.a = layer_brightness * (0.4f32 + twinkle * 0.6f32);
+            draw_rectangle(x.floor(), star.pos.y.floor(), 1f32, 1f32, color);
+        }
+
+        // only the top of it shows above the horizon
+        draw_poly(
+            GAME_CENTER_X + BACKGROUND_PLANET_OFFSET_X,
+            BACKGROUND_HORIZON_Y + BACKGROUND_PLANET_RADIUS - BACKGROUND_PLANET_VISIBLE_HEIGHT,
+            64,
+            BACKGROUND_PLANET_RADIUS,
+            0f32,
+            palette.planet,
+        );
+
+        self.draw_ridge(
+            &self.ridge_far,
+            BACKGROUND_RIDGE_FAR_SPEED,
+            palette.hills_far,
+        );
+        self.draw_ridge(
+            &self.ridge_near,
+            BACKGROUND_RIDGE_NEAR_SPEED,
+            palette.hills_near,
+        );
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,11 @@
 use macroquad::prelude::*;
 
-use crate::{constants::*, resources::Resources};
+use crate::{
+    atlas::draw_region_ex,
+    constants::*,
+    resources::{Resources, TextureSlot},
+    tuning::Tuning,
+};
 
 #[derive(std::cmp::PartialEq)]
 pub enum BulletHurtType {
@@ -9,37 +14,97 @@ pub enum BulletHurtType {
 }
 
 pub struct Bullet {
-    texture: Texture2D,
     pos: Vec2,
+    // position before the last simulation step, drawing interpolates between the two
+    prev_pos: Vec2,
     vel: Vec2,
     pub hurt_type: BulletHurtType,
+    // index of the player that shot it, so kills are credited to them. None for enemy bullets
+    pub owner_optional: Option<usize>,
     anim_timer: f32,
     pub collision_rect: Rect,
     pub is_kill: bool,
+    // flies through enemies instead of stopping at the first one
+    pub piercing: bool,
+    // ids of the enemies a piercing bullet already hurt, it doesn't hurt them again while flying through
+    pub hit_enemy_ids: Vec<u32>,
+    // how far right of its owner a tracer shot flies, taken from where it was fired
+    owner_offset_optional: Option<f32>,
 }
 
 impl Bullet {
-    pub fn new(pos: Vec2, hurt_type: BulletHurtType, resources: &Resources) -> Self {
-        let (vel, texture) = match hurt_type {
-            BulletHurtType::Enemy => (
-                vec2(0f32, -1f32 * PLAYER_BULLET_SPEED),
-                resources.player_missile,
-            ),
-            BulletHurtType::Player => (vec2(0f32, ENEMY_BULLET_SPEED), resources.demon_missile),
+    pub fn new(
+        pos: Vec2,
+        hurt_type: BulletHurtType,
+        owner_optional: Option<usize>,
+        tuning: &Tuning,
+    ) -> Self {
+        let vel = match hurt_type {
+            BulletHurtType::Enemy => vec2(0f32, -tuning.player_bullet_speed),
+            BulletHurtType::Player => vec2(0f32, tuning.enemy_bullet_speed),
         };
 
         Bullet {
             pos,
-            texture,
+            prev_pos: pos,
             vel,
             hurt_type,
+            owner_optional,
             anim_timer: 0f32,
             collision_rect: Rect::new(pos.x, pos.y, 2.0f32, 6f32),
             is_kill: false,
+            piercing: false,
+            hit_enemy_ids: Vec::new(),
+            owner_offset_optional: None,
         }
     }
 
+    // an enemy bullet flying along dir instead of straight down
+    pub fn new_aimed(pos: Vec2, dir: Vec2, tuning: &Tuning) -> Self {
+        let mut bullet = Self::new(pos, BulletHurtType::Player, None, tuning);
+        bullet.vel = dir.normalize_or_zero() * tuning.enemy_bullet_speed;
+        bullet
+    }
+
+    pub fn pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    // the way it's flying
+    pub fn dir(&self) -> Vec2 {
+        self.vel.normalize_or_zero()
+    }
+
+    // tracer shots, keeps the bullet at the same distance from its owner's cannon sideways
+    pub fn follow_owner(&mut self, owner_x: f32) {
+        let pos_x = self.pos.x;
+        let offset = *self.owner_offset_optional.get_or_insert(pos_x - owner_x);
+        self.pos.x = owner_x + offset;
+        self.collision_rect.x = self.pos.x;
+    }
+
+    // guided shots, turns at most max_turn radians toward the target while it's still below
+    pub fn steer_towards(&mut self, target: Vec2, max_turn: f32) {
+        let to_target = target - self.pos;
+        if to_target.y <= 0f32 || self.vel == Vec2::ZERO {
+            return;
+        }
+        let down = vec2(0f32, 1f32);
+        let angle = down.angle_between(self.vel);
+        let target_angle = down
+            .angle_between(to_target)
+            .clamp(-BULLET_GUIDED_MAX_ANGLE, BULLET_GUIDED_MAX_ANGLE);
+        let angle = angle + (target_angle - angle).clamp(-max_turn, max_turn);
+        self.vel = vec2(-angle.sin(), angle.cos()) * self.vel.length();
+    }
+
+    // fast demon shots variant
+    pub fn speed_up(&mut self, factor: f32) {
+        self.vel *= factor;
+    }
+
     pub fn update(&mut self, dt: f32) {
+        self.prev_pos = self.pos;
         self.pos += self.vel * dt;
         self.anim_timer += dt;
         self.collision_rect.x = self.pos.x;
@@ -50,21 +115,35 @@ impl Bullet {
         self.collision_rect.overlaps(other_rect)
     }
 
-    pub fn draw(&mut self) {
-        let frame = ((self.anim_timer / BULLET_ANIM_TIME_SPAWN) * 3.0f32) as i32;
-        draw_texture_ex(
-            self.texture,
-            self.pos.x,
-            self.pos.y,
-            WHITE,
+    pub fn draw(&self, resources: &Resources, interpolation: f32) {
+        let pos = self.prev_pos + (self.pos - self.prev_pos) * interpolation;
+        let (texture, slot) = match self.hurt_type {
+            BulletHurtType::Enemy => (resources.player_missile, TextureSlot::PlayerMissile),
+            BulletHurtType::Player => (resources.demon_missile, TextureSlot::DemonMissile),
+        };
+        let frame = resources
+            .sprite_sheet(slot)
+            .clip("spawn")
+            .frame_at(self.anim_timer);
+        // the sprites point the way the bullet normally flies, turn them to where it's going
+        let forward = match self.hurt_type {
+            BulletHurtType::Enemy => vec2(0f32, -1f32),
+            BulletHurtType::Player => vec2(0f32, 1f32),
+        };
+        let rotation = if self.vel == Vec2::ZERO {
+            0f32
+        } else {
+            forward.angle_between(self.vel)
+        };
+        let color = if self.piercing { ORANGE } else { WHITE };
+        draw_region_ex(
+            texture,
+            pos.x,
+            pos.y,
+            color,
             DrawTextureParams {
-                rotation: 0f32,
-                source: Some(Rect::new(
-                    self.texture.width() / 3f32 * frame as f32,
-                    0f32,
-                    self.texture.width() / 3f32,
-                    self.texture.height(),
-                )),
+                rotation,
+                source: Some(frame.rect),
                 ..Default::default()
             },
         );
@@ -1,5 +1,7 @@
 use macroquad::prelude::*;
 
+use crate::{gamepad::PadInput, pickup::PickupKind};
+
 //pub const GAME_SIZE_X: i32 = 160;
 pub const GAME_SIZE_X: i32 = 240;
 pub const GAME_SIZE_Y: i32 = 130;
@@ -7,13 +9,98 @@ pub const GAME_CENTER_X: f32 = GAME_SIZE_X as f32 * 0.5f32;
 pub const GAME_CENTER_Y: f32 = GAME_SIZE_Y as f32 * 0.5f32;
 pub const _ASPECT_RATIO: f32 = GAME_SIZE_X as f32 / GAME_SIZE_Y as f32;
 
-pub const KEY_RIGHT: KeyCode = KeyCode::Right;
-pub const KEY_LEFT: KeyCode = KeyCode::Left;
-pub const KEY_SHOOT: KeyCode = KeyCode::Space;
-pub const KEY_START_GAME: KeyCode = KeyCode::Space;
+// the simulation always steps this many times per second, no matter the frame rate
+pub const SIMULATION_TICK_RATE: f32 = 120f32;
+pub const SIMULATION_DT: f32 = 1f32 / SIMULATION_TICK_RATE;
+// after a long hitch (like a dragged window) we drop time instead of catching up with more ticks than this
+pub const SIMULATION_MAX_TICKS_PER_FRAME: i32 = 30;
+
+// default bindings for the actions in input.rs, players can rebind them from the controls screen
+pub const DEFAULT_KEYS_MOVE_LEFT: &[KeyCode] = &[KeyCode::Left, KeyCode::A];
+pub const DEFAULT_KEYS_MOVE_RIGHT: &[KeyCode] = &[KeyCode::Right, KeyCode::D];
+pub const DEFAULT_KEYS_FIRE: &[KeyCode] = &[KeyCode::Space, KeyCode::W];
+pub const DEFAULT_KEYS_START: &[KeyCode] = &[KeyCode::Space, KeyCode::Enter];
+pub const DEFAULT_KEYS_PAUSE: &[KeyCode] = &[KeyCode::Escape, KeyCode::P];
+// player two's own keys, so both players fit on one keyboard. Its pad bindings are the same as player one's
+pub const DEFAULT_KEYS_PLAYER_TWO_MOVE_LEFT: &[KeyCode] = &[KeyCode::J];
+pub const DEFAULT_KEYS_PLAYER_TWO_MOVE_RIGHT: &[KeyCode] = &[KeyCode::L];
+pub const DEFAULT_KEYS_PLAYER_TWO_FIRE: &[KeyCode] = &[KeyCode::I];
+pub const DEFAULT_KEYS_PLAYER_TWO_START: &[KeyCode] = &[KeyCode::Key2];
+pub const DEFAULT_KEYS_PLAYER_TWO_PAUSE: &[KeyCode] = &[];
+pub const DEFAULT_PADS_MOVE_LEFT: &[PadInput] = &[PadInput::DPadLeft, PadInput::LeftStickLeft];
+pub const DEFAULT_PADS_MOVE_RIGHT: &[PadInput] = &[PadInput::DPadRight, PadInput::LeftStickRight];
+pub const DEFAULT_PADS_FIRE: &[PadInput] = &[
+    PadInput::South,
+    PadInput::East,
+    PadInput::West,
+    PadInput::North,
+];
+pub const DEFAULT_PADS_START: &[PadInput] = &[PadInput::Start];
+pub const DEFAULT_PADS_PAUSE: &[PadInput] = &[PadInput::Start];
+
+// how far (0 to 1) a stick has to move before it counts at all
+pub const GAMEPAD_DEADZONE: f32 = 0.25f32;
+// how far a button or stick has to be pushed to count as pressed
+pub const GAMEPAD_PRESS_THRESHOLD: f32 = 0.5f32;
+
+// on screen buttons for touch screens, in game pixels
+pub const TOUCH_BUTTON_SIZE: f32 = 24f32;
+pub const TOUCH_BUTTON_MARGIN: f32 = 4f32;
+
+// keys below can't be rebound, so the menus always work
+pub const KEY_WATCH_REPLAY: KeyCode = KeyCode::R;
+pub const KEY_REPLAY_PAUSE: KeyCode = KeyCode::P;
+pub const KEY_REPLAY_FAST_FORWARD: KeyCode = KeyCode::F;
+// step a single tick while paused
+pub const KEY_REPLAY_STEP: KeyCode = KeyCode::Period;
+pub const KEY_REPLAY_EXIT: KeyCode = KeyCode::Escape;
+pub const KEY_HIGH_SCORES: KeyCode = KeyCode::H;
+pub const KEY_CONTROLS: KeyCode = KeyCode::C;
+// opens the game variants screen from the menu
+pub const KEY_VARIANTS: KeyCode = KeyCode::V;
+pub const KEY_MENU_UP: KeyCode = KeyCode::Up;
+pub const KEY_MENU_DOWN: KeyCode = KeyCode::Down;
+pub const KEY_MENU_LEFT: KeyCode = KeyCode::Left;
+pub const KEY_MENU_RIGHT: KeyCode = KeyCode::Right;
+pub const KEY_MENU_CONFIRM: KeyCode = KeyCode::Enter;
+pub const KEY_MENU_BACK: KeyCode = KeyCode::Escape;
+// switches between the players on the controls screen
+pub const KEY_CONTROLS_PLAYER_PREVIOUS: KeyCode = KeyCode::Left;
+pub const KEY_CONTROLS_PLAYER_NEXT: KeyCode = KeyCode::Right;
+// removes every binding of the selected action on the controls screen
+pub const KEY_CONTROLS_CLEAR: KeyCode = KeyCode::Backspace;
+// arcade style name entry, up/down picks the letter, left/right moves between letters
+pub const KEY_NAME_LETTER_NEXT: KeyCode = KeyCode::Up;
+pub const KEY_NAME_LETTER_PREVIOUS: KeyCode = KeyCode::Down;
+pub const KEY_NAME_CURSOR_LEFT: KeyCode = KeyCode::Left;
+pub const KEY_NAME_CURSOR_RIGHT: KeyCode = KeyCode::Right;
+pub const KEY_NAME_CONFIRM: KeyCode = KeyCode::Enter;
+// gamepads navigate every menu the same way
+pub const PAD_MENU_UP: &[PadInput] = &[PadInput::DPadUp, PadInput::LeftStickUp];
+pub const PAD_MENU_DOWN: &[PadInput] = &[PadInput::DPadDown, PadInput::LeftStickDown];
+pub const PAD_MENU_LEFT: &[PadInput] = &[PadInput::DPadLeft, PadInput::LeftStickLeft];
+pub const PAD_MENU_RIGHT: &[PadInput] = &[PadInput::DPadRight, PadInput::LeftStickRight];
+pub const PAD_MENU_CONFIRM: &[PadInput] = &[PadInput::South];
+pub const PAD_MENU_BACK: &[PadInput] = &[PadInput::East];
+pub const PAD_CONTROLS_CLEAR: &[PadInput] = &[PadInput::West];
+
+pub const REPLAY_FAST_FORWARD_SPEED: f32 = 4f32;
+// finished games are saved here
+pub const REPLAY_DIRECTORY: &str = "replays";
+// high scores and settings, on wasm this is the localStorage key prefix instead
+pub const SAVE_DIRECTORY: &str = "save";
+
+pub const HIGH_SCORE_COUNT: usize = 10;
+pub const HIGH_SCORE_NAME_LENGTH: usize = 3;
 
+// players are tinted in this order, player one keeps the sprite's own colors
+pub const PLAYER_TINTS: &[Color] = &[WHITE, SKYBLUE];
+pub const PLAYER_COUNT_MAX: usize = 2;
+
+// Balance values below are the defaults, the game loads the actual values from resources/tuning.ron
 pub const SCORE_NORMAL: i32 = 100;
 pub const SCORE_MINI: i32 = 20;
+pub const SCORE_BOSS: i32 = 5000;
 
 pub const SCORE_KILL_ALL: i32 = 1000;
 pub const SCORE_SURVIVED_ALL: i32 = 750;
@@ -25,23 +112,40 @@ pub const PLAYER_LIVES_START: i32 = 3i32;
 pub const PLAYER_LIVES_MAX: i32 = 7i32;
 pub const PLAYER_TIME_INVISBLE: f32 = 2f32;
 
+// game variants
+pub const VARIANT_FAST_DEMON_SHOTS_FACTOR: f32 = 1.5f32;
+// how long the campaign variant is, like the original cartridge
+pub const CAMPAIGN_WAVE_COUNT: i32 = 84;
+
 pub const ENEMY_SPEED: f32 = 50.0f32;
 pub const ENEMY_ANGLE_SPEED_RANGE: Vec2 = const_vec2!([0.2f32, 3f32]);
 
 pub const ENEMY_SPEED_HOMING: Vec2 = const_vec2!([60f32, 30f32]);
 pub const ENEMY_BULLET_SPEED: f32 = 80f32;
 pub const ENEMY_SHOOT_TIME: f32 = 2f32;
+// radians per second a guided demon missile turns toward the player
+pub const ENEMY_BULLET_GUIDED_TURN_RATE: f32 = 1.5f32;
+// guided demon missiles never turn further than this (radians) from straight down
+pub const BULLET_GUIDED_MAX_ANGLE: f32 = 0.9f32;
 // when shooting more than 1 bullet
 pub const ENEMY_SHOOT_BURST_TIME: f32 = 0.2f32;
 pub const ENEMY_MAX_BURST_COUNT: i32 = 5;
 pub const ENEMY_ANIM_TIME_SPAWN: f32 = 0.7f32;
 pub const ENEMY_MINI_ANIM_TIME_SPAWN: f32 = 0.3f32;
-pub const ENEMY_ANIM_TIME_FLAP: f32 = 0.12f32;
 pub const ENEMY_ANIM_SPAWN_SCALE: f32 = 4.0f32;
+// seconds between the warning beeps of a demon homing in
+pub const ENEMY_HOMING_WARNING_TIME: f32 = 0.48f32;
 // how far away the spawn animation starts
 pub const ENEMY_ANIM_DISTANCE: f32 = 140f32;
+// how long an enemy flashes white after a hit
+pub const ENEMY_HIT_FLASH_TIME: f32 = 0.08f32;
+// armored demons are darker, like they're made of iron
+pub const ENEMY_ARMORED_TINT: Color = Color::new(0.7f32, 0.7f32, 0.8f32, 1f32);
 // The min to max time until a mini will start homing
 pub const ENEMY_MINI_HOMING_TIME_RANGE: Vec2 = const_vec2!([4f32, 10f32]);
+// how often (0 to 1) a generated demon is armored, and how many hits it takes
+pub const ENEMY_ARMORED_CHANCE: f32 = 0.15f32;
+pub const ENEMY_ARMORED_HEALTH: i32 = 3;
 
 // Enemy Spawn management
 pub const ENEMY_SPAWN_STARTING_COUNT: i32 = 2;
@@ -50,4 +154,118 @@ pub const TIME_UNTIL_MAX_DIFFICULTY: f32 = 70f32;
 // spawn every x sec
 pub const ENEMY_SPAWN_TIME: f32 = 0.5f32;
 
+// Boss
+// every x waves is a boss instead of the usual demons, 0 never spawns one
+pub const BOSS_WAVE_INTERVAL: i32 = 5;
+pub const BOSS_HEALTH: i32 = 30;
+// sideways, while fighting
+pub const BOSS_SPEED: f32 = 30f32;
+// time between attacks
+pub const BOSS_ATTACK_TIME: f32 = 1.6f32;
+pub const BOSS_SPREAD_COUNT: i32 = 5;
+pub const BOSS_SUMMON_COUNT: i32 = 2;
+// where a boss hovers unless the wave file says otherwise
+pub const BOSS_POS: Vec2 = const_vec2!([GAME_CENTER_X, 12f32]);
+// how far below the top of the body the wings are attached
+pub const BOSS_WING_OFFSET_Y: f32 = 2f32;
+pub const BOSS_ANIM_TIME_INTRO: f32 = 2.5f32;
+pub const BOSS_ANIM_TIME_DEFEAT: f32 = 2f32;
+// an explosion sound every x sec while it's dying
+pub const BOSS_DEFEAT_BLAST_TIME: f32 = 0.25f32;
+// how wide the spread shot fans out, in radians
+pub const BOSS_SPREAD_ANGLE: f32 = 1.2f32;
+pub const BOSS_AIMED_BURST_COUNT: i32 = 3;
+// how long the mouth stays open after an attack
+pub const BOSS_ANIM_TIME_MOUTH: f32 = 0.3f32;
+
 pub const BULLET_ANIM_TIME_SPAWN: f32 = 0.3f32;
+
+// Pickups
+pub const PICKUP_FALL_SPEED: f32 = 25f32;
+pub const PICKUP_SIZE: f32 = 9f32;
+// the two bullets of a double shot are this far apart
+pub const DOUBLE_SHOT_SPACING: f32 = 4f32;
+// the power-up timers start blinking when they're about to run out
+pub const PICKUP_BLINK_TIME: f32 = 2f32;
+// how long the timed power-ups last
+pub const PICKUP_DURATION: f32 = 10f32;
+// shoot time is multiplied by this during rapid fire
+pub const PICKUP_RAPID_FIRE_FACTOR: f32 = 0.4f32;
+pub const PICKUP_SCORE_MULTIPLIER: i32 = 2;
+// chance (0 to 1) a kill drops something, then what drops by weight, per enemy color
+pub const DROP_CHANCE_PURPLE: f32 = 0.08f32;
+pub const DROPS_PURPLE: &[(PickupKind, f32)] = &[
+    (PickupKind::RapidFire, 3f32),
+    (PickupKind::DoubleShot, 3f32),
+    (PickupKind::ScoreMultiplier, 2f32),
+    (PickupKind::Shield, 1f32),
+];
+pub const DROP_CHANCE_GREEN: f32 = 0.08f32;
+pub const DROPS_GREEN: &[(PickupKind, f32)] = &[
+    (PickupKind::Piercing, 3f32),
+    (PickupKind::DoubleShot, 2f32),
+    (PickupKind::Shield, 2f32),
+    (PickupKind::ExtraLife, 1f32),
+];
+pub const DROP_CHANCE_RED: f32 = 0.12f32;
+pub const DROPS_RED: &[(PickupKind, f32)] = &[
+    (PickupKind::Shield, 2f32),
+    (PickupKind::ScoreMultiplier, 2f32),
+    (PickupKind::Piercing, 2f32),
+    (PickupKind::ExtraLife, 1f32),
+];
+
+// particles, speeds are (min, max) in pixels per second
+pub const PARTICLE_POOL_SIZE: usize = 512;
+pub const PARTICLE_GRAVITY: f32 = 90f32;
+// seconds, (min, max)
+pub const PARTICLE_LIFE_RANGE: Vec2 = const_vec2!([0.3f32, 0.8f32]);
+// debris per square pixel of the demon
+pub const PARTICLE_EXPLOSION_DENSITY: f32 = 0.25f32;
+pub const PARTICLE_EXPLOSION_MIN_COUNT: i32 = 8;
+pub const PARTICLE_EXPLOSION_SPEED: (f32, f32) = (10f32, 45f32);
+// white sparks in the middle of an explosion
+pub const PARTICLE_FLASH_COUNT: i32 = 6;
+pub const PARTICLE_SPARK_COUNT: i32 = 5;
+pub const PARTICLE_SPARK_SPEED: (f32, f32) = (20f32, 60f32);
+pub const PARTICLE_MUZZLE_FLASH_COUNT: i32 = 4;
+pub const PARTICLE_DUST_COUNT: i32 = 10;
+// (sideways, up)
+pub const PARTICLE_DUST_SPEED: Vec2 = const_vec2!([25f32, 30f32]);
+pub const PARTICLE_DUST_COLOR: Color = Color::new(0.6f32, 0.5f32, 0.4f32, 1f32);
+// background, speeds are in pixels per second to the left
+// where the sky ends and the hills stand, the top of the ground strip
+pub const BACKGROUND_HORIZON_Y: f32 = GAME_SIZE_Y as f32 - 24f32;
+pub const BACKGROUND_SKY_BANDS: i32 = 8;
+pub const BACKGROUND_STAR_COUNT: usize = 70;
+// a layer of stars per speed, far to near
+pub const BACKGROUND_STAR_LAYER_SPEEDS: &[f32] = &[1f32, 2.5f32, 5f32];
+// radians per second
+pub const BACKGROUND_STAR_TWINKLE_SPEED: f32 = 3f32;
+pub const BACKGROUND_PLANET_RADIUS: f32 = 160f32;
+pub const BACKGROUND_PLANET_VISIBLE_HEIGHT: f32 = 14f32;
+pub const BACKGROUND_PLANET_OFFSET_X: f32 = 40f32;
+pub const BACKGROUND_RIDGE_FAR_HEIGHT: f32 = 10f32;
+pub const BACKGROUND_RIDGE_FAR_SPEED: f32 = 3f32;
+pub const BACKGROUND_RIDGE_NEAR_HEIGHT: f32 = 6f32;
+pub const BACKGROUND_RIDGE_NEAR_SPEED: f32 = 7f32;
+// seconds the colors take to change to the next wave's
+pub const BACKGROUND_PALETTE_BLEND_TIME: f32 = 3f32;
+
+// screen shake, trauma is 0 -> 1 and wears off at SHAKE_DECAY per second
+pub const SHAKE_TRAUMA_PLAYER_HIT: f32 = 0.6f32;
+pub const SHAKE_TRAUMA_BOSS_DEFEATED: f32 = 1f32;
+pub const SHAKE_TRAUMA_WAVE_CLEARED: f32 = 0.35f32;
+pub const SHAKE_DECAY: f32 = 1.5f32;
+// game pixels at full trauma
+pub const SHAKE_MAX_OFFSET: f32 = 4f32;
+// seconds the game freezes on a kill
+pub const HIT_STOP_TIME_KILL: f32 = 0.05f32;
+pub const HIT_STOP_TIME_BOSS_DEFEATED: f32 = 0.25f32;
+// what the screen shake and hit-stop options cycle through, 0 is off
+pub const JUICE_INTENSITY_STEPS: &[f32] = &[0f32, 0.5f32, 1f32];
+
+// pixels of repeated edge around every sprite in the texture atlas
+pub const ATLAS_PADDING: usize = 1;
+// the atlas is at least this wide, rounded up to a power of two
+pub const ATLAS_WIDTH_MIN: usize = 256;
@@ -0,0 +1,136 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// how the game is drawn when it's scaled up to the window, picked in the options
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrtPreset {
+    // sharp pixels, no shader
+    #[default]
+    Off,
+    Scanlines,
+    Curvature,
+    PhosphorBloom,
+    ColorBleed,
+    Vignette,
+    // everything at once
+    Full,
+}
+
+pub const ALL_CRT_PRESETS: &[CrtPreset] = &[
+    CrtPreset::Off,
+    CrtPreset::Scanlines,
+    CrtPreset::Curvature,
+    CrtPreset::PhosphorBloom,
+    CrtPreset::ColorBleed,
+    CrtPreset::Vignette,
+    CrtPreset::Full,
+];
+
+// the uniforms of the CRT shader, 0 turns an effect off
+struct CrtStrengths {
+    scanlines: f32,
+    curvature: f32,
+    bloom: f32,
+    color_bleed: f32,
+    vignette: f32,
+}
+
+impl CrtPreset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrtPreset::Off => "OFF",
+            CrtPreset::Scanlines => "SCANLINES",
+            CrtPreset::Curvature => "CURVATURE",
+            CrtPreset::PhosphorBloom => "PHOSPHOR BLOOM",
+            CrtPreset::ColorBleed => "COLOR BLEED",
+            CrtPreset::Vignette => "VIGNETTE",
+            CrtPreset::Full => "FULL",
+        }
+    }
+
+    // the one after this in ALL_CRT_PRESETS, back to Off after the last
+    pub fn next(&self) -> Self {
+        let index = ALL_CRT_PRESETS
+            .iter()
+            .position(|preset| preset == self)
+            .unwrap_or(0);
+        ALL_CRT_PRESETS[(index + 1) % ALL_CRT_PRESETS.len()]
+    }
+
+    fn strengths(&self) -> CrtStrengths {
+        let none = CrtStrengths {
+            scanlines: 0f32,
+            curvature: 0f32,
+            bloom: 0f32,
+            color_bleed: 0f32,
+            vignette: 0f32,
+        };
+        match self {
+            CrtPreset::Off => none,
+            CrtPreset::Scanlines => CrtStrengths {
+                scanlines: 0.45f32,
+                ..none
+            },
+            CrtPreset::Curvature => CrtStrengths {
+                curvature: 1f32,
+                vignette: 0.3f32,
+                ..none
+            },
+            CrtPreset::PhosphorBloom => CrtStrengths {
+                scanlines: 0.2f32,
+                bloom: 0.6f32,
+                ..none
+            },
+            CrtPreset::ColorBleed => CrtStrengths {
+                color_bleed: 0.6f32,
+                ..none
+            },
+            CrtPreset::Vignette => CrtStrengths {
+                vignette: 0.8f32,
+                ..none
+            },
+            CrtPreset::Full => CrtStrengths {
+                scanlines: 0.35f32,
+                curvature: 1f32,
+                bloom: 0.4f32,
+                color_bleed: 0.4f32,
+                vignette: 0.5f32,
+            },
+        }
+    }
+}
+
+// draws the game render target scaled to dest_size. With a preset and a CRT material that
+// compiled it goes through the shader, otherwise it's the plain blit
+pub fn draw_upscaled(
+    texture: Texture2D,
+    pos: Vec2,
+    dest_size: Vec2,
+    preset: CrtPreset,
+    crt_material_optional: Option<Material>,
+) {
+    let material_optional = crt_material_optional.filter(|_| preset != CrtPreset::Off);
+    if let Some(material) = material_optional {
+        let strengths = preset.strengths();
+        material.set_uniform("TextureSize", vec2(texture.width(), texture.height()));
+        material.set_uniform("Scanlines", strengths.scanlines);
+        material.set_uniform("Curvature", strengths.curvature);
+        material.set_uniform("Bloom", strengths.bloom);
+        material.set_uniform("ColorBleed", strengths.color_bleed);
+        material.set_uniform("Vignette", strengths.vignette);
+        gl_use_material(material);
+    }
+    draw_texture_ex(
+        texture,
+        pos.x,
+        pos.y,
+        WHITE,
+        DrawTextureParams {
+            dest_size: Some(dest_size),
+            ..Default::default()
+        },
+    );
+    if material_optional.is_some() {
+        gl_use_default_material();
+    }
+}
@@ -1,11 +1,17 @@
 use macroquad::prelude::*;
-use quad_snd::mixer::{SoundMixer, Volume};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    animation::SpriteSheet,
+    atlas::{draw_region_ex, AtlasRegion},
     bullet::{Bullet, BulletHurtType},
     constants::*,
-    resources::{Resources, SoundIdentifier},
+    resources::{Resources, SoundIdentifier, TextureSlot},
+    rng::GameRng,
+    tuning::Tuning,
+    variant_eq,
     wave::{LastEnemyDeathReason, WaveManager},
+    world::{SoundQueue, SpriteMetrics},
 };
 
 #[derive(PartialEq)]
@@ -14,9 +20,12 @@ pub enum EnemyState {
     Normal(EnemyStateNormal),
     Shooting(EnemyStateShooting),
     Homing(EnemyStateHoming),
+    BossIntro(EnemyStateBossIntro),
+    BossFighting(EnemyStateBossFighting),
+    BossDefeat(EnemyStateBossDefeat),
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum EnemyDeathMethod {
     None,
     // count
@@ -27,13 +36,20 @@ pub enum EnemyCommand {
     ChangeState(EnemyState),
 }
 
-#[derive(Clone, Copy)]
+// used to get information from an enemy
+pub enum EnemyMessage {
+    // position, count and color of the minis a boss summons
+    SummonMinis(Vec2, i32, EnemyColor),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EnemyType {
     Normal,
     Mini,
+    Boss,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EnemyColor {
     Purple,
     Green,
@@ -41,20 +57,37 @@ pub enum EnemyColor {
 }
 
 impl EnemyColor {
-    pub fn random() -> Self {
+    pub fn random(rng: &mut GameRng) -> Self {
         use EnemyColor::*;
         let all = [Purple, Green, Red];
-        all[rand::gen_range(0, all.len())]
+        all[rng.gen_range(0, all.len())]
+    }
+
+    // roughly the color of the sprites, for debris
+    pub fn particle_color(&self) -> Color {
+        match self {
+            EnemyColor::Purple => Color::new(0.62f32, 0.35f32, 0.88f32, 1f32),
+            EnemyColor::Green => Color::new(0.4f32, 0.8f32, 0.32f32, 1f32),
+            EnemyColor::Red => Color::new(0.9f32, 0.32f32, 0.3f32, 1f32),
+        }
     }
 }
 
 pub struct EnemyStateShared {
-    texture: Texture2D,
+    // index into the sprite variants of this enemy type and color
+    sprite_variant: usize,
+    size: Vec2,
     pub pos: Vec2,
+    // position before the last simulation step, drawing interpolates between the two
+    prev_pos: Vec2,
     angle: f32,
     angle_speed: f32,
     collision_rect: Rect,
     pub health: i32,
+    // the health it spawned with
+    pub max_health: i32,
+    // counts down after a hit, it's drawn white until then
+    hit_flash_timer: f32,
     pub death_method: EnemyDeathMethod,
     animation_timer: f32,
     pub enemy_type: EnemyType,
@@ -83,77 +116,165 @@ pub struct EnemyStateSpawning {
     spawn_timer: f32,
 }
 
+// a boss flies in from above the screen to where it was spawned
+#[derive(PartialEq)]
+pub struct EnemyStateBossIntro {
+    timer: f32,
+    target_pos: Vec2,
+}
+
+#[derive(PartialEq)]
+pub struct EnemyStateBossFighting {
+    // 1 or -1, it bounces between the sides of the screen
+    move_dir: f32,
+    attack_timer: f32,
+    attack_count: i32,
+    // aimed shots left in the current burst
+    shots_left: i32,
+    shot_timer: f32,
+    mouth_timer: f32,
+}
+
+#[derive(PartialEq)]
+pub struct EnemyStateBossDefeat {
+    timer: f32,
+    blast_timer: f32,
+}
+
+// what a boss attacks with, it gets nastier as it loses health
+enum BossPhase {
+    Spread,
+    Aimed,
+    Summon,
+}
+
 pub struct Enemy {
+    // handed out by the world when it first sees the enemy, unique for the game
+    pub id_optional: Option<u32>,
     pub state_shared: EnemyStateShared,
     pub state: EnemyState,
 }
 
 impl Enemy {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         pos: Vec2,
-        texture: Texture2D,
+        sprite_metrics: &SpriteMetrics,
         health: i32,
         death_method: EnemyDeathMethod,
         enemy_type: EnemyType,
         enemy_color: EnemyColor,
+        rng: &mut GameRng,
+        tuning: &Tuning,
     ) -> Self {
+        // pick one of the sprite variants, the size of the sprite is the size of the enemy
+        let sizes = sprite_metrics.enemy_sizes(enemy_type, enemy_color);
+        let sprite_variant = rng.gen_range(0, sizes.len());
+        let size = sizes[sprite_variant];
         let charge_timer_optional = match enemy_type {
-            EnemyType::Normal => None,
-            EnemyType::Mini => Some(rand::gen_range(
-                ENEMY_MINI_HOMING_TIME_RANGE.x,
-                ENEMY_MINI_HOMING_TIME_RANGE.y,
+            EnemyType::Normal | EnemyType::Boss => None,
+            EnemyType::Mini => Some(rng.gen_range(
+                tuning.enemy_mini_homing_time_range.0,
+                tuning.enemy_mini_homing_time_range.1,
             )),
         };
+        // bosses start above the screen and fly in to pos
+        let (state, pos) = match enemy_type {
+            EnemyType::Boss => (
+                EnemyState::BossIntro(EnemyStateBossIntro {
+                    timer: 0f32,
+                    target_pos: pos,
+                }),
+                vec2(pos.x, -size.y),
+            ),
+            _ => (
+                EnemyState::Spawning(EnemyStateSpawning { spawn_timer: 0f32 }),
+                pos,
+            ),
+        };
         Enemy {
+            id_optional: None,
             state_shared: EnemyStateShared {
                 pos,
-                texture,
-                collision_rect: Rect::new(0f32, 0f32, texture.width(), texture.height()),
+                prev_pos: pos,
+                sprite_variant,
+                size,
+                collision_rect: Rect::new(0f32, 0f32, size.x, size.y),
                 health,
+                max_health: health,
+                hit_flash_timer: 0f32,
                 angle: 0f32,
-                angle_speed: rand::gen_range(ENEMY_ANGLE_SPEED_RANGE.x, ENEMY_ANGLE_SPEED_RANGE.y),
+                angle_speed: rng.gen_range(
+                    tuning.enemy_angle_speed_range.0,
+                    tuning.enemy_angle_speed_range.1,
+                ),
                 death_method,
                 animation_timer: 0f32,
                 enemy_type,
                 charge_timer_optional,
                 enemy_color,
             },
-            state: EnemyState::Spawning(EnemyStateSpawning { spawn_timer: 0f32 }),
+            state,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
         dt: f32,
+        time: f32,
         bullets: &mut Vec<Bullet>,
-        resources: &Resources,
-        player_pos: &Vec2,
+        // every living player, homing enemies go for the closest one
+        player_positions: &[Vec2],
         game_manager: &mut WaveManager,
-        sound_mixer: &mut SoundMixer,
-    ) {
+        sounds: &mut SoundQueue,
+        rng: &mut GameRng,
+        tuning: &Tuning,
+    ) -> Option<EnemyMessage> {
+        self.state_shared.prev_pos = self.state_shared.pos;
+        self.state_shared.hit_flash_timer = (self.state_shared.hit_flash_timer - dt).max(0f32);
+        let mut message_optional = None;
         let command_optional = match &mut self.state {
             EnemyState::Spawning(state_data) => {
                 Self::update_state_spawning(&mut self.state_shared, dt, state_data)
             }
             EnemyState::Normal(state_data) => {
-                Self::update_state_normal(&mut self.state_shared, dt, state_data)
+                Self::update_state_normal(&mut self.state_shared, dt, time, state_data, rng, tuning)
             }
             EnemyState::Shooting(state_data) => Self::update_state_shooting(
                 &mut self.state_shared,
                 dt,
                 bullets,
-                resources,
                 state_data,
-                sound_mixer,
+                sounds,
+                rng,
+                tuning,
             ),
             EnemyState::Homing(_state_data) => Self::update_state_homing(
                 &mut self.state_shared,
                 dt,
-                player_pos,
+                time,
+                player_positions,
                 game_manager,
-                sound_mixer,
-                resources,
+                sounds,
+                tuning,
+            ),
+            EnemyState::BossIntro(state_data) => {
+                Self::update_state_boss_intro(&mut self.state_shared, dt, state_data, sounds)
+            }
+            EnemyState::BossFighting(state_data) => Self::update_state_boss_fighting(
+                &mut self.state_shared,
+                dt,
+                bullets,
+                player_positions,
+                state_data,
+                &mut message_optional,
+                sounds,
+                tuning,
             ),
+            EnemyState::BossDefeat(state_data) => {
+                Self::update_state_boss_defeat(&mut self.state_shared, dt, state_data, sounds)
+            }
         };
         match command_optional {
             None => {}
@@ -163,6 +284,78 @@ impl Enemy {
                 }
             },
         };
+        message_optional
+    }
+
+    // takes a point of health and flashes white for a moment, so hits that don't kill still show
+    pub fn hit(&mut self) {
+        self.state_shared.health -= 1;
+        self.state_shared.hit_flash_timer = ENEMY_HIT_FLASH_TIME;
+    }
+
+    // demons that take more than one hit, bosses have a health bar instead
+    pub fn is_armored(&self) -> bool {
+        self.state_shared.max_health > 1 && self.state_shared.enemy_type != EnemyType::Boss
+    }
+
+    // which of stage_count crack frames an armored demon shows, None while it's unhurt
+    fn crack_stage(&self, stage_count: usize) -> Option<usize> {
+        let damage = self.state_shared.max_health - self.state_shared.health;
+        if !self.is_armored() || damage <= 0 {
+            return None;
+        }
+        // the first hit shows the first frame, the last one is shown when one hit is left
+        let stage =
+            (damage - 1) as usize * stage_count / (self.state_shared.max_health - 1) as usize;
+        Some(stage.min(stage_count - 1))
+    }
+
+    // bosses can't be hurt while they fly in or while they die
+    pub fn is_hittable(&self) -> bool {
+        self.state_shared.health > 0
+            && !matches!(
+                self.state,
+                EnemyState::BossIntro(_) | EnemyState::BossDefeat(_)
+            )
+    }
+
+    // out of health, bosses only after their defeat sequence is over
+    pub fn is_dead(&self) -> bool {
+        if self.state_shared.health > 0 {
+            return false;
+        }
+        match &self.state {
+            EnemyState::BossDefeat(state_data) => state_data.timer >= BOSS_ANIM_TIME_DEFEAT,
+            _ => self.state_shared.enemy_type != EnemyType::Boss,
+        }
+    }
+
+    // shown big on screen while a boss flies in or dies
+    pub fn boss_banner(&self) -> Option<&'static str> {
+        match self.state {
+            EnemyState::BossIntro(_) => Some("THE DEMON LORD APPROACHES"),
+            EnemyState::BossDefeat(_) => Some("DEMON LORD DEFEATED"),
+            _ => None,
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.state_shared.pos + vec2(0f32, self.state_shared.size.y * 0.5f32)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.state_shared.size
+    }
+
+    pub fn is_homing(&self) -> bool {
+        variant_eq(&self.state, &EnemyState::Homing(EnemyStateHoming {}))
+    }
+
+    // its bottom went past y during the last step, going down
+    pub fn crossed_y(&self, y: f32) -> bool {
+        let bottom = self.state_shared.pos.y + self.state_shared.size.y;
+        let prev_bottom = self.state_shared.prev_pos.y + self.state_shared.size.y;
+        prev_bottom < y && bottom >= y
     }
 
     pub fn overlaps(&self, other_rect: &Rect) -> bool {
@@ -192,8 +385,9 @@ impl Enemy {
     ) -> Option<EnemyCommand> {
         state_data.spawn_timer += dt;
         // different enemy types spawn differently
+        // bosses have their own intro instead, they never spawn like this
         let end_time = match state_shared.enemy_type {
-            EnemyType::Normal => ENEMY_ANIM_TIME_SPAWN,
+            EnemyType::Normal | EnemyType::Boss => ENEMY_ANIM_TIME_SPAWN,
             EnemyType::Mini => ENEMY_MINI_ANIM_TIME_SPAWN,
         };
 
@@ -209,17 +403,19 @@ impl Enemy {
     fn update_state_normal(
         state_shared: &mut EnemyStateShared,
         dt: f32,
+        time: f32,
         state_data: &mut EnemyStateNormal,
+        rng: &mut GameRng,
+        tuning: &Tuning,
     ) -> Option<EnemyCommand> {
         let angle_change_speed = std::f32::consts::PI * state_shared.angle_speed;
-        state_shared.angle +=
-            (get_time() as f32 * angle_change_speed).sin() * std::f32::consts::PI * 2f32 * dt;
+        state_shared.angle += (time * angle_change_speed).sin() * std::f32::consts::PI * 2f32 * dt;
         let dir = vec2(state_shared.angle.sin(), -state_shared.angle.cos());
-        state_shared.pos += dir * ENEMY_SPEED * dt;
+        state_shared.pos += dir * tuning.enemy_speed * dt;
         // state_shared.pos.x += rand::gen_range(-1f32, 1f32) * ENEMY_SPEED * dt;
         // state_shared.pos.y += rand::gen_range(-1f32, 1f32) * ENEMY_SPEED * dt;
         Self::clamp_in_view(&mut state_shared.pos);
-        state_shared.collision_rect.x = state_shared.pos.x - state_shared.texture.width() * 0.5f32;
+        state_shared.collision_rect.x = state_shared.pos.x - state_shared.size.x * 0.5f32;
         state_shared.collision_rect.y = state_shared.pos.y;
 
         state_data.shoot_timer += dt;
@@ -234,18 +430,17 @@ impl Enemy {
         }
 
         state_shared.animation_timer += dt;
-        if state_shared.animation_timer > ENEMY_ANIM_TIME_FLAP * 4f32 {
-            state_shared.animation_timer -= ENEMY_ANIM_TIME_FLAP * 4f32;
-        }
-        if state_data.shoot_timer > ENEMY_SHOOT_TIME {
-            let shot_count = rand::gen_range(1, ENEMY_MAX_BURST_COUNT);
+        if state_data.shoot_timer > tuning.enemy_shoot_time {
+            let shot_count = rng.gen_range(1, tuning.enemy_max_burst_count);
             // every time we change state, the enemy will chose a random speed at which it changes its velocity
-            state_shared.angle_speed =
-                rand::gen_range(ENEMY_ANGLE_SPEED_RANGE.x, ENEMY_ANGLE_SPEED_RANGE.y);
-            state_shared.angle = rand::gen_range(-std::f32::consts::PI, std::f32::consts::PI);
+            state_shared.angle_speed = rng.gen_range(
+                tuning.enemy_angle_speed_range.0,
+                tuning.enemy_angle_speed_range.1,
+            );
+            state_shared.angle = rng.gen_range(-std::f32::consts::PI, std::f32::consts::PI);
             return Some(EnemyCommand::ChangeState(EnemyState::Shooting(
                 EnemyStateShooting {
-                    shoot_timer: ENEMY_SHOOT_BURST_TIME,
+                    shoot_timer: tuning.enemy_shoot_burst_time,
                     shots_left: shot_count,
                 },
             )));
@@ -257,53 +452,54 @@ impl Enemy {
         state_shared: &mut EnemyStateShared,
         dt: f32,
         bullets: &mut Vec<Bullet>,
-        resources: &Resources,
         state_data: &mut EnemyStateShooting,
-        sound_mixer: &mut SoundMixer,
+        sounds: &mut SoundQueue,
+        rng: &mut GameRng,
+        tuning: &Tuning,
     ) -> Option<EnemyCommand> {
-        state_shared.pos.x += rand::gen_range(-1f32, 1f32) * ENEMY_SPEED * 0.5f32 * dt;
-        state_shared.pos.y += rand::gen_range(-1f32, 1f32) * ENEMY_SPEED * 0.5f32 * dt;
+        state_shared.pos.x += rng.gen_range(-1f32, 1f32) * tuning.enemy_speed * 0.5f32 * dt;
+        state_shared.pos.y += rng.gen_range(-1f32, 1f32) * tuning.enemy_speed * 0.5f32 * dt;
         Self::clamp_in_view(&mut state_shared.pos);
         state_data.shoot_timer -= dt;
         // SPAWN SHOT
         if state_data.shoot_timer <= 0f32 {
-            state_data.shoot_timer = ENEMY_SHOOT_BURST_TIME;
+            state_data.shoot_timer = tuning.enemy_shoot_burst_time;
             state_data.shots_left -= 1;
 
-            let should_spawn_2 = rand::gen_range(0, 2) > 0;
+            let should_spawn_2 = rng.gen_range(0, 2) > 0;
             if should_spawn_2 {
-                let spawn_offset = vec2((state_shared.texture.width() / 4f32) * 0.5f32, 0f32);
+                let spawn_offset = vec2((state_shared.size.x / 4f32) * 0.5f32, 0f32);
                 bullets.push(Bullet::new(
                     state_shared.pos + spawn_offset,
                     BulletHurtType::Player,
-                    resources,
+                    None,
+                    tuning,
                 ));
                 bullets.push(Bullet::new(
                     state_shared.pos - spawn_offset,
                     BulletHurtType::Player,
-                    resources,
+                    None,
+                    tuning,
                 ));
             } else {
                 let spawn_offset = vec2(0f32, -3f32);
                 bullets.push(Bullet::new(
                     state_shared.pos + spawn_offset,
                     BulletHurtType::Player,
-                    resources,
+                    None,
+                    tuning,
                 ));
             }
-            resources.play_sound(SoundIdentifier::EnemyShoot, sound_mixer, Volume(1.0f32));
+            sounds.play(SoundIdentifier::EnemyShoot, 1.0f32);
 
             // for fun move enemy up when shooting
             state_shared.pos.y -= 2f32;
         }
 
-        state_shared.collision_rect.x = state_shared.pos.x - state_shared.texture.width() * 0.5f32;
+        state_shared.collision_rect.x = state_shared.pos.x - state_shared.size.x * 0.5f32;
         state_shared.collision_rect.y = state_shared.pos.y;
 
         state_shared.animation_timer += dt;
-        if state_shared.animation_timer > ENEMY_ANIM_TIME_FLAP * 4f32 {
-            state_shared.animation_timer -= ENEMY_ANIM_TIME_FLAP * 4f32;
-        }
 
         // done shooting
         if state_data.shots_left <= 0 {
@@ -314,30 +510,47 @@ impl Enemy {
         None
     }
 
+    // the closest of the positions to pos, ties go to the first one
+    pub fn closest_position(positions: &[Vec2], pos: Vec2) -> Option<Vec2> {
+        positions
+            .iter()
+            .fold(None, |closest: Option<Vec2>, other| match closest {
+                Some(closest) if closest.distance(pos) <= other.distance(pos) => Some(closest),
+                _ => Some(*other),
+            })
+    }
+
     fn update_state_homing(
         state_shared: &mut EnemyStateShared,
         dt: f32,
-        player_pos: &Vec2,
+        time: f32,
+        player_positions: &[Vec2],
         game_manager: &mut WaveManager,
-        sound_mixer: &mut SoundMixer,
-        resources: &Resources,
+        sounds: &mut SoundQueue,
+        tuning: &Tuning,
     ) -> Option<EnemyCommand> {
+        let previous_warnings = (state_shared.animation_timer / ENEMY_HOMING_WARNING_TIME) as i32;
         state_shared.animation_timer += dt;
-        if state_shared.animation_timer > ENEMY_ANIM_TIME_FLAP * 4f32 {
-            state_shared.animation_timer -= ENEMY_ANIM_TIME_FLAP * 4f32;
-            resources.play_sound(SoundIdentifier::Warning, sound_mixer, Volume(1.0f32));
+        if (state_shared.animation_timer / ENEMY_HOMING_WARNING_TIME) as i32 != previous_warnings {
+            sounds.play(SoundIdentifier::Warning, 1.0f32);
         }
         // MOVE TOWARDS PLAYER
-        let player_dx = player_pos.x - state_shared.pos.x;
-        let dx = if player_dx > 0f32 { 1f32 } else { -1f32 };
+        // the closest one. With nobody left it just falls straight down
+        let target_optional = Self::closest_position(player_positions, state_shared.pos);
+        let dx = match target_optional {
+            Some(target) if target.x - state_shared.pos.x > 0f32 => 1f32,
+            Some(_) => -1f32,
+            None => 0f32,
+        };
         let sway_speed = 20f32;
-        let sway = (get_time() as f32 * sway_speed).sin();
+        let sway = (time * sway_speed).sin();
         // remap from -1 -> 1 TO 0 -> 1
         let sway = (sway + 1f32) * 0.5f32;
 
-        let vel = vec2(dx * ENEMY_SPEED_HOMING.x * sway, ENEMY_SPEED_HOMING.y);
+        let (homing_speed_x, homing_speed_y) = tuning.enemy_speed_homing;
+        let vel = vec2(dx * homing_speed_x * sway, homing_speed_y);
         state_shared.pos += vel * dt;
-        state_shared.collision_rect.x = state_shared.pos.x - state_shared.texture.width() * 0.5f32;
+        state_shared.collision_rect.x = state_shared.pos.x - state_shared.size.x * 0.5f32;
         state_shared.collision_rect.y = state_shared.pos.y;
 
         // kill monsters below screen
@@ -349,152 +562,479 @@ impl Enemy {
         None
     }
 
+    fn update_state_boss_intro(
+        state_shared: &mut EnemyStateShared,
+        dt: f32,
+        state_data: &mut EnemyStateBossIntro,
+        sounds: &mut SoundQueue,
+    ) -> Option<EnemyCommand> {
+        state_data.timer += dt;
+        let fraction = (state_data.timer / BOSS_ANIM_TIME_INTRO).min(1f32);
+        // slows down as it gets there
+        let eased = 1f32 - (1f32 - fraction) * (1f32 - fraction);
+        let start_y = -state_shared.size.y;
+        state_shared.pos.x = state_data.target_pos.x;
+        state_shared.pos.y = start_y + (state_data.target_pos.y - start_y) * eased;
+        state_shared.collision_rect.x = state_shared.pos.x - state_shared.size.x * 0.5f32;
+        state_shared.collision_rect.y = state_shared.pos.y;
+
+        let previous_warnings = (state_shared.animation_timer / ENEMY_HOMING_WARNING_TIME) as i32;
+        state_shared.animation_timer += dt;
+        if (state_shared.animation_timer / ENEMY_HOMING_WARNING_TIME) as i32 != previous_warnings {
+            sounds.play(SoundIdentifier::Warning, 1.0f32);
+        }
+
+        if fraction >= 1f32 {
+            return Some(EnemyCommand::ChangeState(EnemyState::BossFighting(
+                EnemyStateBossFighting {
+                    move_dir: 1f32,
+                    attack_timer: 0f32,
+                    attack_count: 0,
+                    shots_left: 0,
+                    shot_timer: 0f32,
+                    mouth_timer: 0f32,
+                },
+            )));
+        }
+        None
+    }
+
+    fn boss_phase(state_shared: &EnemyStateShared) -> BossPhase {
+        let health_fraction = state_shared.health as f32 / state_shared.max_health as f32;
+        if health_fraction > 2f32 / 3f32 {
+            BossPhase::Spread
+        } else if health_fraction > 1f32 / 3f32 {
+            BossPhase::Aimed
+        } else {
+            BossPhase::Summon
+        }
+    }
+
+    // a fan of bullets, evenly spread over BOSS_SPREAD_ANGLE
+    fn boss_spread_shot(pos: Vec2, bullets: &mut Vec<Bullet>, tuning: &Tuning) {
+        let count = tuning.boss_spread_count;
+        for i in 0..count {
+            // -0.5 -> 0.5, a single bullet goes straight down
+            let fraction = if count > 1 {
+                i as f32 / (count - 1) as f32 - 0.5f32
+            } else {
+                0f32
+            };
+            let angle = fraction * BOSS_SPREAD_ANGLE;
+            bullets.push(Bullet::new_aimed(
+                pos,
+                vec2(angle.sin(), angle.cos()),
+                tuning,
+            ));
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn update_state_boss_fighting(
+        state_shared: &mut EnemyStateShared,
+        dt: f32,
+        bullets: &mut Vec<Bullet>,
+        player_positions: &[Vec2],
+        state_data: &mut EnemyStateBossFighting,
+        message_optional: &mut Option<EnemyMessage>,
+        sounds: &mut SoundQueue,
+        tuning: &Tuning,
+    ) -> Option<EnemyCommand> {
+        if state_shared.health <= 0 {
+            return Some(EnemyCommand::ChangeState(EnemyState::BossDefeat(
+                EnemyStateBossDefeat {
+                    timer: 0f32,
+                    blast_timer: 0f32,
+                },
+            )));
+        }
+
+        // bounce between the sides of the screen
+        let x_padding = 4f32;
+        let half_width = state_shared.size.x * 0.5f32;
+        state_shared.pos.x += state_data.move_dir * tuning.boss_speed * dt;
+        if state_shared.pos.x - half_width < x_padding {
+            state_data.move_dir = 1f32;
+        } else if state_shared.pos.x + half_width > GAME_SIZE_X as f32 - x_padding {
+            state_data.move_dir = -1f32;
+        }
+        state_shared.collision_rect.x = state_shared.pos.x - half_width;
+        state_shared.collision_rect.y = state_shared.pos.y;
+
+        state_shared.animation_timer += dt;
+
+        state_data.mouth_timer -= dt;
+        let mouth_pos = state_shared.pos + vec2(-1f32, state_shared.size.y * 0.5f32);
+
+        // aimed bursts fire one shot at a time at the closest player
+        if state_data.shots_left > 0 {
+            state_data.shot_timer -= dt;
+            if state_data.shot_timer <= 0f32 {
+                state_data.shot_timer = tuning.enemy_shoot_burst_time;
+                state_data.shots_left -= 1;
+                let dir = match Self::closest_position(player_positions, mouth_pos) {
+                    Some(target) => target - mouth_pos,
+                    None => vec2(0f32, 1f32),
+                };
+                bullets.push(Bullet::new_aimed(mouth_pos, dir, tuning));
+                sounds.play(SoundIdentifier::EnemyShoot, 1.0f32);
+                state_data.mouth_timer = BOSS_ANIM_TIME_MOUTH;
+            }
+        }
+
+        state_data.attack_timer += dt;
+        if state_data.attack_timer > tuning.boss_attack_time {
+            state_data.attack_timer -= tuning.boss_attack_time;
+            state_data.attack_count += 1;
+            match Self::boss_phase(state_shared) {
+                BossPhase::Spread => {
+                    Self::boss_spread_shot(mouth_pos, bullets, tuning);
+                    sounds.play(SoundIdentifier::EnemyShoot, 1.0f32);
+                    state_data.mouth_timer = BOSS_ANIM_TIME_MOUTH;
+                }
+                BossPhase::Aimed => {
+                    state_data.shots_left = BOSS_AIMED_BURST_COUNT;
+                    state_data.shot_timer = 0f32;
+                }
+                // every other attack summons minis instead of shooting
+                BossPhase::Summon => {
+                    if state_data.attack_count % 2 == 0 && tuning.boss_summon_count > 0 {
+                        *message_optional = Some(EnemyMessage::SummonMinis(
+                            state_shared.pos + vec2(-10f32, state_shared.size.y),
+                            tuning.boss_summon_count,
+                            state_shared.enemy_color,
+                        ));
+                    } else {
+                        Self::boss_spread_shot(mouth_pos, bullets, tuning);
+                        sounds.play(SoundIdentifier::EnemyShoot, 1.0f32);
+                    }
+                    state_data.mouth_timer = BOSS_ANIM_TIME_MOUTH;
+                }
+            }
+        }
+        None
+    }
+
+    fn update_state_boss_defeat(
+        state_shared: &mut EnemyStateShared,
+        dt: f32,
+        state_data: &mut EnemyStateBossDefeat,
+        sounds: &mut SoundQueue,
+    ) -> Option<EnemyCommand> {
+        state_data.timer += dt;
+        state_data.blast_timer -= dt;
+        if state_data.blast_timer <= 0f32 {
+            state_data.blast_timer += BOSS_DEFEAT_BLAST_TIME;
+            sounds.play(SoundIdentifier::EnemyOuch, 1.0f32);
+        }
+        // sinks while it blows up, is_dead removes it when it's done
+        state_shared.pos.y += 10f32 * dt;
+        state_shared.collision_rect.y = state_shared.pos.y;
+        None
+    }
+
     fn draw_state_spawning_normal(
-        state_shared: &EnemyStateShared,
         state_data: &EnemyStateSpawning,
+        texture: AtlasRegion,
+        sprite_sheet: &SpriteSheet,
+        pos: Vec2,
     ) {
-        let rand_frame = rand::gen_range(0i32, 2i32);
+        // follows the spawn timer so the flicker stops while paused
+        let frame = sprite_sheet.clip("spawn").frame_at(state_data.spawn_timer);
         let fraction = 1.0f32 - state_data.spawn_timer / ENEMY_ANIM_TIME_SPAWN;
         let offset = fraction * ENEMY_ANIM_DISTANCE;
-        let sprite_width = state_shared.texture.width() / 3f32;
+        let sprite_width = frame.rect.w;
         let scale = sprite_width + fraction * ENEMY_ANIM_SPAWN_SCALE * sprite_width;
         // Left wing
-        draw_texture_ex(
-            state_shared.texture,
-            state_shared.pos.x - ((state_shared.texture.width() / 3.0f32) * 1.0f32) - offset,
-            state_shared.pos.y,
+        draw_region_ex(
+            texture,
+            pos.x - sprite_width - offset,
+            pos.y,
             WHITE,
             DrawTextureParams {
                 rotation: 0f32,
-                dest_size: Some(vec2(scale, state_shared.texture.height())),
-                source: Some(Rect::new(
-                    state_shared.texture.width() / 3f32 * rand_frame as f32,
-                    0f32,
-                    state_shared.texture.width() / 3f32,
-                    state_shared.texture.height(),
-                )),
+                dest_size: Some(vec2(scale, frame.rect.h)),
+                source: Some(frame.rect),
                 ..Default::default()
             },
         );
         // right wing
-        draw_texture_ex(
-            state_shared.texture,
-            state_shared.pos.x + offset,
-            state_shared.pos.y,
+        draw_region_ex(
+            texture,
+            pos.x + offset,
+            pos.y,
             WHITE,
             DrawTextureParams {
                 rotation: 0f32,
                 flip_x: true,
-                dest_size: Some(vec2(scale, state_shared.texture.height())),
-                source: Some(Rect::new(
-                    state_shared.texture.width() / 3f32 * rand_frame as f32,
-                    0f32,
-                    state_shared.texture.width() / 3f32,
-                    state_shared.texture.height(),
-                )),
+                dest_size: Some(vec2(scale, frame.rect.h)),
+                source: Some(frame.rect),
                 ..Default::default()
             },
         );
     }
 
-    fn draw_state_spawning_mini(state_shared: &EnemyStateShared, state_data: &EnemyStateSpawning) {
-        let rand_frame = rand::gen_range(0i32, 2i32);
+    fn draw_state_spawning_mini(
+        state_data: &EnemyStateSpawning,
+        texture: AtlasRegion,
+        sprite_sheet: &SpriteSheet,
+        pos: Vec2,
+    ) {
+        // follows the spawn timer so the flicker stops while paused
+        let frame = sprite_sheet.clip("spawn").frame_at(state_data.spawn_timer);
         let fraction = state_data.spawn_timer / ENEMY_MINI_ANIM_TIME_SPAWN;
-        let sprite_width = state_shared.texture.width() / 4f32;
+        let sprite_width = frame.rect.w;
         let scale = sprite_width * 0.5f32 + fraction * 1.5f32 * sprite_width;
-        draw_texture_ex(
-            state_shared.texture,
-            state_shared.pos.x - ((state_shared.texture.width() / 4.0f32) * 1.0f32),
-            state_shared.pos.y,
+        draw_region_ex(
+            texture,
+            pos.x - sprite_width,
+            pos.y,
             WHITE,
             DrawTextureParams {
                 rotation: fraction * std::f32::consts::PI * 2f32,
                 dest_size: Some(vec2(scale, scale)),
-                source: Some(Rect::new(
-                    state_shared.texture.width() / 4f32 * rand_frame as f32,
-                    0f32,
-                    state_shared.texture.width() / 4f32,
-                    state_shared.texture.height(),
-                )),
+                source: Some(frame.rect),
                 ..Default::default()
             },
         );
         // right wing
-        draw_texture_ex(
-            state_shared.texture,
-            state_shared.pos.x,
-            state_shared.pos.y,
+        draw_region_ex(
+            texture,
+            pos.x,
+            pos.y,
             WHITE,
             DrawTextureParams {
                 rotation: fraction * std::f32::consts::PI * 2f32,
                 flip_x: true,
                 dest_size: Some(vec2(scale, scale)),
-                source: Some(Rect::new(
-                    state_shared.texture.width() / 4f32 * rand_frame as f32,
-                    0f32,
-                    state_shared.texture.width() / 4f32,
-                    state_shared.texture.height(),
-                )),
+                source: Some(frame.rect),
                 ..Default::default()
             },
         );
     }
 
-    fn draw_state_spawning(state_shared: &EnemyStateShared, state_data: &EnemyStateSpawning) {
+    fn draw_state_spawning(
+        state_shared: &EnemyStateShared,
+        state_data: &EnemyStateSpawning,
+        texture: AtlasRegion,
+        sprite_sheet: &SpriteSheet,
+        pos: Vec2,
+    ) {
         match state_shared.enemy_type {
-            EnemyType::Normal => Self::draw_state_spawning_normal(state_shared, state_data),
-            EnemyType::Mini => Self::draw_state_spawning_mini(state_shared, state_data),
+            EnemyType::Normal | EnemyType::Boss => {
+                Self::draw_state_spawning_normal(state_data, texture, sprite_sheet, pos)
+            }
+            EnemyType::Mini => {
+                Self::draw_state_spawning_mini(state_data, texture, sprite_sheet, pos)
+            }
         }
     }
 
-    fn draw_state_normal(&self) {
-        let rand_frame = (self.state_shared.animation_timer / ENEMY_ANIM_TIME_FLAP).floor();
+    fn draw_state_normal(
+        &self,
+        texture: AtlasRegion,
+        sprite_sheet: &SpriteSheet,
+        resources: &Resources,
+        pos: Vec2,
+    ) {
+        let frame = sprite_sheet
+            .clip("flap")
+            .frame_at(self.state_shared.animation_timer);
+        let wing_width = frame.rect.w;
+        // without the flash shader a hit armored demon lights up to its normal colors instead
+        let flashing = self.state_shared.hit_flash_timer > 0f32;
+        let tint = if self.is_armored() && !flashing {
+            ENEMY_ARMORED_TINT
+        } else {
+            WHITE
+        };
         // Left wing
-        draw_texture_ex(
-            self.state_shared.texture,
-            self.state_shared.pos.x - ((self.state_shared.texture.width() / 4.0f32) * 1.0f32),
-            self.state_shared.pos.y,
-            WHITE,
+        draw_region_ex(
+            texture,
+            pos.x - wing_width,
+            pos.y,
+            tint,
             DrawTextureParams {
                 rotation: 0f32,
-                source: Some(Rect::new(
-                    self.state_shared.texture.width() / 4f32 * rand_frame as f32,
-                    0f32,
-                    self.state_shared.texture.width() / 4f32,
-                    self.state_shared.texture.height(),
-                )),
+                source: Some(frame.rect),
                 ..Default::default()
             },
         );
         // right wing
-        draw_texture_ex(
-            self.state_shared.texture,
-            self.state_shared.pos.x,
-            self.state_shared.pos.y,
-            WHITE,
+        draw_region_ex(
+            texture,
+            pos.x,
+            pos.y,
+            tint,
             DrawTextureParams {
                 rotation: 0f32,
                 flip_x: true,
-                source: Some(Rect::new(
-                    self.state_shared.texture.width() / 4f32 * rand_frame as f32,
-                    0f32,
-                    self.state_shared.texture.width() / 4f32,
-                    self.state_shared.texture.height(),
-                )),
+                source: Some(frame.rect),
                 ..Default::default()
             },
         );
+
+        // cracks spread over armored demons as they lose health, stretched over each wing
+        let cracks_clip = resources
+            .sprite_sheet(TextureSlot::DemonCracks)
+            .clip("stages");
+        if let Some(crack_stage) = self.crack_stage(cracks_clip.frames.len()) {
+            let crack_rect = cracks_clip.frame(crack_stage).rect;
+            let params = |flip_x: bool| DrawTextureParams {
+                dest_size: Some(vec2(wing_width, frame.rect.h)),
+                source: Some(crack_rect),
+                flip_x,
+                ..Default::default()
+            };
+            draw_region_ex(
+                resources.demon_cracks,
+                pos.x - wing_width,
+                pos.y,
+                WHITE,
+                params(false),
+            );
+            draw_region_ex(resources.demon_cracks, pos.x, pos.y, WHITE, params(true));
+        }
     }
 
-    pub fn draw(&mut self) {
+    // a wing on each side of the body, the wings share the flapping of normal demons
+    fn draw_boss(
+        &self,
+        wing_texture: AtlasRegion,
+        wing_sprite_sheet: &SpriteSheet,
+        resources: &Resources,
+        pos: Vec2,
+        mouth_open: bool,
+    ) {
+        let wing_frame = wing_sprite_sheet
+            .clip("flap")
+            .frame_at(self.state_shared.animation_timer);
+        let wing_width = wing_frame.rect.w;
+        let body_clip = if mouth_open {
+            "mouth_open"
+        } else {
+            "mouth_closed"
+        };
+        let body_frame = resources
+            .sprite_sheet(TextureSlot::DemonBossBody)
+            .clip(body_clip)
+            .frame_at(self.state_shared.animation_timer);
+        let body_width = body_frame.rect.w;
+        // Left wing, tucked a pixel under the body
+        draw_region_ex(
+            wing_texture,
+            pos.x - body_width * 0.5f32 - wing_width + 1f32,
+            pos.y + BOSS_WING_OFFSET_Y,
+            WHITE,
+            DrawTextureParams {
+                source: Some(wing_frame.rect),
+                ..Default::default()
+            },
+        );
+        // right wing
+        draw_region_ex(
+            wing_texture,
+            pos.x + body_width * 0.5f32 - 1f32,
+            pos.y + BOSS_WING_OFFSET_Y,
+            WHITE,
+            DrawTextureParams {
+                flip_x: true,
+                source: Some(wing_frame.rect),
+                ..Default::default()
+            },
+        );
+        draw_region_ex(
+            resources.demon_boss_body,
+            pos.x - body_width * 0.5f32,
+            pos.y,
+            WHITE,
+            DrawTextureParams {
+                source: Some(body_frame.rect),
+                ..Default::default()
+            },
+        );
+    }
+
+    // bosses show their health along the top of the screen, it fills up while they fly in
+    pub fn draw_health_bar(&self) {
+        let fraction = match &self.state {
+            EnemyState::BossIntro(state_data) => {
+                (state_data.timer / BOSS_ANIM_TIME_INTRO).min(1f32)
+            }
+            EnemyState::BossFighting(_) => {
+                self.state_shared.health.max(0) as f32 / self.state_shared.max_health as f32
+            }
+            _ => return,
+        };
+        let width = GAME_SIZE_X as f32 * 0.5f32;
+        let height = 3f32;
+        let x = GAME_CENTER_X - width * 0.5f32;
+        let y = 2f32;
+        // the colors of the boss sprite
+        let dark_red = Color::new(0.46f32, 0.16f32, 0.16f32, 1f32);
+        let red = Color::new(0.78f32, 0.35f32, 0.35f32, 1f32);
+        draw_rectangle(x - 1f32, y - 1f32, width + 2f32, height + 2f32, dark_red);
+        draw_rectangle(x, y, width, height, BLACK);
+        draw_rectangle(x, y, width * fraction, height, red);
+    }
+
+    // interpolation is how far we are between the last simulation step and the next, 0 -> 1
+    pub fn draw(&self, resources: &Resources, interpolation: f32) {
+        let texture = resources.enemy_texture(
+            self.state_shared.enemy_type,
+            self.state_shared.enemy_color,
+            self.state_shared.sprite_variant,
+        );
+        let sprite_sheet = resources.enemy_sprite_sheet(
+            self.state_shared.enemy_type,
+            self.state_shared.enemy_color,
+            self.state_shared.sprite_variant,
+        );
+        let pos = self.state_shared.prev_pos
+            + (self.state_shared.pos - self.state_shared.prev_pos) * interpolation;
+        let flashing = self.state_shared.hit_flash_timer > 0f32;
+        if let (true, Some(flash_material)) = (flashing, resources.flash_material_optional) {
+            gl_use_material(flash_material);
+        }
         match &self.state {
-            EnemyState::Spawning(state_data) => {
-                Self::draw_state_spawning(&self.state_shared, state_data)
+            EnemyState::Spawning(state_data) => Self::draw_state_spawning(
+                &self.state_shared,
+                state_data,
+                texture,
+                sprite_sheet,
+                pos,
+            ),
+            EnemyState::Normal(_state_data) => {
+                self.draw_state_normal(texture, sprite_sheet, resources, pos)
             }
-            EnemyState::Normal(_state_data) => self.draw_state_normal(),
             // enemy doesn't look different when shooting
-            EnemyState::Shooting(_state_data) => self.draw_state_normal(),
-            EnemyState::Homing(_state_data) => self.draw_state_normal(),
+            EnemyState::Shooting(_state_data) => {
+                self.draw_state_normal(texture, sprite_sheet, resources, pos)
+            }
+            EnemyState::Homing(_state_data) => {
+                self.draw_state_normal(texture, sprite_sheet, resources, pos)
+            }
+            EnemyState::BossIntro(_state_data) => {
+                self.draw_boss(texture, sprite_sheet, resources, pos, false)
+            }
+            EnemyState::BossFighting(state_data) => self.draw_boss(
+                texture,
+                sprite_sheet,
+                resources,
+                pos,
+                state_data.mouth_timer > 0f32,
+            ),
+            // shakes and flickers while it blows up
+            EnemyState::BossDefeat(state_data) => {
+                let flicker = (state_data.timer * 15f32) as i32 % 2 == 0;
+                if flicker {
+                    let shake = vec2((state_data.timer * 60f32).sin() * 2f32, 0f32);
+                    self.draw_boss(texture, sprite_sheet, resources, pos + shake, true);
+                }
+            }
+        }
+        if flashing {
+            gl_use_default_material();
         }
     }
 }
@@ -0,0 +1,47 @@
+// Noticing when the player switched away from the game, so it can pause itself.
+// The browser tells us through utils/wasm/focus.js, on desktop the window isn't asked,
+// instead a frame that took very long (minimized, dragged, switched away) counts as losing focus.
+
+// a frame longer than this (in seconds) means the game wasn't being looked at
+#[cfg(not(target_arch = "wasm32"))]
+const FOCUS_LOST_FRAME_TIME: f32 = 0.5f32;
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    // 1 if the page is visible and focused
+    fn focus_window_has_focus() -> i32;
+}
+
+pub struct FocusTracker {
+    focused: bool,
+}
+
+impl FocusTracker {
+    pub fn new() -> Self {
+        FocusTracker { focused: true }
+    }
+
+    // true on the frame focus was lost
+    pub fn update(&mut self, dt: f32) -> bool {
+        let focused = window_has_focus(dt);
+        let lost = self.focused && !focused;
+        self.focused = focused;
+        lost
+    }
+}
+
+impl Default for FocusTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn window_has_focus(_dt: f32) -> bool {
+    unsafe { focus_window_has_focus() != 0 }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn window_has_focus(dt: f32) -> bool {
+    dt <= FOCUS_LOST_FRAME_TIME
+}
@@ -2,28 +2,41 @@ use macroquad::prelude::*;
 use std::collections::HashMap;
 
 use crate::{
-    bullet::{Bullet, BulletHurtType},
+    atlas::{draw_region_ex, AtlasRegion},
+    background::Background,
     constants::*,
-    enemy::{Enemy, EnemyDeathMethod, EnemyStateHoming},
-    enemy::{EnemyColor, EnemyState, EnemyType},
-    player::{Player, PlayerCommand, PlayerState},
-    resources::{Resources, SoundIdentifier},
-    variant_eq,
-    wave::{
-        spawn_enemy, LastEnemyDeathReason, SpawnBlueprint, WaveManager, WaveManagerMessage,
-        WaveManagerState,
-    },
+    crt::{draw_upscaled, CrtPreset},
+    focus::FocusTracker,
+    gamepad::Gamepads,
+    highscore::{today, HighScoreEntry, HighScores},
+    hot_reload::{HotReloadMessage, HotReloader},
+    input::{Action, Binding, Bindings, ALL_ACTIONS},
+    juice::Juice,
+    pickup::{draw_pickup_icon, PickupKind, ALL_PICKUP_KINDS},
+    player::Player,
+    replay::{Replay, ReplayPlayback},
+    resources::{Resources, TextureSlot},
+    settings::Settings,
+    touch::{draw_touch_controls, TouchButtons, TouchControls},
+    tuning::Tuning,
+    variants::GameVariants,
+    viewport::{apply_fullscreen, Viewport, ViewportMode},
+    wave::{LastEnemyDeathReason, WaveManager, WaveManagerState},
+    wave_file::WaveDefinition,
+    world::{SpriteMetrics, World, WorldInput, WorldMessage},
 };
 
 use quad_snd::mixer::{SoundMixer, Volume};
 
 // okay this is pretty hacky...
 // if last kill was from player then a life should've been gained, so animate lives
+// player one's lives are in the bottom left corner, player two's in the bottom right
 pub fn draw_lives(
-    player_lives: &i32,
-    texture_life: Texture2D,
-    texture_ground_bg: &Texture2D,
+    player: &Player,
+    texture_life: AtlasRegion,
+    texture_ground_bg: &AtlasRegion,
     game_manager: &WaveManager,
+    time: f32,
 ) {
     let lives_padding = 2f32;
     let last_kill_from_player =
@@ -31,16 +44,23 @@ pub fn draw_lives(
     let wave_speed = 20f32;
     let wave_offset_y = -7f32;
     let wave_time_offset = 0.7f32;
+    let life_x = |i: i32| {
+        let offset = 5f32 + i as f32 * (texture_life.width() + lives_padding);
+        if player.index == 0 {
+            offset
+        } else {
+            GAME_SIZE_X as f32 - offset - texture_life.width()
+        }
+    };
 
     match &game_manager.state {
         WaveManagerState::Spawning(_spawning_state) if last_kill_from_player => {
-            for i in 0..*player_lives {
-                let wave = ((get_time() as f32 * wave_speed + i as f32 * wave_time_offset).sin()
-                    + 1f32)
-                    * 0.5f32;
-                draw_texture_ex(
+            for i in 0..player.lives {
+                let wave =
+                    ((time * wave_speed + i as f32 * wave_time_offset).sin() + 1f32) * 0.5f32;
+                draw_region_ex(
                     texture_life,
-                    5f32 + i as f32 * (texture_life.width() + lives_padding),
+                    life_x(i),
                     GAME_SIZE_Y as f32 - texture_ground_bg.height() + 3f32 + wave * wave_offset_y,
                     PINK,
                     DrawTextureParams {
@@ -50,12 +70,12 @@ pub fn draw_lives(
             }
         }
         _ => {
-            for i in 0..*player_lives {
-                draw_texture_ex(
+            for i in 0..player.lives {
+                draw_region_ex(
                     texture_life,
-                    5f32 + i as f32 * (texture_life.width() + lives_padding),
+                    life_x(i),
                     GAME_SIZE_Y as f32 - texture_ground_bg.height() + 3f32,
-                    WHITE,
+                    player.tint(),
                     DrawTextureParams {
                         ..Default::default()
                     },
@@ -65,23 +85,77 @@ pub fn draw_lives(
     }
 }
 
+// the running power-ups with a bar for the time left, below the lives on the player's side.
+// They blink when about to run out
+pub fn draw_power_ups(player: &Player, resources: &Resources, tuning: &Tuning, time: f32) {
+    let icon_padding = 2f32;
+    let bar_height = 1f32;
+    let blink_speed = 8f32;
+    let y = GAME_SIZE_Y as f32 - resources.ground_bg.height() + 12f32;
+    for (i, kind) in ALL_PICKUP_KINDS
+        .iter()
+        .filter(|kind| **kind != PickupKind::ExtraLife && player.power_ups.is_active(**kind))
+        .enumerate()
+    {
+        let offset = 5f32 + i as f32 * (PICKUP_SIZE + icon_padding);
+        let x = if player.index == 0 {
+            offset
+        } else {
+            GAME_SIZE_X as f32 - offset - PICKUP_SIZE
+        };
+        let time_left = player.power_ups.time_left(*kind);
+        if time_left < PICKUP_BLINK_TIME && (time * blink_speed) as i32 % 2 == 0 {
+            continue;
+        }
+        draw_pickup_icon(resources, *kind, vec2(x, y));
+        let fraction = (time_left / tuning.pickup_duration).min(1f32);
+        draw_rectangle(
+            x,
+            y + PICKUP_SIZE,
+            PICKUP_SIZE * fraction,
+            bar_height,
+            player.tint(),
+        );
+    }
+}
+
 pub struct MenuPayload {
     score: i32,
+    wave: i32,
+    replay: Replay,
+    // false when the replay wouldn't play back the game that was played, see recording_tainted
+    replay_watchable: bool,
+    // where the game landed in the high score table, if it made it
+    high_score_index_optional: Option<usize>,
 }
 
 // hacky way to transfer data between states
 pub enum ChangeStatePayload {
     MenuPayload(MenuPayload),
+    // start the game state watching a replay instead of playing
+    ReplayPayload(Replay),
+    // back to the game from the pause menu, continuing where it left off
+    Resume,
+    // from the pause menu, starts the game (or the replay being watched) over
+    Restart,
+    // opens the controls screen, which goes back to the given state when closed (with Resume)
+    ControlsPayload(GameStateIdentifier),
+    // starts a new game with this many players and variants, without a payload the game starts
+    // with one player and no variants
+    NewGamePayload(usize, GameVariants),
 }
 
 pub enum GameStateCommand {
     ChangeState(GameStateIdentifier, Option<ChangeStatePayload>),
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub enum GameStateIdentifier {
     Menu,
     Game,
+    NameEntry,
+    Pause,
+    Controls,
 }
 
 pub trait GameState {
@@ -90,52 +164,289 @@ pub trait GameState {
         dt: f32,
         resources: &Resources,
         sound_mixer: &mut SoundMixer,
+        settings: &mut Settings,
+        gamepads: &Gamepads,
+        touch: &TouchControls,
     ) -> Option<GameStateCommand>;
     fn draw(&self, resources: &Resources);
-    fn draw_unscaled(&self, resources: &Resources);
+    // viewport is where the game sits in the window, for placing text over it
+    fn draw_unscaled(&self, resources: &Resources, viewport: &Viewport);
     fn on_enter(&mut self, resources: &Resources, payload_optional: Option<ChangeStatePayload>);
+    // called after files changed on disk were reloaded
+    fn on_resources_reloaded(&mut self, _resources: &Resources) {}
+    fn on_tuning_reloaded(&mut self, _tuning: &Tuning) {}
+    fn on_waves_reloaded(&mut self, _waves: &[WaveDefinition]) {}
+    // the window stopped being looked at, only called when pausing on focus loss is enabled
+    fn on_focus_lost(&mut self) -> Option<GameStateCommand> {
+        None
+    }
+    // the previous state is drawn (frozen) below this one, like a menu over the game
+    fn draws_over_previous_state(&self) -> bool {
+        false
+    }
+    // in game pixels, moves the camera for screen shake
+    fn camera_offset(&self) -> Vec2 {
+        Vec2::ZERO
+    }
 }
 
 pub struct GameStateGame {
-    wave_manager: WaveManager,
-    player_score: i32,
-    player_lives: i32,
-    bullets: Vec<Bullet>,
-    enemies: Vec<Enemy>,
-    player: Player,
+    world: World,
+    // frame time not yet simulated, carried over to the next frame
+    tick_accumulator: f32,
+    // how far we are between the last simulation step and the next, used to interpolate drawing
+    interpolation: f32,
+    // every tick's input of the game being played, so it can be saved and watched again
+    recording: Replay,
+    // when watching a replay the inputs come from here instead of the keyboard
+    playback_optional: Option<ReplayPlayback>,
+    // the tuning, waves or sprites were hot reloaded during the game,
+    // the recording wouldn't play back the same
+    recording_tainted: bool,
+    // reloads that came in while watching a replay, the next game starts with them
+    pending_tuning_optional: Option<Tuning>,
+    pending_waves_optional: Option<Vec<WaveDefinition>>,
+    pending_sprite_metrics_optional: Option<SpriteMetrics>,
+    // the on screen buttons to draw, None when nothing was touched yet or a replay is playing
+    touch_buttons_optional: Option<TouchButtons>,
+    // screen shake and hit-stop
+    juice: Juice,
+    background: Background,
 }
 
 impl GameStateGame {
-    pub fn new(resources: &Resources) -> Self {
-        let player_spawn_y =
-            GAME_SIZE_Y as f32 - resources.ground_bg.height() - resources.player.height();
-        let player_pos = vec2(GAME_CENTER_X, player_spawn_y);
-        let player = Player::new(
-            player_pos,
-            resources.player,
-            resources.player_missile,
-            resources.player_explotion,
-        );
-
+    pub fn new(resources: &Resources, tuning: Tuning, waves: Vec<WaveDefinition>) -> Self {
+        let seed = Self::new_seed();
         GameStateGame {
-            wave_manager: WaveManager::new(),
-            player_score: 0,
-            player_lives: PLAYER_LIVES_START,
-            bullets: Vec::<Bullet>::new(),
-            enemies: Vec::<Enemy>::new(),
-            player,
+            world: World::new(
+                resources.sprite_metrics(),
+                tuning,
+                waves,
+                1,
+                GameVariants::default(),
+                seed,
+            ),
+            tick_accumulator: 0f32,
+            interpolation: 0f32,
+            recording: Replay::new(seed, 1, GameVariants::default()),
+            playback_optional: None,
+            recording_tainted: false,
+            pending_tuning_optional: None,
+            pending_waves_optional: None,
+            pending_sprite_metrics_optional: None,
+            touch_buttons_optional: None,
+            juice: Juice::default(),
+            background: Background::new(),
+        }
+    }
+
+    // reloads that came in while watching a replay
+    fn apply_pending_reloads(&mut self) {
+        if let Some(tuning) = self.pending_tuning_optional.take() {
+            self.world.tuning = tuning;
+        }
+        if let Some(waves) = self.pending_waves_optional.take() {
+            self.world.wave_manager.reload_waves(waves);
+        }
+        if let Some(sprite_metrics) = self.pending_sprite_metrics_optional.take() {
+            self.world.sprite_metrics = sprite_metrics;
+        }
+    }
+
+    // events of the explosion frames reached since the last frame, they're only for show so they
+    // don't go through the world's step
+    fn play_explosion_events(&mut self, resources: &Resources, progress_before: &[Option<f32>]) {
+        let clip = resources
+            .sprite_sheet(TextureSlot::PlayerExplotion)
+            .clip("explode");
+        for (player, progress_before) in self.world.players.iter().zip(progress_before.iter()) {
+            let progress = match player.explosion_progress_optional(&self.world.tuning) {
+                Some(progress) => progress,
+                None => continue,
+            };
+            // a new explosion plays from the start
+            let progress_before = match progress_before {
+                Some(progress_before) if *progress_before <= progress => *progress_before,
+                _ => -1f32,
+            };
+            for event in clip.events_between(
+                progress_before * clip.duration(),
+                progress * clip.duration(),
+            ) {
+                if event == "debris" {
+                    let rect = player.collision_rect;
+                    self.world
+                        .particles
+                        .sparks(rect.point() + rect.size() * 0.5f32);
+                }
+            }
+        }
+    }
+
+    fn start_new_game(&mut self, player_count: usize, variants: GameVariants) {
+        let seed = Self::new_seed();
+        self.world.reset(player_count, variants, seed);
+        self.recording = Replay::new(seed, player_count, variants);
+        self.playback_optional = None;
+    }
+
+    fn start_replay(&mut self, replay: Replay) {
+        self.world
+            .reset(replay.player_count, replay.variants, replay.seed);
+        self.recording = Replay::new(replay.seed, replay.player_count, replay.variants);
+        self.playback_optional = Some(ReplayPlayback::new(replay));
+    }
+
+    // every game started from the menu gets a fresh seed
+    fn new_seed() -> u64 {
+        (macroquad::miniquad::date::now() * 1000f64) as u64
+    }
+
+    // sticks move the cannon as fast as they're pushed, keys, d-pads and touch buttons always at full speed
+    fn read_input(
+        bindings: &Bindings,
+        gamepads: &Gamepads,
+        pad: usize,
+        touch_buttons: TouchButtons,
+    ) -> WorldInput {
+        let mut move_x = bindings.value(Action::MoveRight, gamepads, Some(pad))
+            - bindings.value(Action::MoveLeft, gamepads, Some(pad));
+        if touch_buttons.left {
+            move_x -= 1f32;
+        }
+        if touch_buttons.right {
+            move_x += 1f32;
+        }
+        WorldInput::new(
+            move_x,
+            bindings.is_down(Action::Fire, gamepads, Some(pad)) || touch_buttons.fire,
+        )
+    }
+
+    // returns the amount of ticks to force when paused and stepping frame by frame
+    fn update_replay_controls(playback: &mut ReplayPlayback) -> i32 {
+        if is_key_pressed(KEY_REPLAY_PAUSE) {
+            playback.paused = !playback.paused;
+        }
+        playback.speed = if is_key_down(KEY_REPLAY_FAST_FORWARD) {
+            REPLAY_FAST_FORWARD_SPEED
+        } else {
+            1f32
+        };
+        if playback.paused && is_key_pressed(KEY_REPLAY_STEP) {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn save_recording(&self) {
+        if self.recording_tainted {
+            eprintln!(
+                "not saving the replay, the tuning, waves or sprites changed during the game"
+            );
+            return;
         }
+        let path = format!("{}/replay_{}.rdar", REPLAY_DIRECTORY, self.recording.seed);
+        if let Err(err) = self.recording.save(&path) {
+            eprintln!("failed to save replay {}: {}", path, err);
+        }
+    }
+
+    fn to_menu(&self) -> Option<GameStateCommand> {
+        let replay = match &self.playback_optional {
+            Some(playback) => playback.replay.clone(),
+            None => self.recording.clone(),
+        };
+        // watched replays don't get to enter the table again
+        let next_state = if self.playback_optional.is_none()
+            && HighScores::load().qualifies(self.world.score(), &self.world.variants)
+        {
+            GameStateIdentifier::NameEntry
+        } else {
+            GameStateIdentifier::Menu
+        };
+        Some(GameStateCommand::ChangeState(
+            next_state,
+            Some(ChangeStatePayload::MenuPayload(MenuPayload {
+                score: self.world.score(),
+                wave: self.world.wave_manager.wave,
+                replay,
+                replay_watchable: self.playback_optional.is_some() || !self.recording_tainted,
+                high_score_index_optional: None,
+            })),
+        ))
     }
 }
 
 impl GameState for GameStateGame {
-    fn on_enter(&mut self, resources: &Resources, _payload_optional: Option<ChangeStatePayload>) {
-        self.wave_manager.reset();
-        self.player.reset(resources);
-        self.player_score = 0;
-        self.player_lives = PLAYER_LIVES_START;
-        self.enemies.clear();
-        self.bullets.clear();
+    fn on_enter(&mut self, _resources: &Resources, payload_optional: Option<ChangeStatePayload>) {
+        // a resumed game keeps its reloads pending and its recording as it was
+        if let Some(ChangeStatePayload::Resume) = payload_optional {
+            return;
+        }
+        self.apply_pending_reloads();
+        self.recording_tainted = false;
+        match payload_optional {
+            Some(ChangeStatePayload::ReplayPayload(replay)) => self.start_replay(replay),
+            Some(ChangeStatePayload::Restart) => match self.playback_optional.take() {
+                Some(playback) => self.start_replay(playback.replay),
+                None => self.start_new_game(self.world.players.len(), self.world.variants),
+            },
+            Some(ChangeStatePayload::NewGamePayload(player_count, variants)) => {
+                self.start_new_game(player_count, variants)
+            }
+            _ => self.start_new_game(1, GameVariants::default()),
+        }
+        self.tick_accumulator = 0f32;
+        self.interpolation = 0f32;
+        self.juice.reset();
+        self.background.reset();
+    }
+
+    fn camera_offset(&self) -> Vec2 {
+        self.juice.offset()
+    }
+
+    fn on_focus_lost(&mut self) -> Option<GameStateCommand> {
+        Some(GameStateCommand::ChangeState(
+            GameStateIdentifier::Pause,
+            None,
+        ))
+    }
+
+    // the running game keeps going with the new sizes, they change collisions like the tuning does
+    fn on_resources_reloaded(&mut self, resources: &Resources) {
+        let sprite_metrics = resources.sprite_metrics();
+        if sprite_metrics == self.world.sprite_metrics {
+            return;
+        }
+        if self.playback_optional.is_some() {
+            self.pending_sprite_metrics_optional = Some(sprite_metrics);
+            return;
+        }
+        self.world.sprite_metrics = sprite_metrics;
+        self.recording_tainted = true;
+    }
+
+    // a replay being watched keeps the tuning it started with, it would play out differently
+    fn on_tuning_reloaded(&mut self, tuning: &Tuning) {
+        if self.playback_optional.is_some() {
+            self.pending_tuning_optional = Some(tuning.clone());
+            return;
+        }
+        self.world.tuning = tuning.clone();
+        self.recording_tainted = true;
+    }
+
+    // the wave being played keeps going, the changes show up from the next wave
+    fn on_waves_reloaded(&mut self, waves: &[WaveDefinition]) {
+        if self.playback_optional.is_some() {
+            self.pending_waves_optional = Some(waves.to_vec());
+            return;
+        }
+        self.world.wave_manager.reload_waves(waves.to_vec());
+        self.recording_tainted = true;
     }
 
     fn update(
@@ -143,159 +454,143 @@ impl GameState for GameStateGame {
         dt: f32,
         resources: &Resources,
         sound_mixer: &mut SoundMixer,
+        settings: &mut Settings,
+        gamepads: &Gamepads,
+        touch: &TouchControls,
     ) -> Option<GameStateCommand> {
-        let manager_message_optional =
-            self.wave_manager
-                .update(dt, &mut self.enemies, resources, sound_mixer);
-        if let Some(manager_message) = manager_message_optional {
-            match manager_message {
-                WaveManagerMessage::LevelCleared => {
-                    self.player_lives += 1;
-                    self.player_lives = self.player_lives.min(PLAYER_LIVES_MAX);
-                    let score_add = match self.wave_manager.last_enemy_death_reason {
-                        LastEnemyDeathReason::Environment => SCORE_SURVIVED_ALL,
-                        LastEnemyDeathReason::Player => SCORE_KILL_ALL,
-                    };
-                    resources.play_sound(SoundIdentifier::WaveCleared, sound_mixer, Volume(0.6f32));
-                    self.player_score += score_add;
-                }
-            }
-        }
-
-        for enemy in self.enemies.iter_mut() {
-            enemy.update(
-                dt,
-                &mut self.bullets,
-                resources,
-                &self.player.pos,
-                &mut self.wave_manager,
-                sound_mixer,
-            );
-            enemy.draw();
+        self.touch_buttons_optional = if touch.enabled && self.playback_optional.is_none() {
+            Some(touch.down)
+        } else {
+            None
+        };
+        // anyone can pause
+        let pause_pressed = (0..PLAYER_COUNT_MAX).any(|player| {
+            settings
+                .bindings_for(player)
+                .is_pressed(Action::Pause, gamepads, None)
+        }) || touch.pause_pressed();
+        if pause_pressed && self.playback_optional.is_none() {
+            return Some(GameStateCommand::ChangeState(
+                GameStateIdentifier::Pause,
+                None,
+            ));
         }
 
-        for bullet in self.bullets.iter_mut() {
-            bullet.update(dt);
-            bullet.draw();
+        let mut frame_time = dt;
+        let mut forced_ticks = 0;
+        if let Some(playback) = &mut self.playback_optional {
+            if is_key_pressed(KEY_REPLAY_EXIT) {
+                return self.to_menu();
+            }
+            forced_ticks = Self::update_replay_controls(playback);
+            frame_time = if playback.paused {
+                0f32
+            } else {
+                dt * playback.speed
+            };
         }
 
-        // bullets hurting player
-        for bullet in self
-            .bullets
-            .iter_mut()
-            .filter(|b| b.hurt_type == BulletHurtType::Player)
+        // hit-stop freezes the world for a moment, it doesn't count as simulated time
+        let frame_time = self.juice.update(frame_time);
+        self.background
+            .update(frame_time, self.world.wave_manager.wave);
+        // the world always steps with SIMULATION_DT so the game plays the same at any frame rate
+        self.tick_accumulator = (self.tick_accumulator + frame_time)
+            .min(SIMULATION_DT * SIMULATION_MAX_TICKS_PER_FRAME as f32);
+        // player one uses the first gamepad slot and the touch buttons, player two the second slot
+        let player_inputs: Vec<WorldInput> = (0..self.world.players.len())
+            .map(|player| {
+                let touch_buttons = if player == 0 {
+                    touch.down
+                } else {
+                    TouchButtons::default()
+                };
+                Self::read_input(
+                    settings.bindings_for(player),
+                    gamepads,
+                    player,
+                    touch_buttons,
+                )
+            })
+            .collect();
+        let explosion_progress_before: Vec<Option<f32>> = self
+            .world
+            .players
+            .iter()
+            .map(|player| player.explosion_progress_optional(&self.world.tuning))
+            .collect();
+        let mut world_message_optional = None;
+        while (self.tick_accumulator >= SIMULATION_DT || forced_ticks > 0)
+            && world_message_optional.is_none()
         {
-            if bullet.overlaps(&self.player.collision_rect) {
-                if self.player.state != PlayerState::Normal {
-                    continue;
-                }
-                self.player_lives -= 1;
-                resources.play_sound(SoundIdentifier::PlayerOuch, sound_mixer, Volume(1.0f32));
-                // CHANGE PLAYER STATE
-                self.player
-                    .process_command_optional(Some(PlayerCommand::ChangeState(
-                        PlayerState::Invisible(PLAYER_TIME_INVISBLE),
-                    )));
-                if self.player_lives <= 0 {
-                    return Some(GameStateCommand::ChangeState(
-                        GameStateIdentifier::Menu,
-                        Some(ChangeStatePayload::MenuPayload(MenuPayload {
-                            score: self.player_score,
-                        })),
-                    ));
-                }
-                bullet.is_kill = true;
-                break;
+            if forced_ticks > 0 {
+                forced_ticks -= 1;
+            } else {
+                self.tick_accumulator -= SIMULATION_DT;
             }
+            let input = match &mut self.playback_optional {
+                Some(playback) => match playback.next_input() {
+                    Some(input) => input,
+                    None => break,
+                },
+                None => {
+                    self.recording.inputs.push(player_inputs.clone());
+                    player_inputs.clone()
+                }
+            };
+            world_message_optional = self.world.step(&input, SIMULATION_DT);
         }
+        self.interpolation = self.tick_accumulator / SIMULATION_DT;
+        self.play_explosion_events(resources, &explosion_progress_before);
 
-        // homing enemies hurting player
-        for enemy in self
-            .enemies
-            .iter_mut()
-            // filter enemies containing homing state, variant_eq is used so we can disregard homing data
-            .filter(|e| variant_eq(&e.state, &EnemyState::Homing(EnemyStateHoming {})))
-        {
-            if enemy.overlaps(&self.player.collision_rect) {
-                let player_invisible =
-                    variant_eq(&self.player.state, &PlayerState::Invisible(0f32));
-                if !player_invisible {
-                    self.player_lives -= 1;
-                    resources.play_sound(SoundIdentifier::PlayerOuch, sound_mixer, Volume(1.0f32));
-                    self.player
-                        .process_command_optional(Some(PlayerCommand::ChangeState(
-                            PlayerState::Invisible(PLAYER_TIME_INVISBLE),
-                        )));
-                    enemy.state_shared.health = 0;
-                }
-            }
+        for impact in self.world.impacts.drain() {
+            self.juice.impact(impact, settings);
         }
 
-        // todo explain
-        let mut death_methods =
-            Vec::<(Vec2, EnemyDeathMethod, EnemyType, EnemyColor)>::with_capacity(4);
+        for sound_request in self.world.sounds.drain() {
+            resources.play_sound(
+                sound_request.identifier,
+                sound_mixer,
+                Volume(sound_request.volume),
+            );
+        }
 
-        // bullets hurting enemies
-        for bullet in self
-            .bullets
-            .iter_mut()
-            .filter(|b| b.hurt_type == BulletHurtType::Enemy)
-        {
-            for enemy in self.enemies.iter_mut() {
-                if enemy.overlaps(&bullet.collision_rect) && !bullet.is_kill {
-                    enemy.state_shared.health -= 1;
-                    self.wave_manager.last_enemy_death_reason = LastEnemyDeathReason::Player;
-                    // death
-                    if enemy.state_shared.health <= 0 {
-                        resources.play_sound(
-                            SoundIdentifier::EnemyOuch,
-                            sound_mixer,
-                            Volume(1.0f32),
-                        );
-                        death_methods.push((
-                            enemy.state_shared.pos,
-                            enemy.state_shared.death_method,
-                            enemy.state_shared.enemy_type,
-                            enemy.state_shared.enemy_color,
-                        ));
+        if let Some(world_message) = world_message_optional {
+            match world_message {
+                WorldMessage::GameOver | WorldMessage::CampaignCleared => {
+                    if self.playback_optional.is_none() {
+                        self.save_recording();
                     }
-                    // can only hurt one enemy, flag for deletion
-                    bullet.is_kill = true;
+                    return self.to_menu();
                 }
             }
         }
-
-        for (pos, death_method, enemy_type, enemy_color) in death_methods.iter() {
-            let score_add = match enemy_type {
-                EnemyType::Normal => SCORE_NORMAL,
-                EnemyType::Mini => SCORE_MINI,
-            };
-            self.player_score += score_add;
-            match death_method {
-                EnemyDeathMethod::None => {}
-                EnemyDeathMethod::SpawnChildren(amount) => {
-                    resources.play_sound(SoundIdentifier::SpawnMini, sound_mixer, Volume(1.0f32));
-                    let spawn_width = 20f32;
-                    let step = 1. / (*amount as f32);
-                    for i in 0..*amount {
-                        let spawn_pos = *pos + vec2(step * spawn_width * i as f32, 0f32);
-                        spawn_enemy(
-                            &mut self.enemies,
-                            resources,
-                            SpawnBlueprint::Mini(spawn_pos),
-                            *enemy_color,
-                        );
-                    }
-                }
+        if let Some(playback) = &self.playback_optional {
+            if playback.is_finished() {
+                return self.to_menu();
             }
         }
+        None
+    }
+
+    fn draw(&self, resources: &Resources) {
+        self.background.draw();
+
+        for enemy in self.world.enemies.iter() {
+            enemy.draw(resources, self.interpolation);
+        }
+
+        for bullet in self.world.bullets.iter() {
+            bullet.draw(resources, self.interpolation);
+        }
+
+        for pickup in self.world.pickups.iter() {
+            pickup.draw(resources, self.interpolation);
+        }
 
-        // remove bullets that hit something
-        self.bullets.retain(|e| !e.is_kill);
-        // remove dead enemies
-        self.enemies.retain(|e| e.state_shared.health > 0);
+        self.world.particles.draw(self.interpolation);
 
-        draw_texture_ex(
+        draw_region_ex(
             resources.ground_bg,
             0f32,
             GAME_SIZE_Y as f32 - resources.ground_bg.height(),
@@ -307,141 +602,318 @@ impl GameState for GameStateGame {
             },
         );
 
-        draw_lives(
-            &self.player_lives,
-            resources.life,
-            &resources.ground_bg,
-            &self.wave_manager,
-        );
+        for player in self.world.players.iter() {
+            draw_lives(
+                player,
+                resources.life,
+                &resources.ground_bg,
+                &self.world.wave_manager,
+                self.world.time,
+            );
+            draw_power_ups(player, resources, &self.world.tuning, self.world.time);
+        }
 
-        self.player
-            .update(dt, &mut self.bullets, resources, sound_mixer);
-        self.player.draw();
-        None
+        for player in self.world.players.iter().filter(|player| player.is_alive()) {
+            player.draw(resources, &self.world.tuning, self.interpolation);
+        }
+
+        for enemy in self.world.enemies.iter() {
+            enemy.draw_health_bar();
+        }
     }
 
-    fn draw(&self, _resources: &Resources) {}
+    fn draw_unscaled(&self, resources: &Resources, viewport: &Viewport) {
+        let top_left = viewport.game_to_window(Vec2::ZERO);
+        let center = viewport.game_to_window(vec2(GAME_CENTER_X, GAME_CENTER_Y));
+        let scaled_game_size = viewport.size();
 
-    fn draw_unscaled(&self, resources: &Resources) {
-        let game_diff_w = screen_width() / GAME_SIZE_X as f32;
-        let game_diff_h = screen_height() / GAME_SIZE_Y as f32;
-        let aspect_diff = game_diff_w.min(game_diff_h);
+        let font_size = viewport.font_size(10f32);
+        // spread over the top like the cannons are over the ground, co-op scores have their player's tint
+        let player_count = self.world.players.len();
+        for player in self.world.players.iter() {
+            let score_text = format!("{}", player.score);
+            let column = (player.index + 1) as f32 / (player_count + 1) as f32;
+            let mut text_x = top_left.x + scaled_game_size.x * column;
+            text_x -= score_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+            let color = if player_count == 1 {
+                YELLOW
+            } else {
+                player.tint()
+            };
+            draw_text_ex(
+                score_text.as_ref(),
+                text_x,
+                top_left.y + font_size as f32 * 2f32,
+                TextParams {
+                    font: resources.font,
+                    font_size,
+                    font_scale: 1f32,
+                    color,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
+
+        // small at the very top, so it's clear what rules the score is played with
+        if self.world.variants != GameVariants::default() {
+            let variants_font_size = viewport.font_size(6f32);
+            let variants_text = self.world.variants.label();
+            let mut text_x = center.x;
+            text_x -= variants_text.len() as f32 * 0.5f32 * variants_font_size as f32 * 0.6f32;
+            draw_text_ex(
+                variants_text.as_ref(),
+                text_x,
+                top_left.y + variants_font_size as f32 * 1.2f32,
+                TextParams {
+                    font: resources.font,
+                    font_size: variants_font_size,
+                    font_scale: 1f32,
+                    color: GRAY,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
 
-        let scaled_game_size_w = GAME_SIZE_X as f32 * aspect_diff;
-        let scaled_game_size_h = GAME_SIZE_Y as f32 * aspect_diff;
+        if let Some(playback) = &self.playback_optional {
+            let replay_text = if playback.paused {
+                "REPLAY PAUSED".to_owned()
+            } else if playback.speed > 1f32 {
+                format!("REPLAY x{}", playback.speed)
+            } else {
+                "REPLAY".to_owned()
+            };
+            draw_text_ex(
+                replay_text.as_ref(),
+                top_left.x + font_size as f32,
+                top_left.y + font_size as f32 * 2f32,
+                TextParams {
+                    font: resources.font,
+                    font_size,
+                    font_scale: 1f32,
+                    color: WHITE,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
 
-        let width_padding = (screen_width() - scaled_game_size_w) * 0.5f32;
-        let height_padding = (screen_height() - scaled_game_size_h) * 0.5f32;
+        // blinks in the middle of the screen while a boss flies in or dies
+        let banner_optional = self
+            .world
+            .enemies
+            .iter()
+            .find_map(|enemy| enemy.boss_banner());
+        if let Some(banner) = banner_optional {
+            let banner_visible = (self.world.time * 4f32) as i32 % 2 == 0;
+            if banner_visible {
+                let banner_font_size = viewport.font_size(8f32);
+                let text_size = measure_text(banner, Some(resources.font), banner_font_size, 1f32);
+                draw_text_ex(
+                    banner,
+                    top_left.x + (scaled_game_size.x - text_size.width) * 0.5f32,
+                    center.y,
+                    TextParams {
+                        font: resources.font,
+                        font_size: banner_font_size,
+                        font_scale: 1f32,
+                        color: RED,
+                        font_scale_aspect: 1f32,
+                    },
+                );
+            }
+        }
 
-        let score_text = format!("{}", self.player_score);
-        let font_size = (aspect_diff * 10f32) as u16;
-        let mut text_x = width_padding + scaled_game_size_w * 0.5f32;
-        text_x -= score_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
-        draw_text_ex(
-            score_text.as_ref(),
-            text_x,
-            height_padding + font_size as f32 * 2f32,
-            TextParams {
-                font: resources.font,
-                font_size,
-                font_scale: 1f32,
-                color: YELLOW,
-                font_scale_aspect: 1f32,
-            },
-        );
+        if let Some(touch_buttons) = self.touch_buttons_optional {
+            draw_touch_controls(touch_buttons, resources.font, viewport);
+        }
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum VariantsMenuItem {
+    StartingLives,
+    TracerShots,
+    GuidedDemonShots,
+    FastDemonShots,
+    NoExtraLives,
+    OneHit,
+    Campaign,
+    Back,
+}
+
+const VARIANTS_MENU_ITEMS: &[VariantsMenuItem] = &[
+    VariantsMenuItem::StartingLives,
+    VariantsMenuItem::TracerShots,
+    VariantsMenuItem::GuidedDemonShots,
+    VariantsMenuItem::FastDemonShots,
+    VariantsMenuItem::NoExtraLives,
+    VariantsMenuItem::OneHit,
+    VariantsMenuItem::Campaign,
+    VariantsMenuItem::Back,
+];
+
 pub struct GameStateMenu {
     last_score_optional: Option<i32>,
+    // the last game played or watched, can be watched (again)
+    last_replay_optional: Option<Replay>,
+    high_scores: HighScores,
+    // the leaderboard is shown instead of the start text
+    show_high_scores: bool,
+    // the entry that was just added, drawn highlighted
+    new_high_score_index_optional: Option<usize>,
+    // copy of the variants in the settings, for drawing
+    variants: GameVariants,
+    // the variants screen is shown instead of the start text
+    show_variants: bool,
+    selected_variant: usize,
+    // the first wave's look, scrolling behind the text
+    background: Background,
+    // the tuning's, the starting lives variant goes up to it
+    player_lives_max: i32,
 }
 
 impl GameStateMenu {
-    pub fn new() -> Self {
+    pub fn new(tuning: &Tuning) -> Self {
         GameStateMenu {
             last_score_optional: None,
+            last_replay_optional: None,
+            high_scores: HighScores::load(),
+            show_high_scores: false,
+            new_high_score_index_optional: None,
+            variants: GameVariants::default(),
+            show_variants: false,
+            selected_variant: 0,
+            background: Background::new(),
+            player_lives_max: tuning.player_lives_max,
         }
     }
-}
-
-impl GameState for GameStateMenu {
-    fn update(
-        &mut self,
-        _dt: f32,
-        _resources: &Resources,
-        _sound_mixer: &mut SoundMixer,
-    ) -> Option<GameStateCommand> {
-        if is_key_pressed(KEY_START_GAME) {
-            return Some(GameStateCommand::ChangeState(
-                GameStateIdentifier::Game,
-                None,
-            ));
-        }
-        None
-    }
 
-    fn draw(&self, resources: &Resources) {
-        draw_texture_ex(
-            resources.ground_bg,
-            0f32,
-            GAME_SIZE_Y as f32 - resources.ground_bg.height(),
-            WHITE,
-            DrawTextureParams {
-                //dest_size: Some(vec2(screen_width(), screen_height())),
-                dest_size: Some(Vec2::new(GAME_SIZE_X as f32, resources.ground_bg.height())),
-                ..Default::default()
+    fn variant_text(&self, item: VariantsMenuItem) -> String {
+        let on_off = |on: bool| if on { "ON" } else { "OFF" };
+        match item {
+            VariantsMenuItem::StartingLives => match self.variants.starting_lives_optional {
+                Some(lives) => format!("STARTING LIVES: {}", lives),
+                None => "STARTING LIVES: DEFAULT".to_owned(),
             },
-        );
-    }
-
-    fn on_enter(&mut self, _resources: &Resources, payload_optional: Option<ChangeStatePayload>) {
-        if let Some(payload) = payload_optional {
-            match payload {
-                ChangeStatePayload::MenuPayload(menu_payload) => {
-                    self.last_score_optional = Some(menu_payload.score)
+            VariantsMenuItem::TracerShots => {
+                format!("TRACER SHOTS: {}", on_off(self.variants.tracer_shots))
+            }
+            VariantsMenuItem::GuidedDemonShots => format!(
+                "GUIDED DEMON SHOTS: {}",
+                on_off(self.variants.guided_demon_shots)
+            ),
+            VariantsMenuItem::FastDemonShots => {
+                format!(
+                    "FAST DEMON SHOTS: {}",
+                    on_off(self.variants.fast_demon_shots)
+                )
+            }
+            VariantsMenuItem::NoExtraLives => {
+                format!("NO EXTRA LIVES: {}", on_off(self.variants.no_extra_lives))
+            }
+            VariantsMenuItem::OneHit => format!("ONE HIT: {}", on_off(self.variants.one_hit)),
+            VariantsMenuItem::Campaign => {
+                if self.variants.campaign {
+                    format!("WAVES: {}", CAMPAIGN_WAVE_COUNT)
+                } else {
+                    "WAVES: ENDLESS".to_owned()
                 }
             }
+            VariantsMenuItem::Back => "BACK".to_owned(),
         }
     }
 
-    fn draw_unscaled(&self, resources: &Resources) {
-        let game_diff_w = screen_width() / GAME_SIZE_X as f32;
-        let game_diff_h = screen_height() / GAME_SIZE_Y as f32;
-        let aspect_diff = game_diff_w.min(game_diff_h);
-
-        let scaled_game_size_w = GAME_SIZE_X as f32 * aspect_diff;
-        let scaled_game_size_h = GAME_SIZE_Y as f32 * aspect_diff;
-
-        let width_padding = (screen_width() - scaled_game_size_w) * 0.5f32;
-        let height_padding = (screen_height() - scaled_game_size_h) * 0.5f32;
+    // up/down picks a variant, left/right or confirm changes it
+    fn update_variants(&mut self, settings: &mut Settings, gamepads: &Gamepads) {
+        let item_count = VARIANTS_MENU_ITEMS.len();
+        if is_key_pressed(KEY_MENU_UP) || gamepads.any_pressed(None, PAD_MENU_UP) {
+            self.selected_variant = (self.selected_variant + item_count - 1) % item_count;
+        }
+        if is_key_pressed(KEY_MENU_DOWN) || gamepads.any_pressed(None, PAD_MENU_DOWN) {
+            self.selected_variant = (self.selected_variant + 1) % item_count;
+        }
+        if is_key_pressed(KEY_MENU_BACK)
+            || is_key_pressed(KEY_VARIANTS)
+            || gamepads.any_pressed(None, PAD_MENU_BACK)
+        {
+            self.show_variants = false;
+            return;
+        }
+        let left = is_key_pressed(KEY_MENU_LEFT) || gamepads.any_pressed(None, PAD_MENU_LEFT);
+        let right = is_key_pressed(KEY_MENU_RIGHT)
+            || gamepads.any_pressed(None, PAD_MENU_RIGHT)
+            || is_key_pressed(KEY_MENU_CONFIRM)
+            || gamepads.any_pressed(None, PAD_MENU_CONFIRM);
+        if !left && !right {
+            return;
+        }
 
-        let font_size = (aspect_diff * 10f32) as u16;
+        let variants = &mut settings.variants;
+        match VARIANTS_MENU_ITEMS[self.selected_variant] {
+            VariantsMenuItem::StartingLives => {
+                variants.next_starting_lives(right, self.player_lives_max)
+            }
+            VariantsMenuItem::TracerShots => variants.tracer_shots = !variants.tracer_shots,
+            VariantsMenuItem::GuidedDemonShots => {
+                variants.guided_demon_shots = !variants.guided_demon_shots
+            }
+            VariantsMenuItem::FastDemonShots => {
+                variants.fast_demon_shots = !variants.fast_demon_shots
+            }
+            VariantsMenuItem::NoExtraLives => variants.no_extra_lives = !variants.no_extra_lives,
+            VariantsMenuItem::OneHit => variants.one_hit = !variants.one_hit,
+            VariantsMenuItem::Campaign => variants.campaign = !variants.campaign,
+            VariantsMenuItem::Back => {
+                self.show_variants = false;
+                return;
+            }
+        }
+        // picked variants are kept for the next time the game runs
+        self.variants = settings.variants;
+        settings.save();
+    }
 
-        if let Some(last_score) = self.last_score_optional {
-            let score_text = format!("{}", last_score);
-            let mut text_x = width_padding + scaled_game_size_w * 0.5f32;
-            text_x -= score_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+    fn draw_variants(&self, resources: &Resources, viewport: &Viewport, font_size: u16) {
+        let top_left = viewport.game_to_window(Vec2::ZERO);
+        let center = viewport.game_to_window(vec2(GAME_CENTER_X, GAME_CENTER_Y));
+        let mut texts = vec![("VARIANTS".to_owned(), 2f32, YELLOW)];
+        for (i, item) in VARIANTS_MENU_ITEMS.iter().enumerate() {
+            let color = if i == self.selected_variant {
+                YELLOW
+            } else {
+                WHITE
+            };
+            texts.push((self.variant_text(*item), 3.6f32 + i as f32 * 1.1f32, color));
+        }
+        for (text, line, color) in texts.iter() {
+            let mut text_x = center.x;
+            text_x -= text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
             draw_text_ex(
-                score_text.as_ref(),
+                text.as_ref(),
                 text_x,
-                height_padding + font_size as f32 * 2f32,
+                top_left.y + font_size as f32 * line,
                 TextParams {
                     font: resources.font,
                     font_size,
                     font_scale: 1f32,
-                    color: YELLOW,
+                    color: *color,
                     font_scale_aspect: 1f32,
                 },
             );
         }
-        let start_text = "TAP SPACE TO START";
-        let mut text_x = width_padding + scaled_game_size_w * 0.5f32;
-        text_x -= start_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+    }
 
+    fn draw_high_scores(&self, resources: &Resources, viewport: &Viewport, font_size: u16) {
+        let top_left = viewport.game_to_window(Vec2::ZERO);
+        let center = viewport.game_to_window(vec2(GAME_CENTER_X, GAME_CENTER_Y));
+        let scaled_game_size = viewport.size();
+        let row_font_size = (font_size as f32 * 0.7f32) as u16;
+        let title = "HIGH SCORES";
+        let mut text_x = center.x;
+        text_x -= title.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
         draw_text_ex(
-            start_text,
+            title,
             text_x,
-            screen_height() * 0.5f32,
+            top_left.y + font_size as f32 * 2f32,
             TextParams {
                 font: resources.font,
                 font_size,
@@ -450,21 +922,1055 @@ impl GameState for GameStateMenu {
                 font_scale_aspect: 1f32,
             },
         );
+
+        // every combination of variants has its own table, the one picked right now is shown
+        let table = self.high_scores.table(&self.variants);
+        let variants_text = self.variants.label();
+        let mut text_x = center.x;
+        text_x -= variants_text.len() as f32 * 0.5f32 * row_font_size as f32 * 0.6f32;
+        draw_text_ex(
+            variants_text.as_ref(),
+            text_x,
+            top_left.y + font_size as f32 * 11.4f32,
+            TextParams {
+                font: resources.font,
+                font_size: row_font_size,
+                font_scale: 1f32,
+                color: GRAY,
+                font_scale_aspect: 1f32,
+            },
+        );
+
+        if table.is_empty() {
+            let empty_text = "NO SCORES YET";
+            let mut text_x = center.x;
+            text_x -= empty_text.len() as f32 * 0.5f32 * row_font_size as f32 * 0.6f32;
+            draw_text_ex(
+                empty_text,
+                text_x,
+                center.y,
+                TextParams {
+                    font: resources.font,
+                    font_size: row_font_size,
+                    font_scale: 1f32,
+                    color: WHITE,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
+
+        // rank, name, score, wave and date each get a column, as fractions of the game width
+        let columns = [0.12f32, 0.2f32, 0.36f32, 0.56f32, 0.68f32];
+        for (i, entry) in table.iter().enumerate() {
+            let color = if self.new_high_score_index_optional == Some(i) {
+                YELLOW
+            } else {
+                WHITE
+            };
+            let row_y =
+                top_left.y + font_size as f32 * 3.2f32 + i as f32 * row_font_size as f32 * 1.1f32;
+            let texts = [
+                format!("{}.", i + 1),
+                entry.name.clone(),
+                format!("{}", entry.score),
+                format!("W{}", entry.wave),
+                entry.date.clone(),
+            ];
+            for (text, column) in texts.iter().zip(columns.iter()) {
+                draw_text_ex(
+                    text.as_ref(),
+                    top_left.x + scaled_game_size.x * column,
+                    row_y,
+                    TextParams {
+                        font: resources.font,
+                        font_size: row_font_size,
+                        font_scale: 1f32,
+                        color,
+                        font_scale_aspect: 1f32,
+                    },
+                );
+            }
+        }
     }
 }
 
-pub struct GameManager {
-    states: HashMap<GameStateIdentifier, Box<dyn GameState>>,
-    current_state_identifier: GameStateIdentifier,
-    resources: Resources,
-    sound_mixer: SoundMixer,
-}
+impl GameState for GameStateMenu {
+    fn update(
+        &mut self,
+        dt: f32,
+        _resources: &Resources,
+        _sound_mixer: &mut SoundMixer,
+        settings: &mut Settings,
+        gamepads: &Gamepads,
+        touch: &TouchControls,
+    ) -> Option<GameStateCommand> {
+        self.background.update(dt, 1);
+        self.variants = settings.variants;
+        if self.show_variants {
+            self.update_variants(settings, gamepads);
+            return None;
+        }
+        // on a touch screen anywhere will do
+        if settings
+            .bindings
+            .is_pressed(Action::Start, gamepads, Some(0))
+            || touch.tapped
+        {
+            return Some(GameStateCommand::ChangeState(
+                GameStateIdentifier::Game,
+                Some(ChangeStatePayload::NewGamePayload(1, settings.variants)),
+            ));
+        }
+        // player two pressing start joins in, starting a co-op game
+        if settings
+            .player_two_bindings
+            .is_pressed(Action::Start, gamepads, Some(1))
+        {
+            return Some(GameStateCommand::ChangeState(
+                GameStateIdentifier::Game,
+                Some(ChangeStatePayload::NewGamePayload(2, settings.variants)),
+            ));
+        }
+        if is_key_pressed(KEY_HIGH_SCORES) {
+            self.show_high_scores = !self.show_high_scores;
+        }
+        if is_key_pressed(KEY_VARIANTS) {
+            self.show_variants = true;
+            self.show_high_scores = false;
+            self.selected_variant = 0;
+        }
+        if is_key_pressed(KEY_CONTROLS) {
+            return Some(GameStateCommand::ChangeState(
+                GameStateIdentifier::Controls,
+                Some(ChangeStatePayload::ControlsPayload(
+                    GameStateIdentifier::Menu,
+                )),
+            ));
+        }
+        if is_key_pressed(KEY_WATCH_REPLAY) {
+            if let Some(replay) = &self.last_replay_optional {
+                return Some(GameStateCommand::ChangeState(
+                    GameStateIdentifier::Game,
+                    Some(ChangeStatePayload::ReplayPayload(replay.clone())),
+                ));
+            }
+        }
+        None
+    }
+
+    fn draw(&self, resources: &Resources) {
+        self.background.draw();
+
+        draw_region_ex(
+            resources.ground_bg,
+            0f32,
+            GAME_SIZE_Y as f32 - resources.ground_bg.height(),
+            WHITE,
+            DrawTextureParams {
+                //dest_size: Some(vec2(screen_width(), screen_height())),
+                dest_size: Some(Vec2::new(GAME_SIZE_X as f32, resources.ground_bg.height())),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn on_tuning_reloaded(&mut self, tuning: &Tuning) {
+        self.player_lives_max = tuning.player_lives_max;
+    }
+
+    fn on_enter(&mut self, _resources: &Resources, payload_optional: Option<ChangeStatePayload>) {
+        if let Some(ChangeStatePayload::MenuPayload(menu_payload)) = payload_optional {
+            self.last_score_optional = Some(menu_payload.score);
+            self.last_replay_optional = if menu_payload.replay_watchable {
+                Some(menu_payload.replay)
+            } else {
+                None
+            };
+            self.new_high_score_index_optional = menu_payload.high_score_index_optional;
+            self.show_high_scores = self.new_high_score_index_optional.is_some();
+        }
+        self.show_variants = false;
+        self.high_scores = HighScores::load();
+    }
+
+    fn draw_unscaled(&self, resources: &Resources, viewport: &Viewport) {
+        let top_left = viewport.game_to_window(Vec2::ZERO);
+        let center = viewport.game_to_window(vec2(GAME_CENTER_X, GAME_CENTER_Y));
+        let scaled_game_size = viewport.size();
+
+        let font_size = viewport.font_size(10f32);
+
+        if self.show_variants {
+            self.draw_variants(resources, viewport, font_size);
+            return;
+        }
+
+        if self.show_high_scores {
+            self.draw_high_scores(resources, viewport, font_size);
+            let close_text = "TAP H TO CLOSE";
+            let mut text_x = center.x;
+            text_x -= close_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+            draw_text_ex(
+                close_text,
+                text_x,
+                top_left.y + scaled_game_size.y - font_size as f32 * 0.5f32,
+                TextParams {
+                    font: resources.font,
+                    font_size,
+                    font_scale: 1f32,
+                    color: YELLOW,
+                    font_scale_aspect: 1f32,
+                },
+            );
+            return;
+        }
+
+        if let Some(last_score) = self.last_score_optional {
+            let score_text = format!("{}", last_score);
+            let mut text_x = center.x;
+            text_x -= score_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+            draw_text_ex(
+                score_text.as_ref(),
+                text_x,
+                top_left.y + font_size as f32 * 2f32,
+                TextParams {
+                    font: resources.font,
+                    font_size,
+                    font_scale: 1f32,
+                    color: YELLOW,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
+        let variants_font_size = (font_size as f32 * 0.7f32) as u16;
+        let variants_text = format!("V VARIANTS: {}", self.variants.label());
+        let mut text_x = center.x;
+        text_x -= variants_text.len() as f32 * 0.5f32 * variants_font_size as f32 * 0.6f32;
+        draw_text_ex(
+            variants_text.as_ref(),
+            text_x,
+            center.y - font_size as f32 * 2f32,
+            TextParams {
+                font: resources.font,
+                font_size: variants_font_size,
+                font_scale: 1f32,
+                color: WHITE,
+                font_scale_aspect: 1f32,
+            },
+        );
+
+        let start_text = "TAP SPACE TO START";
+        let mut text_x = center.x;
+        text_x -= start_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+
+        draw_text_ex(
+            start_text,
+            text_x,
+            center.y,
+            TextParams {
+                font: resources.font,
+                font_size,
+                font_scale: 1f32,
+                color: YELLOW,
+                font_scale_aspect: 1f32,
+            },
+        );
+
+        let coop_text = "TAP 2 FOR TWO PLAYERS";
+        let mut text_x = center.x;
+        text_x -= coop_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+        draw_text_ex(
+            coop_text,
+            text_x,
+            center.y + font_size as f32 * 2f32,
+            TextParams {
+                font: resources.font,
+                font_size,
+                font_scale: 1f32,
+                color: YELLOW,
+                font_scale_aspect: 1f32,
+            },
+        );
+
+        let high_scores_text = "H HIGH SCORES  C CONTROLS";
+        let mut text_x = center.x;
+        text_x -= high_scores_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+        draw_text_ex(
+            high_scores_text,
+            text_x,
+            center.y + font_size as f32 * 6f32,
+            TextParams {
+                font: resources.font,
+                font_size,
+                font_scale: 1f32,
+                color: YELLOW,
+                font_scale_aspect: 1f32,
+            },
+        );
+
+        if self.last_replay_optional.is_some() {
+            let replay_text = "TAP R TO WATCH REPLAY";
+            let mut text_x = center.x;
+            text_x -= replay_text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+            draw_text_ex(
+                replay_text,
+                text_x,
+                center.y + font_size as f32 * 4f32,
+                TextParams {
+                    font: resources.font,
+                    font_size,
+                    font_scale: 1f32,
+                    color: YELLOW,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
+    }
+}
+
+// after a game over with a score good enough for the high score table
+pub struct GameStateNameEntry {
+    letters: [u8; HIGH_SCORE_NAME_LENGTH],
+    // the letter being picked
+    cursor: usize,
+    // the finished game, handed on to the menu once the name is entered
+    menu_payload_optional: Option<MenuPayload>,
+}
+
+impl GameStateNameEntry {
+    pub fn new() -> Self {
+        GameStateNameEntry {
+            letters: [b'A'; HIGH_SCORE_NAME_LENGTH],
+            cursor: 0,
+            menu_payload_optional: None,
+        }
+    }
+
+    fn name(&self) -> String {
+        self.letters.iter().map(|letter| *letter as char).collect()
+    }
+}
+
+impl Default for GameStateNameEntry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameState for GameStateNameEntry {
+    fn update(
+        &mut self,
+        _dt: f32,
+        _resources: &Resources,
+        _sound_mixer: &mut SoundMixer,
+        _settings: &mut Settings,
+        gamepads: &Gamepads,
+        touch: &TouchControls,
+    ) -> Option<GameStateCommand> {
+        let letter = &mut self.letters[self.cursor];
+        if is_key_pressed(KEY_NAME_LETTER_NEXT) || gamepads.any_pressed(None, PAD_MENU_UP) {
+            *letter = if *letter >= b'Z' { b'A' } else { *letter + 1 };
+        }
+        if is_key_pressed(KEY_NAME_LETTER_PREVIOUS) || gamepads.any_pressed(None, PAD_MENU_DOWN) {
+            *letter = if *letter <= b'A' { b'Z' } else { *letter - 1 };
+        }
+        let cursor_left =
+            is_key_pressed(KEY_NAME_CURSOR_LEFT) || gamepads.any_pressed(None, PAD_MENU_LEFT);
+        let cursor_right =
+            is_key_pressed(KEY_NAME_CURSOR_RIGHT) || gamepads.any_pressed(None, PAD_MENU_RIGHT);
+        if cursor_left && self.cursor > 0 {
+            self.cursor -= 1;
+        }
+        if cursor_right && self.cursor < HIGH_SCORE_NAME_LENGTH - 1 {
+            self.cursor += 1;
+        }
+        // a tap confirms letter by letter, so phones without a keyboard still get through
+        let confirm = is_key_pressed(KEY_NAME_CONFIRM)
+            || gamepads.any_pressed(None, PAD_MENU_CONFIRM)
+            || touch.tapped;
+        if !confirm {
+            return None;
+        }
+        if self.cursor < HIGH_SCORE_NAME_LENGTH - 1 {
+            self.cursor += 1;
+            return None;
+        }
+
+        let mut menu_payload_optional = self.menu_payload_optional.take();
+        if let Some(menu_payload) = &mut menu_payload_optional {
+            let mut high_scores = HighScores::load();
+            menu_payload.high_score_index_optional = high_scores.insert(HighScoreEntry {
+                name: self.name(),
+                score: menu_payload.score,
+                wave: menu_payload.wave,
+                date: today(),
+                seed: menu_payload.replay.seed,
+                variants: menu_payload.replay.variants,
+            });
+            high_scores.save();
+        }
+        Some(GameStateCommand::ChangeState(
+            GameStateIdentifier::Menu,
+            menu_payload_optional.map(ChangeStatePayload::MenuPayload),
+        ))
+    }
+
+    fn draw(&self, resources: &Resources) {
+        draw_region_ex(
+            resources.ground_bg,
+            0f32,
+            GAME_SIZE_Y as f32 - resources.ground_bg.height(),
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(GAME_SIZE_X as f32, resources.ground_bg.height())),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn on_enter(&mut self, _resources: &Resources, payload_optional: Option<ChangeStatePayload>) {
+        // the letters are kept from the last entry, most of the time it's the same player
+        self.cursor = 0;
+        self.menu_payload_optional = match payload_optional {
+            Some(ChangeStatePayload::MenuPayload(menu_payload)) => Some(menu_payload),
+            _ => None,
+        };
+    }
+
+    fn draw_unscaled(&self, resources: &Resources, viewport: &Viewport) {
+        let top_left = viewport.game_to_window(Vec2::ZERO);
+        let center = viewport.game_to_window(vec2(GAME_CENTER_X, GAME_CENTER_Y));
+
+        let font_size = viewport.font_size(10f32);
+
+        let mut texts = vec![("NEW HIGH SCORE".to_owned(), 2f32)];
+        if let Some(menu_payload) = &self.menu_payload_optional {
+            texts.push((format!("{}", menu_payload.score), 3.5f32));
+        }
+        texts.push(("UP DOWN TO PICK, ENTER TO CONFIRM".to_owned(), 10f32));
+        for (text, line) in texts.iter() {
+            let mut text_x = center.x;
+            text_x -= text.len() as f32 * 0.5f32 * font_size as f32 * 0.6f32;
+            draw_text_ex(
+                text.as_ref(),
+                text_x,
+                top_left.y + font_size as f32 * line,
+                TextParams {
+                    font: resources.font,
+                    font_size,
+                    font_scale: 1f32,
+                    color: YELLOW,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
+
+        // the letters are drawn big, the one being picked blinks
+        let letter_font_size = font_size * 2;
+        let letter_spacing = letter_font_size as f32 * 1.2f32;
+        let letters_x = center.x - letter_spacing * HIGH_SCORE_NAME_LENGTH as f32 * 0.5f32;
+        for (i, letter) in self.letters.iter().enumerate() {
+            let blink = (get_time() * 4f64) as i32 % 2 == 0;
+            let color = if i == self.cursor && blink {
+                YELLOW
+            } else {
+                WHITE
+            };
+            let letter_text = (*letter as char).to_string();
+            draw_text_ex(
+                letter_text.as_ref(),
+                letters_x + i as f32 * letter_spacing,
+                center.y + letter_font_size as f32 * 0.5f32,
+                TextParams {
+                    font: resources.font,
+                    font_size: letter_font_size,
+                    font_scale: 1f32,
+                    color,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PauseMenuItem {
+    Resume,
+    Restart,
+    Options,
+    QuitToMenu,
+    // options page
+    PauseOnFocusLoss,
+    ScreenShake,
+    HitStop,
+    Crt,
+    Display,
+    Fullscreen,
+    Border,
+    Controls,
+    Back,
+}
+
+const PAUSE_MENU_ITEMS: &[PauseMenuItem] = &[
+    PauseMenuItem::Resume,
+    PauseMenuItem::Restart,
+    PauseMenuItem::Options,
+    PauseMenuItem::QuitToMenu,
+];
+const PAUSE_OPTIONS_ITEMS: &[PauseMenuItem] = &[
+    PauseMenuItem::PauseOnFocusLoss,
+    PauseMenuItem::ScreenShake,
+    PauseMenuItem::HitStop,
+    PauseMenuItem::Crt,
+    PauseMenuItem::Display,
+    PauseMenuItem::Fullscreen,
+    PauseMenuItem::Border,
+    PauseMenuItem::Controls,
+    PauseMenuItem::Back,
+];
+
+// drawn over the frozen game, the game state isn't updated while this is the current state
+pub struct GameStatePause {
+    selected: usize,
+    // showing the options page instead of the pause menu
+    in_options: bool,
+    // copied from the settings every update so it can be drawn
+    pause_on_focus_loss: bool,
+    shake_intensity: f32,
+    hit_stop_intensity: f32,
+    crt_preset: CrtPreset,
+    // the CRT option is left out when its shader didn't compile
+    crt_available: bool,
+    viewport_mode: ViewportMode,
+    fullscreen: bool,
+    show_border: bool,
+}
+
+impl GameStatePause {
+    pub fn new() -> Self {
+        GameStatePause {
+            selected: 0,
+            in_options: false,
+            pause_on_focus_loss: true,
+            shake_intensity: 1f32,
+            hit_stop_intensity: 1f32,
+            crt_preset: CrtPreset::Off,
+            crt_available: true,
+            viewport_mode: ViewportMode::AspectFit,
+            fullscreen: false,
+            show_border: false,
+        }
+    }
+
+    fn on_off_text(on: bool) -> &'static str {
+        if on {
+            "ON"
+        } else {
+            "OFF"
+        }
+    }
+
+    fn intensity_text(intensity: f32) -> String {
+        if intensity <= 0f32 {
+            "OFF".to_owned()
+        } else {
+            format!("{}%", (intensity * 100f32).round())
+        }
+    }
+
+    // the next of JUICE_INTENSITY_STEPS, back to off after the strongest
+    fn next_intensity(intensity: f32) -> f32 {
+        JUICE_INTENSITY_STEPS
+            .iter()
+            .copied()
+            .find(|step| *step > intensity)
+            .unwrap_or(0f32)
+    }
+
+    fn items(&self) -> &'static [PauseMenuItem] {
+        if self.in_options {
+            PAUSE_OPTIONS_ITEMS
+        } else {
+            PAUSE_MENU_ITEMS
+        }
+    }
+
+    fn item_text(&self, item: PauseMenuItem) -> String {
+        match item {
+            PauseMenuItem::Resume => "RESUME".to_owned(),
+            PauseMenuItem::Restart => "RESTART".to_owned(),
+            PauseMenuItem::Options => "OPTIONS".to_owned(),
+            PauseMenuItem::QuitToMenu => "QUIT TO MENU".to_owned(),
+            PauseMenuItem::PauseOnFocusLoss => format!(
+                "PAUSE WHEN UNFOCUSED: {}",
+                Self::on_off_text(self.pause_on_focus_loss)
+            ),
+            PauseMenuItem::ScreenShake => format!(
+                "SCREEN SHAKE: {}",
+                Self::intensity_text(self.shake_intensity)
+            ),
+            PauseMenuItem::HitStop => {
+                format!(
+                    "HIT STOP: {}",
+                    Self::intensity_text(self.hit_stop_intensity)
+                )
+            }
+            PauseMenuItem::Crt => {
+                if self.crt_available {
+                    format!("CRT: {}", self.crt_preset.name())
+                } else {
+                    "CRT: NOT SUPPORTED".to_owned()
+                }
+            }
+            PauseMenuItem::Display => format!("DISPLAY: {}", self.viewport_mode.name()),
+            PauseMenuItem::Fullscreen => {
+                format!("FULLSCREEN: {}", Self::on_off_text(self.fullscreen))
+            }
+            PauseMenuItem::Border => format!("BORDER: {}", Self::on_off_text(self.show_border)),
+            PauseMenuItem::Controls => "CONTROLS".to_owned(),
+            PauseMenuItem::Back => "BACK".to_owned(),
+        }
+    }
+
+    fn close_options(&mut self) {
+        self.in_options = false;
+        self.selected = PAUSE_MENU_ITEMS
+            .iter()
+            .position(|item| *item == PauseMenuItem::Options)
+            .unwrap_or(0);
+    }
+}
+
+impl Default for GameStatePause {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameState for GameStatePause {
+    fn update(
+        &mut self,
+        _dt: f32,
+        resources: &Resources,
+        _sound_mixer: &mut SoundMixer,
+        settings: &mut Settings,
+        gamepads: &Gamepads,
+        touch: &TouchControls,
+    ) -> Option<GameStateCommand> {
+        self.pause_on_focus_loss = settings.pause_on_focus_loss;
+        self.crt_available = resources.crt_material_optional.is_some();
+        self.shake_intensity = settings.shake_intensity;
+        self.hit_stop_intensity = settings.hit_stop_intensity;
+        self.crt_preset = settings.crt_preset;
+        self.viewport_mode = settings.viewport_mode;
+        self.fullscreen = settings.fullscreen;
+        self.show_border = settings.show_border;
+        let item_count = self.items().len();
+        if is_key_pressed(KEY_MENU_UP) || gamepads.any_pressed(None, PAD_MENU_UP) {
+            self.selected = (self.selected + item_count - 1) % item_count;
+        }
+        if is_key_pressed(KEY_MENU_DOWN) || gamepads.any_pressed(None, PAD_MENU_DOWN) {
+            self.selected = (self.selected + 1) % item_count;
+        }
+        // the pause menu can't be navigated by touch, tapping anywhere resumes
+        if is_key_pressed(KEY_MENU_BACK)
+            || gamepads.any_pressed(None, PAD_MENU_BACK)
+            || (0..PLAYER_COUNT_MAX).any(|player| {
+                settings
+                    .bindings_for(player)
+                    .is_pressed(Action::Pause, gamepads, None)
+            })
+            || touch.tapped
+        {
+            if self.in_options {
+                self.close_options();
+                return None;
+            }
+            return Some(GameStateCommand::ChangeState(
+                GameStateIdentifier::Game,
+                Some(ChangeStatePayload::Resume),
+            ));
+        }
+        let confirm = is_key_pressed(KEY_MENU_CONFIRM)
+            || gamepads.any_pressed(None, PAD_MENU_CONFIRM)
+            || settings.bindings.is_pressed(Action::Start, gamepads, None);
+        if !confirm {
+            return None;
+        }
+
+        match self.items()[self.selected] {
+            PauseMenuItem::Resume => {
+                return Some(GameStateCommand::ChangeState(
+                    GameStateIdentifier::Game,
+                    Some(ChangeStatePayload::Resume),
+                ))
+            }
+            PauseMenuItem::Restart => {
+                return Some(GameStateCommand::ChangeState(
+                    GameStateIdentifier::Game,
+                    Some(ChangeStatePayload::Restart),
+                ))
+            }
+            PauseMenuItem::Options => {
+                self.in_options = true;
+                self.selected = 0;
+            }
+            PauseMenuItem::QuitToMenu => {
+                return Some(GameStateCommand::ChangeState(
+                    GameStateIdentifier::Menu,
+                    None,
+                ))
+            }
+            PauseMenuItem::PauseOnFocusLoss => {
+                settings.pause_on_focus_loss = !settings.pause_on_focus_loss;
+                self.pause_on_focus_loss = settings.pause_on_focus_loss;
+                settings.save();
+            }
+            PauseMenuItem::ScreenShake => {
+                settings.shake_intensity = Self::next_intensity(settings.shake_intensity);
+                self.shake_intensity = settings.shake_intensity;
+                settings.save();
+            }
+            PauseMenuItem::HitStop => {
+                settings.hit_stop_intensity = Self::next_intensity(settings.hit_stop_intensity);
+                self.hit_stop_intensity = settings.hit_stop_intensity;
+                settings.save();
+            }
+            PauseMenuItem::Crt if self.crt_available => {
+                settings.crt_preset = settings.crt_preset.next();
+                self.crt_preset = settings.crt_preset;
+                settings.save();
+            }
+            PauseMenuItem::Crt => {}
+            PauseMenuItem::Display => {
+                settings.viewport_mode = settings.viewport_mode.next();
+                self.viewport_mode = settings.viewport_mode;
+                settings.save();
+            }
+            PauseMenuItem::Fullscreen => {
+                settings.fullscreen = !settings.fullscreen;
+                self.fullscreen = settings.fullscreen;
+                apply_fullscreen(settings.fullscreen);
+                settings.save();
+            }
+            PauseMenuItem::Border => {
+                settings.show_border = !settings.show_border;
+                self.show_border = settings.show_border;
+                settings.save();
+            }
+            PauseMenuItem::Controls => {
+                return Some(GameStateCommand::ChangeState(
+                    GameStateIdentifier::Controls,
+                    Some(ChangeStatePayload::ControlsPayload(
+                        GameStateIdentifier::Pause,
+                    )),
+                ))
+            }
+            PauseMenuItem::Back => self.close_options(),
+        }
+        None
+    }
+
+    fn draw(&self, _resources: &Resources) {
+        // darken the frozen game below
+        draw_rectangle(
+            0f32,
+            0f32,
+            GAME_SIZE_X as f32,
+            GAME_SIZE_Y as f32,
+            Color::new(0f32, 0f32, 0f32, 0.6f32),
+        );
+    }
+
+    fn on_enter(&mut self, _resources: &Resources, payload_optional: Option<ChangeStatePayload>) {
+        // coming back from the controls screen keeps the options page open
+        if let Some(ChangeStatePayload::Resume) = payload_optional {
+            return;
+        }
+        self.selected = 0;
+        self.in_options = false;
+    }
+
+    fn draws_over_previous_state(&self) -> bool {
+        true
+    }
+
+    fn draw_unscaled(&self, resources: &Resources, viewport: &Viewport) {
+        let top_left = viewport.game_to_window(Vec2::ZERO);
+        let center = viewport.game_to_window(vec2(GAME_CENTER_X, GAME_CENTER_Y));
+
+        let font_size = viewport.font_size(10f32);
+        // the options page has more items, smaller and closer together so they fit
+        let (item_font_size, first_line, line_spacing) = if self.in_options {
+            ((font_size as f32 * 0.7f32) as u16, 4.4f32, 0.85f32)
+        } else {
+            (font_size, 5f32, 1.5f32)
+        };
+
+        let title = if self.in_options { "OPTIONS" } else { "PAUSED" };
+        let mut texts = vec![(title.to_owned(), 3f32, font_size, YELLOW)];
+        for (i, item) in self.items().iter().enumerate() {
+            let color = if i == self.selected { YELLOW } else { WHITE };
+            let line = first_line + i as f32 * line_spacing;
+            texts.push((self.item_text(*item), line, item_font_size, color));
+        }
+        for (text, line, text_font_size, color) in texts.iter() {
+            let mut text_x = center.x;
+            text_x -= text.len() as f32 * 0.5f32 * *text_font_size as f32 * 0.6f32;
+            draw_text_ex(
+                text.as_ref(),
+                text_x,
+                top_left.y + font_size as f32 * line,
+                TextParams {
+                    font: resources.font,
+                    font_size: *text_font_size,
+                    font_scale: 1f32,
+                    color: *color,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
+    }
+}
+
+// lists every action with its bindings, lets the player add and clear them
+pub struct GameStateControls {
+    // an action from ALL_ACTIONS, then reset and back
+    selected: usize,
+    // the next key pressed gets bound to the selected action
+    waiting_for_key: bool,
+    // where closing the screen goes back to
+    return_state: GameStateIdentifier,
+    // whose bindings are shown, 0 for player one
+    player: usize,
+    // copied from the settings every update so it can be drawn
+    bindings: Bindings,
+}
+
+impl GameStateControls {
+    pub fn new() -> Self {
+        GameStateControls {
+            selected: 0,
+            waiting_for_key: false,
+            return_state: GameStateIdentifier::Menu,
+            player: 0,
+            bindings: Bindings::default(),
+        }
+    }
+
+    // the two rows after the actions
+    fn reset_index() -> usize {
+        ALL_ACTIONS.len()
+    }
+
+    fn back_index() -> usize {
+        ALL_ACTIONS.len() + 1
+    }
+
+    fn close(&self) -> Option<GameStateCommand> {
+        Some(GameStateCommand::ChangeState(
+            self.return_state,
+            Some(ChangeStatePayload::Resume),
+        ))
+    }
+}
+
+impl Default for GameStateControls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameState for GameStateControls {
+    fn update(
+        &mut self,
+        _dt: f32,
+        _resources: &Resources,
+        _sound_mixer: &mut SoundMixer,
+        settings: &mut Settings,
+        gamepads: &Gamepads,
+        _touch: &TouchControls,
+    ) -> Option<GameStateCommand> {
+        let command_optional = self.update_menu(settings, gamepads);
+        self.bindings = settings.bindings_for(self.player).clone();
+        command_optional
+    }
+
+    fn draw(&self, _resources: &Resources) {
+        draw_rectangle(
+            0f32,
+            0f32,
+            GAME_SIZE_X as f32,
+            GAME_SIZE_Y as f32,
+            Color::new(0f32, 0f32, 0f32, 0.8f32),
+        );
+    }
+
+    fn on_enter(&mut self, _resources: &Resources, payload_optional: Option<ChangeStatePayload>) {
+        if let Some(ChangeStatePayload::ControlsPayload(return_state)) = payload_optional {
+            self.return_state = return_state;
+        }
+        self.selected = 0;
+        self.waiting_for_key = false;
+    }
+
+    fn draws_over_previous_state(&self) -> bool {
+        true
+    }
+
+    fn draw_unscaled(&self, resources: &Resources, viewport: &Viewport) {
+        let top_left = viewport.game_to_window(Vec2::ZERO);
+        let scaled_game_size = viewport.size();
+
+        let font_size = viewport.font_size(10f32);
+        let row_font_size = (font_size as f32 * 0.7f32) as u16;
+
+        // (text, x as a fraction of the game width, line, font size, color)
+        let title = format!("CONTROLS  < PLAYER {} >", self.player + 1);
+        let mut texts = vec![(title, 0.1f32, 2f32, font_size, YELLOW)];
+        for (i, action) in ALL_ACTIONS.iter().enumerate() {
+            let color = if i == self.selected { YELLOW } else { WHITE };
+            let bindings_text = if i == self.selected && self.waiting_for_key {
+                "PRESS A KEY OR BUTTON, ESCAPE CANCELS".to_owned()
+            } else if self.bindings.get(*action).is_empty() {
+                "-".to_owned()
+            } else {
+                self.bindings
+                    .get(*action)
+                    .iter()
+                    .map(|binding| binding.name())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            let line = 3.5f32 + i as f32;
+            texts.push((action.name().to_owned(), 0.1f32, line, row_font_size, color));
+            texts.push((bindings_text, 0.4f32, line, row_font_size, color));
+        }
+        for (i, text) in [
+            (Self::reset_index(), "RESET TO DEFAULTS"),
+            (Self::back_index(), "BACK"),
+        ]
+        .iter()
+        {
+            let color = if *i == self.selected { YELLOW } else { WHITE };
+            let line = 4f32 + *i as f32;
+            texts.push((text.to_string(), 0.1f32, line, row_font_size, color));
+        }
+        texts.push((
+            "ENTER ADDS A KEY OR BUTTON, BACKSPACE CLEARS".to_owned(),
+            0.1f32,
+            12f32,
+            row_font_size,
+            WHITE,
+        ));
+
+        for (text, column, line, text_font_size, color) in texts.iter() {
+            draw_text_ex(
+                text.as_ref(),
+                top_left.x + scaled_game_size.x * column,
+                top_left.y + font_size as f32 * line,
+                TextParams {
+                    font: resources.font,
+                    font_size: *text_font_size,
+                    font_scale: 1f32,
+                    color: *color,
+                    font_scale_aspect: 1f32,
+                },
+            );
+        }
+    }
+}
+
+impl GameStateControls {
+    fn update_menu(
+        &mut self,
+        settings: &mut Settings,
+        gamepads: &Gamepads,
+    ) -> Option<GameStateCommand> {
+        if self.waiting_for_key {
+            // a pad can bind any of its buttons, so only the keyboard can cancel
+            let binding_optional = if is_key_pressed(KEY_MENU_BACK) {
+                self.waiting_for_key = false;
+                None
+            } else if let Some(key_code) = get_last_key_pressed() {
+                Some(Binding::Key(key_code))
+            } else {
+                gamepads.last_pressed().map(Binding::Pad)
+            };
+            if let Some(binding) = binding_optional {
+                settings
+                    .bindings_for_mut(self.player)
+                    .add(ALL_ACTIONS[self.selected], binding);
+                settings.save();
+                self.waiting_for_key = false;
+            }
+            return None;
+        }
+
+        let item_count = Self::back_index() + 1;
+        if is_key_pressed(KEY_MENU_UP) || gamepads.any_pressed(None, PAD_MENU_UP) {
+            self.selected = (self.selected + item_count - 1) % item_count;
+        }
+        if is_key_pressed(KEY_MENU_DOWN) || gamepads.any_pressed(None, PAD_MENU_DOWN) {
+            self.selected = (self.selected + 1) % item_count;
+        }
+        if is_key_pressed(KEY_MENU_BACK) || gamepads.any_pressed(None, PAD_MENU_BACK) {
+            return self.close();
+        }
+        let switch_player = is_key_pressed(KEY_CONTROLS_PLAYER_PREVIOUS)
+            || is_key_pressed(KEY_CONTROLS_PLAYER_NEXT)
+            || gamepads.any_pressed(None, PAD_MENU_LEFT)
+            || gamepads.any_pressed(None, PAD_MENU_RIGHT);
+        if switch_player {
+            self.player = (self.player + 1) % PLAYER_COUNT_MAX;
+        }
+        let clear =
+            is_key_pressed(KEY_CONTROLS_CLEAR) || gamepads.any_pressed(None, PAD_CONTROLS_CLEAR);
+        if clear && self.selected < ALL_ACTIONS.len() {
+            settings
+                .bindings_for_mut(self.player)
+                .get_mut(ALL_ACTIONS[self.selected])
+                .clear();
+            settings.save();
+        }
+        if is_key_pressed(KEY_MENU_CONFIRM) || gamepads.any_pressed(None, PAD_MENU_CONFIRM) {
+            if self.selected < ALL_ACTIONS.len() {
+                self.waiting_for_key = true;
+            } else if self.selected == Self::reset_index() {
+                *settings.bindings_for_mut(self.player) = if self.player == 0 {
+                    Bindings::default()
+                } else {
+                    Bindings::default_player_two()
+                };
+                settings.save();
+            } else {
+                return self.close();
+            }
+        }
+        None
+    }
+}
+
+pub struct GameManager {
+    states: HashMap<GameStateIdentifier, Box<dyn GameState>>,
+    current_state_identifier: GameStateIdentifier,
+    // drawn frozen below the current state when it draws over the previous state
+    background_state_identifier_optional: Option<GameStateIdentifier>,
+    resources: Resources,
+    sound_mixer: SoundMixer,
+    settings: Settings,
+    hot_reloader: HotReloader,
+    focus_tracker: FocusTracker,
+    gamepads: Gamepads,
+    touch: TouchControls,
+}
 
 impl GameManager {
     pub fn new(
         all_states: Vec<(GameStateIdentifier, Box<dyn GameState>)>,
         resources: Resources,
         sound_mixer: SoundMixer,
+        settings: Settings,
     ) -> Self {
         let mut states = HashMap::new();
         for state in all_states.into_iter() {
@@ -473,8 +1979,14 @@ impl GameManager {
         GameManager {
             states,
             current_state_identifier: GameStateIdentifier::Menu,
+            background_state_identifier_optional: None,
+            hot_reloader: HotReloader::new(&resources),
             resources,
             sound_mixer,
+            settings,
+            focus_tracker: FocusTracker::new(),
+            gamepads: Gamepads::new(),
+            touch: TouchControls::new(),
         }
     }
 
@@ -483,13 +1995,47 @@ impl GameManager {
     }
 
     pub fn update(&mut self, dt: f32) {
+        for message in self.hot_reloader.update(dt, &mut self.resources) {
+            // every state hears about it, not just the current one, so none of them hold stale data
+            for game_state in self.states.values_mut() {
+                match &message {
+                    HotReloadMessage::ResourcesReloaded => {
+                        game_state.on_resources_reloaded(&self.resources)
+                    }
+                    HotReloadMessage::TuningReloaded(tuning) => {
+                        game_state.on_tuning_reloaded(tuning)
+                    }
+                    HotReloadMessage::WavesReloaded(waves) => game_state.on_waves_reloaded(waves),
+                }
+            }
+        }
+
         // since we access the state through identifier instead of reference
         // we try to get the state, then update it. If we ChangeState, then we can't call on_enter IN this scope,
         // because we would have 2 state references, the current one and the one we change to.
         // (we can't set state if we are holding a reference to the current state)
+        self.gamepads.update();
+        self.touch.update(&self.viewport());
+        let focus_lost = self.focus_tracker.update(dt) && self.settings.pause_on_focus_loss;
         let state_command_optional =
             if let Some(game_state) = self.states.get_mut(&self.current_state_identifier) {
-                game_state.update(dt, &self.resources, &mut self.sound_mixer)
+                let focus_command_optional = if focus_lost {
+                    game_state.on_focus_lost()
+                } else {
+                    None
+                };
+                // the frame we lost focus isn't simulated, it's usually a long one
+                match focus_command_optional {
+                    Some(focus_command) => Some(focus_command),
+                    None => game_state.update(
+                        dt,
+                        &self.resources,
+                        &mut self.sound_mixer,
+                        &mut self.settings,
+                        &self.gamepads,
+                        &self.touch,
+                    ),
+                }
             } else {
                 None
             };
@@ -497,24 +2043,88 @@ impl GameManager {
         if let Some(state_command) = state_command_optional {
             match state_command {
                 GameStateCommand::ChangeState(next_state, payload_optional) => {
-                    self.current_state_identifier = next_state;
-                    if let Some(game_state) = self.states.get_mut(&self.current_state_identifier) {
-                        game_state.on_enter(&self.resources, payload_optional);
-                    }
+                    self.change_state(next_state, payload_optional);
                 }
             }
         }
     }
 
+    pub fn change_state(
+        &mut self,
+        next_state: GameStateIdentifier,
+        payload_optional: Option<ChangeStatePayload>,
+    ) {
+        let previous_state = self.current_state_identifier;
+        let previous_draws_over = self
+            .states
+            .get(&previous_state)
+            .map(|game_state| game_state.draws_over_previous_state())
+            .unwrap_or(false);
+        self.current_state_identifier = next_state;
+        if let Some(game_state) = self.states.get_mut(&self.current_state_identifier) {
+            // a menu opened from a menu (controls from pause) keeps the game below both
+            self.background_state_identifier_optional = if !game_state.draws_over_previous_state() {
+                None
+            } else if previous_draws_over {
+                self.background_state_identifier_optional
+            } else {
+                Some(previous_state)
+            };
+            game_state.on_enter(&self.resources, payload_optional);
+        }
+    }
+
+    // plays the replay through the game state, returns to the menu when it's done
+    pub fn watch_replay(&mut self, replay: Replay) {
+        self.change_state(
+            GameStateIdentifier::Game,
+            Some(ChangeStatePayload::ReplayPayload(replay)),
+        );
+    }
+
     pub fn draw(&self) {
+        for identifier in self.background_state_identifier_optional.iter() {
+            if let Some(game_state) = self.states.get(identifier) {
+                game_state.draw(&self.resources);
+            }
+        }
         if let Some(game_state) = self.states.get(&self.current_state_identifier) {
             game_state.draw(&self.resources);
         }
     }
 
-    pub fn draw_unscaled(&self) {
+    // where the game sits in the window this frame, for the display mode picked in the options
+    pub fn viewport(&self) -> Viewport {
+        Viewport::current(self.settings.viewport_mode)
+    }
+
+    // the game render target scaled up to the window, through the CRT shader picked in the options.
+    // The border art goes below it when turned on
+    pub fn draw_game_texture(&self, texture: Texture2D, viewport: &Viewport) {
+        if self.settings.show_border {
+            viewport.draw_border(self.resources.border);
+        }
+        draw_upscaled(
+            texture,
+            viewport.padding,
+            viewport.size(),
+            self.settings.crt_preset,
+            self.resources.crt_material_optional,
+        );
+    }
+
+    // only the current state shakes, a menu over the game holds it still
+    pub fn camera_offset(&self) -> Vec2 {
+        self.states
+            .get(&self.current_state_identifier)
+            .map(|game_state| game_state.camera_offset())
+            .unwrap_or(Vec2::ZERO)
+    }
+
+    // only the current state, text of the state below would show through the overlay
+    pub fn draw_unscaled(&self, viewport: &Viewport) {
         if let Some(game_state) = self.states.get(&self.current_state_identifier) {
-            game_state.draw_unscaled(&self.resources);
+            game_state.draw_unscaled(&self.resources, viewport);
         }
     }
 }
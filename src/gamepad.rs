@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+
+// a button or stick direction of a standard layout gamepad,
+// face buttons are named by where they sit so they mean the same on every brand
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PadInput {
+    South,
+    East,
+    West,
+    North,
+    Select,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftStickUp,
+    LeftStickDown,
+    LeftStickLeft,
+    LeftStickRight,
+}
+
+pub const ALL_PAD_INPUTS: &[PadInput] = &[
+    PadInput::South,
+    PadInput::East,
+    PadInput::West,
+    PadInput::North,
+    PadInput::Select,
+    PadInput::Start,
+    PadInput::DPadUp,
+    PadInput::DPadDown,
+    PadInput::DPadLeft,
+    PadInput::DPadRight,
+    PadInput::LeftStickUp,
+    PadInput::LeftStickDown,
+    PadInput::LeftStickLeft,
+    PadInput::LeftStickRight,
+];
+
+const PAD_INPUT_COUNT: usize = 14;
+
+impl PadInput {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PadInput::South => "SOUTH",
+            PadInput::East => "EAST",
+            PadInput::West => "WEST",
+            PadInput::North => "NORTH",
+            PadInput::Select => "SELECT",
+            PadInput::Start => "START",
+            PadInput::DPadUp => "DPAD UP",
+            PadInput::DPadDown => "DPAD DOWN",
+            PadInput::DPadLeft => "DPAD LEFT",
+            PadInput::DPadRight => "DPAD RIGHT",
+            PadInput::LeftStickUp => "STICK UP",
+            PadInput::LeftStickDown => "STICK DOWN",
+            PadInput::LeftStickLeft => "STICK LEFT",
+            PadInput::LeftStickRight => "STICK RIGHT",
+        }
+    }
+
+    fn is_stick(&self) -> bool {
+        matches!(
+            self,
+            PadInput::LeftStickUp
+                | PadInput::LeftStickDown
+                | PadInput::LeftStickLeft
+                | PadInput::LeftStickRight
+        )
+    }
+}
+
+// a stick is 0 inside the deadzone, then goes up to 1 at the edge so slow movement is still possible
+fn apply_deadzone(value: f32) -> f32 {
+    ((value - GAMEPAD_DEADZONE) / (1f32 - GAMEPAD_DEADZONE)).clamp(0f32, 1f32)
+}
+
+struct Pad {
+    id: backend::PadId,
+    // 0 to 1 for every PadInput, indexed by the PadInput
+    values: [f32; PAD_INPUT_COUNT],
+    previous_values: [f32; PAD_INPUT_COUNT],
+}
+
+// Every connected gamepad gets a slot, the first free one when it's plugged in.
+// A pad keeps its slot until it's unplugged, so players don't swap pads when another one connects.
+pub struct Gamepads {
+    backend: backend::Backend,
+    slots: Vec<Option<Pad>>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Gamepads {
+            backend: backend::Backend::new(),
+            slots: Vec::new(),
+        }
+    }
+
+    // call once per frame, before asking about any input
+    pub fn update(&mut self) {
+        let connected = self.backend.connected();
+        for pad_optional in self.slots.iter_mut() {
+            let disconnected = match pad_optional {
+                Some(pad) => !connected.contains(&pad.id),
+                None => false,
+            };
+            if disconnected {
+                *pad_optional = None;
+            }
+        }
+        for id in connected {
+            let assigned = self.slots.iter().flatten().any(|pad| pad.id == id);
+            if assigned {
+                continue;
+            }
+            let pad = Pad {
+                id,
+                values: [0f32; PAD_INPUT_COUNT],
+                previous_values: [0f32; PAD_INPUT_COUNT],
+            };
+            match self.slots.iter().position(|pad| pad.is_none()) {
+                Some(slot) => self.slots[slot] = Some(pad),
+                None => self.slots.push(Some(pad)),
+            }
+        }
+
+        for pad in self.slots.iter_mut().flatten() {
+            pad.previous_values = pad.values;
+            self.backend.read(pad.id, &mut pad.values);
+            for input in ALL_PAD_INPUTS.iter().filter(|input| input.is_stick()) {
+                pad.values[*input as usize] = apply_deadzone(pad.values[*input as usize]);
+            }
+        }
+    }
+
+    // the pads to look at, None means every pad
+    fn pads(&self, slot_optional: Option<usize>) -> impl Iterator<Item = &Pad> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(move |(slot, _)| slot_optional.is_none() || slot_optional == Some(*slot))
+            .filter_map(|(_, pad_optional)| pad_optional.as_ref())
+    }
+
+    // how far the input is pushed from 0 to 1, the highest of the pads asked about
+    pub fn value(&self, slot_optional: Option<usize>, input: PadInput) -> f32 {
+        self.pads(slot_optional)
+            .map(|pad| pad.values[input as usize])
+            .fold(0f32, f32::max)
+    }
+
+    pub fn is_down(&self, slot_optional: Option<usize>, input: PadInput) -> bool {
+        self.value(slot_optional, input) >= GAMEPAD_PRESS_THRESHOLD
+    }
+
+    // true on the frame the input goes down
+    pub fn is_pressed(&self, slot_optional: Option<usize>, input: PadInput) -> bool {
+        self.pads(slot_optional).any(|pad| {
+            pad.values[input as usize] >= GAMEPAD_PRESS_THRESHOLD
+                && pad.previous_values[input as usize] < GAMEPAD_PRESS_THRESHOLD
+        })
+    }
+
+    pub fn any_pressed(&self, slot_optional: Option<usize>, inputs: &[PadInput]) -> bool {
+        inputs
+            .iter()
+            .any(|input| self.is_pressed(slot_optional, *input))
+    }
+
+    // something pressed this frame on any pad, used when binding controls
+    pub fn last_pressed(&self) -> Option<PadInput> {
+        ALL_PAD_INPUTS
+            .iter()
+            .find(|input| self.is_pressed(None, **input))
+            .copied()
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use super::{PadInput, PAD_INPUT_COUNT};
+    use gilrs::{Axis, Button, Gilrs};
+
+    pub type PadId = gilrs::GamepadId;
+
+    pub struct Backend {
+        // None if the platform has no gamepad support, the game then just doesn't see any pads
+        gilrs_optional: Option<Gilrs>,
+    }
+
+    impl Backend {
+        pub fn new() -> Self {
+            let gilrs_optional = match Gilrs::new() {
+                Ok(gilrs) => Some(gilrs),
+                Err(err) => {
+                    eprintln!("gamepads are not available: {}", err);
+                    None
+                }
+            };
+            Backend { gilrs_optional }
+        }
+
+        pub fn connected(&mut self) -> Vec<PadId> {
+            let gilrs = match self.gilrs_optional.as_mut() {
+                Some(gilrs) => gilrs,
+                None => return Vec::new(),
+            };
+            // gilrs only updates the pad state while its events are read
+            while gilrs.next_event().is_some() {}
+            gilrs.gamepads().map(|(id, _)| id).collect()
+        }
+
+        pub fn read(&self, id: PadId, values: &mut [f32; PAD_INPUT_COUNT]) {
+            let gamepad = match self.gilrs_optional.as_ref() {
+                Some(gilrs) => gilrs.gamepad(id),
+                None => return,
+            };
+            let button = |button: Button| {
+                if gamepad.is_pressed(button) {
+                    1f32
+                } else {
+                    0f32
+                }
+            };
+            // some pads report the d-pad as an axis instead of buttons
+            let dpad_x = gamepad.value(Axis::DPadX);
+            let dpad_y = gamepad.value(Axis::DPadY);
+            let stick_x = gamepad.value(Axis::LeftStickX);
+            let stick_y = gamepad.value(Axis::LeftStickY);
+
+            values[PadInput::South as usize] = button(Button::South);
+            values[PadInput::East as usize] = button(Button::East);
+            values[PadInput::West as usize] = button(Button::West);
+            values[PadInput::North as usize] = button(Button::North);
+            values[PadInput::Select as usize] = button(Button::Select);
+            values[PadInput::Start as usize] = button(Button::Start);
+            values[PadInput::DPadUp as usize] = button(Button::DPadUp).max(dpad_y);
+            values[PadInput::DPadDown as usize] = button(Button::DPadDown).max(-dpad_y);
+            values[PadInput::DPadLeft as usize] = button(Button::DPadLeft).max(-dpad_x);
+            values[PadInput::DPadRight as usize] = button(Button::DPadRight).max(dpad_x);
+            // gilrs has up as positive y
+            values[PadInput::LeftStickUp as usize] = stick_y.max(0f32);
+            values[PadInput::LeftStickDown as usize] = (-stick_y).max(0f32);
+            values[PadInput::LeftStickLeft as usize] = (-stick_x).max(0f32);
+            values[PadInput::LeftStickRight as usize] = stick_x.max(0f32);
+        }
+    }
+}
+
+// the browser's gamepad api through utils/wasm/gamepad.js, pads are the indices of navigator.getGamepads()
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use super::{PadInput, PAD_INPUT_COUNT};
+
+    extern "C" {
+        fn gamepad_count() -> i32;
+        fn gamepad_connected(index: i32) -> i32;
+        // indices of the standard gamepad mapping, https://w3c.github.io/gamepad/#remapping
+        fn gamepad_button(index: i32, button: i32) -> f32;
+        fn gamepad_axis(index: i32, axis: i32) -> f32;
+    }
+
+    pub type PadId = i32;
+
+    pub struct Backend;
+
+    impl Backend {
+        pub fn new() -> Self {
+            Backend
+        }
+
+        pub fn connected(&mut self) -> Vec<PadId> {
+            let count = unsafe { gamepad_count() };
+            (0..count)
+                .filter(|index| unsafe { gamepad_connected(*index) } != 0)
+                .collect()
+        }
+
+        pub fn read(&self, id: PadId, values: &mut [f32; PAD_INPUT_COUNT]) {
+            let button = |button: i32| unsafe { gamepad_button(id, button) };
+            let stick_x = unsafe { gamepad_axis(id, 0) };
+            // browsers have down as positive y
+            let stick_y = unsafe { gamepad_axis(id, 1) };
+
+            values[PadInput::South as usize] = button(0);
+            values[PadInput::East as usize] = button(1);
+            values[PadInput::West as usize] = button(2);
+            values[PadInput::North as usize] = button(3);
+            values[PadInput::Select as usize] = button(8);
+            values[PadInput::Start as usize] = button(9);
+            values[PadInput::DPadUp as usize] = button(12);
+            values[PadInput::DPadDown as usize] = button(13);
+            values[PadInput::DPadLeft as usize] = button(14);
+            values[PadInput::DPadRight as usize] = button(15);
+            values[PadInput::LeftStickUp as usize] = (-stick_y).max(0f32);
+            values[PadInput::LeftStickDown as usize] = stick_y.max(0f32);
+            values[PadInput::LeftStickLeft as usize] = (-stick_x).max(0f32);
+            values[PadInput::LeftStickRight as usize] = stick_x.max(0f32);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+use std::collections::HashMap;
+
+use crate::{constants::*, storage, variants::GameVariants};
+
+// where the table is kept, see storage.rs
+const HIGH_SCORES_STORAGE_KEY: &str = "highscores";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: i32,
+    // the wave the game ended on
+    pub wave: i32,
+    // YYYY-MM-DD
+    pub date: String,
+    // the game's seed, so the run can be looked up in replays/
+    pub seed: u64,
+    // every combination of variants has its own table, entries from before variants are NORMAL
+    #[serde(default)]
+    pub variants: GameVariants,
+}
+
+// best scores first, never more than HIGH_SCORE_COUNT per combination of variants
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    // a missing table is empty, a broken one is reported and treated as empty
+    pub fn load() -> Self {
+        match storage::load_string(HIGH_SCORES_STORAGE_KEY) {
+            Some(source) => match ron::de::from_str::<HighScores>(&source) {
+                Ok(mut high_scores) => {
+                    high_scores.sort();
+                    high_scores
+                }
+                Err(err) => {
+                    eprintln!(
+                        "high scores are not valid RON, starting a new table: {}",
+                        err
+                    );
+                    HighScores::default()
+                }
+            },
+            None => HighScores::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|source| storage::save_string(HIGH_SCORES_STORAGE_KEY, &source));
+        if let Err(err) = result {
+            eprintln!("failed to save high scores: {}", err);
+        }
+    }
+
+    // the table of games played with these variants, best first
+    pub fn table(&self, variants: &GameVariants) -> Vec<&HighScoreEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.variants == *variants)
+            .collect()
+    }
+
+    pub fn qualifies(&self, score: i32, variants: &GameVariants) -> bool {
+        if score <= 0 {
+            return false;
+        }
+        let table = self.table(variants);
+        table.len() < HIGH_SCORE_COUNT || table.iter().any(|entry| score > entry.score)
+    }
+
+    // returns the index the entry ended up at in its variants' table, None if it didn't make it
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        // ties go below the older entry, it got there first
+        let table_index = self
+            .table(&entry.variants)
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or_else(|| self.table(&entry.variants).len());
+        if table_index >= HIGH_SCORE_COUNT {
+            return None;
+        }
+        let index = self
+            .entries
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(index, entry);
+        self.truncate();
+        Some(table_index)
+    }
+
+    fn sort(&mut self) {
+        // stable, so hand edited ties keep their order
+        self.entries.sort_by_key(|entry| Reverse(entry.score));
+        self.truncate();
+    }
+
+    // drops everything below HIGH_SCORE_COUNT in each table, entries are sorted by score
+    fn truncate(&mut self) {
+        let mut table_lengths = HashMap::<GameVariants, usize>::new();
+        self.entries.retain(|entry| {
+            let table_length = table_lengths.entry(entry.variants).or_insert(0);
+            *table_length += 1;
+            *table_length <= HIGH_SCORE_COUNT
+        });
+    }
+}
+
+// today's date as YYYY-MM-DD (UTC)
+pub fn today() -> String {
+    let days = (macroquad::miniquad::date::now() / 86400f64).floor() as i64;
+    // days since 1970-01-01 to a civil date, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACER: GameVariants = GameVariants {
+        starting_lives_optional: None,
+        tracer_shots: true,
+        guided_demon_shots: false,
+        fast_demon_shots: false,
+        no_extra_lives: false,
+        one_hit: false,
+        campaign: false,
+    };
+
+    fn entry(name: &str, score: i32) -> HighScoreEntry {
+        variant_entry(name, score, GameVariants::default())
+    }
+
+    fn variant_entry(name: &str, score: i32, variants: GameVariants) -> HighScoreEntry {
+        HighScoreEntry {
+            name: name.to_owned(),
+            score,
+            wave: 1,
+            date: "2021-01-01".to_owned(),
+            seed: 0,
+            variants,
+        }
+    }
+
+    fn full_table() -> HighScores {
+        let mut high_scores = HighScores::default();
+        for i in 0..HIGH_SCORE_COUNT as i32 {
+            high_scores.insert(entry("AAA", 1000 - i * 100));
+        }
+        high_scores
+    }
+
+    fn names(table: &[&HighScoreEntry]) -> Vec<String> {
+        table.iter().map(|entry| entry.name.clone()).collect()
+    }
+
+    #[test]
+    fn empty_table_takes_any_score_above_zero() {
+        let high_scores = HighScores::default();
+        assert!(high_scores.qualifies(1, &GameVariants::default()));
+        assert!(!high_scores.qualifies(0, &GameVariants::default()));
+    }
+
+    #[test]
+    fn full_table_only_takes_better_scores() {
+        let high_scores = full_table();
+        let lowest = high_scores.entries.last().unwrap().score;
+        assert!(!high_scores.qualifies(lowest, &GameVariants::default()));
+        assert!(high_scores.qualifies(lowest + 1, &GameVariants::default()));
+    }
+
+    #[test]
+    fn ties_go_below_the_older_entry() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("OLD", 500));
+        assert_eq!(high_scores.insert(entry("NEW", 500)), Some(1));
+        assert_eq!(high_scores.insert(entry("TOP", 600)), Some(0));
+        assert_eq!(
+            names(&high_scores.table(&GameVariants::default())),
+            vec!["TOP", "OLD", "NEW"]
+        );
+    }
+
+    #[test]
+    fn table_is_capped() {
+        let mut high_scores = full_table();
+        assert_eq!(high_scores.insert(entry("NEW", 550)), Some(5));
+        assert_eq!(high_scores.entries.len(), HIGH_SCORE_COUNT);
+        assert_eq!(high_scores.entries.last().unwrap().score, 200);
+        let lowest = high_scores.entries.last().unwrap().score;
+        assert_eq!(high_scores.insert(entry("LOW", lowest)), None);
+        assert_eq!(high_scores.entries.len(), HIGH_SCORE_COUNT);
+    }
+
+    #[test]
+    fn variants_have_their_own_tables() {
+        let mut high_scores = full_table();
+        assert!(high_scores.qualifies(10, &TRACER));
+        assert_eq!(
+            high_scores.insert(variant_entry("TRC", 10, TRACER)),
+            Some(0)
+        );
+        assert_eq!(names(&high_scores.table(&TRACER)), vec!["TRC"]);
+        // a full table of normal games doesn't push out the other tables
+        assert_eq!(high_scores.insert(entry("NEW", 2000)), Some(0));
+        assert_eq!(
+            high_scores.table(&GameVariants::default()).len(),
+            HIGH_SCORE_COUNT
+        );
+        assert_eq!(names(&high_scores.table(&TRACER)), vec!["TRC"]);
+    }
+
+    #[test]
+    fn sort_keeps_hand_edited_ties_in_order_and_caps_every_table() {
+        let mut entries = vec![entry("B", 100), entry("A", 300), entry("C", 100)];
+        entries.extend((0..HIGH_SCORE_COUNT as i32 + 2).map(|i| variant_entry("T", i + 1, TRACER)));
+        let mut high_scores = HighScores { entries };
+        high_scores.sort();
+        assert_eq!(
+            names(&high_scores.table(&GameVariants::default())),
+            vec!["A", "B", "C"]
+        );
+        let tracer_table = high_scores.table(&TRACER);
+        assert_eq!(tracer_table.len(), HIGH_SCORE_COUNT);
+        assert_eq!(tracer_table.last().unwrap().score, 3);
+    }
+}
@@ -0,0 +1,213 @@
+use macroquad::prelude::*;
+use std::time::SystemTime;
+
+use crate::{
+    animation::{sidecar_path, SpriteSheet},
+    resources::{Resources, SoundIdentifier, TextureSlot, SOUND_PATHS},
+    tuning::{Tuning, TUNING_FILE_PATH},
+    wave_file::{waves_from_ron_str, WaveDefinition, WAVES_FILE_PATH},
+};
+
+// how often (in seconds) the watched files are checked for changes
+const HOT_RELOAD_POLL_TIME: f32 = 0.25f32;
+
+pub enum HotReloadMessage {
+    // textures, sprite sheets or sounds in Resources were replaced
+    ResourcesReloaded,
+    // boxed, the tuning is much bigger than the other messages
+    TuningReloaded(Box<Tuning>),
+    WavesReloaded(Vec<WaveDefinition>),
+}
+
+#[derive(Clone, Copy)]
+enum WatchedAsset {
+    Texture(TextureSlot),
+    // the sidecar file of the texture in the slot
+    SpriteSheet(TextureSlot),
+    Sound(SoundIdentifier),
+    Tuning,
+    Waves,
+}
+
+struct WatchedFile {
+    path: String,
+    asset: WatchedAsset,
+    modified_optional: Option<SystemTime>,
+    // changed since the last poll, reloaded once it stops changing so half written files are skipped
+    pending: bool,
+}
+
+// Polls the modification time of every file the game loaded and reloads the ones that changed,
+// so art, sounds, tuning and waves can be tweaked without restarting.
+// On wasm there is no filesystem to poll, so nothing is ever reloaded there.
+pub struct HotReloader {
+    files: Vec<WatchedFile>,
+    poll_timer: f32,
+}
+
+impl HotReloader {
+    pub fn new(resources: &Resources) -> Self {
+        let mut files = Vec::new();
+        for (path, slot) in resources.texture_paths.iter() {
+            files.push(WatchedFile::new(path, WatchedAsset::Texture(*slot)));
+            files.push(WatchedFile::new(
+                &sidecar_path(path),
+                WatchedAsset::SpriteSheet(*slot),
+            ));
+        }
+        for (identifier, path) in SOUND_PATHS.iter() {
+            files.push(WatchedFile::new(path, WatchedAsset::Sound(*identifier)));
+        }
+        files.push(WatchedFile::new(TUNING_FILE_PATH, WatchedAsset::Tuning));
+        files.push(WatchedFile::new(WAVES_FILE_PATH, WatchedAsset::Waves));
+        // files missing now are watched too, they're loaded once they're created
+
+        HotReloader {
+            files,
+            poll_timer: 0f32,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, resources: &mut Resources) -> Vec<HotReloadMessage> {
+        let mut messages = Vec::new();
+        self.poll_timer -= dt;
+        if self.poll_timer > 0f32 {
+            return messages;
+        }
+        self.poll_timer = HOT_RELOAD_POLL_TIME;
+
+        let mut resources_reloaded = false;
+        for file in self.files.iter_mut() {
+            let modified_optional = modified_time(&file.path);
+            if modified_optional != file.modified_optional {
+                file.modified_optional = modified_optional;
+                file.pending = true;
+                continue;
+            }
+            if !file.pending || modified_optional.is_none() {
+                continue;
+            }
+            file.pending = false;
+
+            match file.asset {
+                WatchedAsset::Texture(slot) => match load_image_file(&file.path) {
+                    Ok(image) => {
+                        resources.replace_image(slot, image);
+                        // frames cut by frame_count change with the size of the png
+                        reload_sprite_sheet(&file.path, slot, resources);
+                        resources_reloaded = true;
+                    }
+                    Err(err) => eprintln!("{}: {}", file.path, err),
+                },
+                WatchedAsset::SpriteSheet(slot) => {
+                    let png_path = resources
+                        .texture_paths
+                        .iter()
+                        .find(|(_, texture_slot)| *texture_slot == slot)
+                        .map(|(path, _)| path.clone());
+                    if let Some(png_path) = png_path {
+                        if reload_sprite_sheet(&png_path, slot, resources) {
+                            resources_reloaded = true;
+                            println!("reloaded {}", file.path);
+                        }
+                    }
+                }
+                WatchedAsset::Sound(identifier) => {
+                    let result = std::fs::read(&file.path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|bytes| {
+                            resources
+                                .try_load_sound(&bytes, identifier)
+                                .map_err(|err| err.to_string())
+                        });
+                    match result {
+                        Ok(()) => resources_reloaded = true,
+                        Err(err) => eprintln!("{}: {}", file.path, err),
+                    }
+                }
+                WatchedAsset::Tuning => {
+                    let result = std::fs::read_to_string(&file.path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|source| {
+                            Tuning::from_ron_str(&source).map_err(|err| err.to_string())
+                        });
+                    match result {
+                        Ok(tuning) => {
+                            messages.push(HotReloadMessage::TuningReloaded(Box::new(tuning)))
+                        }
+                        Err(err) => eprintln!("{}: {}\nkeeping current tuning", file.path, err),
+                    }
+                }
+                WatchedAsset::Waves => {
+                    let result = std::fs::read_to_string(&file.path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|source| {
+                            waves_from_ron_str(&source).map_err(|err| err.to_string())
+                        });
+                    match result {
+                        Ok(waves) => {
+                            println!("reloaded {}", file.path);
+                            messages.push(HotReloadMessage::WavesReloaded(waves));
+                        }
+                        Err(err) => eprintln!("{}: {}\nkeeping current waves", file.path, err),
+                    }
+                }
+            }
+        }
+
+        if resources_reloaded {
+            messages.push(HotReloadMessage::ResourcesReloaded);
+        }
+        messages
+    }
+}
+
+impl WatchedFile {
+    fn new(path: &str, asset: WatchedAsset) -> Self {
+        WatchedFile {
+            path: path.to_owned(),
+            asset,
+            modified_optional: modified_time(path),
+            pending: false,
+        }
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// keeps the current frames when the sidecar is broken, returns if it was replaced
+fn reload_sprite_sheet(png_path: &str, slot: TextureSlot, resources: &mut Resources) -> bool {
+    let path = sidecar_path(png_path);
+    let texture_size = resources.texture_size(slot);
+    let result = match std::fs::read_to_string(&path) {
+        Ok(source) => {
+            SpriteSheet::from_ron_str(&source, texture_size).map_err(|err| err.to_string())
+        }
+        // a png without a sidecar is a single frame
+        Err(_) => Ok(SpriteSheet::single_frame(texture_size)),
+    };
+    match result {
+        Ok(sprite_sheet) => {
+            resources.sprite_sheets.insert(slot, sprite_sheet);
+            true
+        }
+        Err(err) => {
+            eprintln!("{}: {}\nkeeping current frames", path, err);
+            false
+        }
+    }
+}
+
+fn load_image_file(path: &str) -> Result<Image, String> {
+    let bytes = std::fs::read(path).map_err(|err| err.to_string())?;
+    // macroquad panics on broken images, so only take pngs that were written to the end
+    let png_signature: &[u8] = &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    if !bytes.starts_with(png_signature) || !bytes.windows(4).any(|chunk| chunk == b"IEND") {
+        return Err("not a complete png file".to_owned());
+    }
+    Ok(Image::from_file_with_format(&bytes, None))
+}
@@ -0,0 +1,332 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::*,
+    gamepad::{Gamepads, PadInput},
+};
+
+// what the player wants to do, the game only asks about actions and never about keys directly
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Start,
+    Pause,
+}
+
+pub const ALL_ACTIONS: &[Action] = &[
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::Fire,
+    Action::Start,
+    Action::Pause,
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::Fire => "FIRE",
+            Action::Start => "START",
+            Action::Pause => "PAUSE",
+        }
+    }
+}
+
+// something physical an action can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    // saved by the name of the key, like Key("Space")
+    Key(#[serde(with = "key_code_name")] KeyCode),
+    Pad(PadInput),
+}
+
+// pad bindings only listen to the gamepad in pad_optional's slot (see gamepad.rs), None listens to every pad
+impl Binding {
+    // 0 to 1, keys are either fully pushed or not at all
+    pub fn value(&self, gamepads: &Gamepads, pad_optional: Option<usize>) -> f32 {
+        match self {
+            Binding::Key(key_code) => {
+                if is_key_down(*key_code) {
+                    1f32
+                } else {
+                    0f32
+                }
+            }
+            Binding::Pad(input) => gamepads.value(pad_optional, *input),
+        }
+    }
+
+    pub fn is_down(&self, gamepads: &Gamepads, pad_optional: Option<usize>) -> bool {
+        match self {
+            Binding::Key(key_code) => is_key_down(*key_code),
+            Binding::Pad(input) => gamepads.is_down(pad_optional, *input),
+        }
+    }
+
+    pub fn is_pressed(&self, gamepads: &Gamepads, pad_optional: Option<usize>) -> bool {
+        match self {
+            Binding::Key(key_code) => is_key_pressed(*key_code),
+            Binding::Pad(input) => gamepads.is_pressed(pad_optional, *input),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key_code) => format!("{:?}", key_code).to_uppercase(),
+            Binding::Pad(input) => format!("PAD {}", input.name()),
+        }
+    }
+}
+
+// every action with all the bindings that trigger it, any of them will do
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    pub move_left: Vec<Binding>,
+    pub move_right: Vec<Binding>,
+    pub fire: Vec<Binding>,
+    pub start: Vec<Binding>,
+    pub pause: Vec<Binding>,
+}
+
+// player one's bindings
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            move_left: Self::default_bindings(DEFAULT_KEYS_MOVE_LEFT, DEFAULT_PADS_MOVE_LEFT),
+            move_right: Self::default_bindings(DEFAULT_KEYS_MOVE_RIGHT, DEFAULT_PADS_MOVE_RIGHT),
+            fire: Self::default_bindings(DEFAULT_KEYS_FIRE, DEFAULT_PADS_FIRE),
+            start: Self::default_bindings(DEFAULT_KEYS_START, DEFAULT_PADS_START),
+            pause: Self::default_bindings(DEFAULT_KEYS_PAUSE, DEFAULT_PADS_PAUSE),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn default_player_two() -> Self {
+        Bindings {
+            move_left: Self::default_bindings(
+                DEFAULT_KEYS_PLAYER_TWO_MOVE_LEFT,
+                DEFAULT_PADS_MOVE_LEFT,
+            ),
+            move_right: Self::default_bindings(
+                DEFAULT_KEYS_PLAYER_TWO_MOVE_RIGHT,
+                DEFAULT_PADS_MOVE_RIGHT,
+            ),
+            fire: Self::default_bindings(DEFAULT_KEYS_PLAYER_TWO_FIRE, DEFAULT_PADS_FIRE),
+            start: Self::default_bindings(DEFAULT_KEYS_PLAYER_TWO_START, DEFAULT_PADS_START),
+            pause: Self::default_bindings(DEFAULT_KEYS_PLAYER_TWO_PAUSE, DEFAULT_PADS_PAUSE),
+        }
+    }
+
+    fn default_bindings(key_codes: &[KeyCode], pad_inputs: &[PadInput]) -> Vec<Binding> {
+        key_codes
+            .iter()
+            .map(|key| Binding::Key(*key))
+            .chain(pad_inputs.iter().map(|input| Binding::Pad(*input)))
+            .collect()
+    }
+    pub fn get(&self, action: Action) -> &Vec<Binding> {
+        match action {
+            Action::MoveLeft => &self.move_left,
+            Action::MoveRight => &self.move_right,
+            Action::Fire => &self.fire,
+            Action::Start => &self.start,
+            Action::Pause => &self.pause,
+        }
+    }
+
+    pub fn get_mut(&mut self, action: Action) -> &mut Vec<Binding> {
+        match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::Fire => &mut self.fire,
+            Action::Start => &mut self.start,
+            Action::Pause => &mut self.pause,
+        }
+    }
+
+    // the strongest of the action's bindings, so a half pushed stick doesn't hide a held key
+    pub fn value(&self, action: Action, gamepads: &Gamepads, pad_optional: Option<usize>) -> f32 {
+        self.get(action)
+            .iter()
+            .map(|binding| binding.value(gamepads, pad_optional))
+            .fold(0f32, f32::max)
+    }
+
+    pub fn is_down(
+        &self,
+        action: Action,
+        gamepads: &Gamepads,
+        pad_optional: Option<usize>,
+    ) -> bool {
+        self.get(action)
+            .iter()
+            .any(|binding| binding.is_down(gamepads, pad_optional))
+    }
+
+    pub fn is_pressed(
+        &self,
+        action: Action,
+        gamepads: &Gamepads,
+        pad_optional: Option<usize>,
+    ) -> bool {
+        self.get(action)
+            .iter()
+            .any(|binding| binding.is_pressed(gamepads, pad_optional))
+    }
+
+    // adding a binding the action already has does nothing
+    pub fn add(&mut self, action: Action, binding: Binding) {
+        let bindings = self.get_mut(action);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+}
+
+// KeyCode doesn't implement serde, so keys are written by their variant name
+mod key_code_name {
+    use macroquad::prelude::KeyCode;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key_code: &KeyCode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:?}", key_code))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyCode, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        super::ALL_KEY_CODES
+            .iter()
+            .find(|key_code| format!("{:?}", key_code) == name)
+            .copied()
+            .ok_or_else(|| D::Error::custom(format!("unknown key {}", name)))
+    }
+}
+
+const ALL_KEY_CODES: &[KeyCode] = &[
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::World1,
+    KeyCode::World2,
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Down,
+    KeyCode::Up,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::CapsLock,
+    KeyCode::ScrollLock,
+    KeyCode::NumLock,
+    KeyCode::PrintScreen,
+    KeyCode::Pause,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::F13,
+    KeyCode::F14,
+    KeyCode::F15,
+    KeyCode::F16,
+    KeyCode::F17,
+    KeyCode::F18,
+    KeyCode::F19,
+    KeyCode::F20,
+    KeyCode::F21,
+    KeyCode::F22,
+    KeyCode::F23,
+    KeyCode::F24,
+    KeyCode::F25,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpDecimal,
+    KeyCode::KpDivide,
+    KeyCode::KpMultiply,
+    KeyCode::KpSubtract,
+    KeyCode::KpAdd,
+    KeyCode::KpEnter,
+    KeyCode::KpEqual,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::LeftSuper,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+    KeyCode::RightSuper,
+    KeyCode::Menu,
+];
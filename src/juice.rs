@@ -0,0 +1,55 @@
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::{constants::*, settings::Settings, world::Impact};
+
+// screen shake and hit-stop for the impacts the world reports. Kept out of the world,
+// so turning them down in the settings never changes how a game (or replay) plays out
+#[derive(Default)]
+pub struct Juice {
+    // 0 -> 1, how hard the screen shakes, wears off over time
+    trauma: f32,
+    // seconds the simulation stays frozen
+    hit_stop_timer: f32,
+    // in game pixels, added to the camera
+    offset: Vec2,
+}
+
+impl Juice {
+    pub fn reset(&mut self) {
+        *self = Juice::default();
+    }
+
+    // intensities come from the settings, 0 turns the effect off
+    pub fn impact(&mut self, impact: Impact, settings: &Settings) {
+        let (trauma, hit_stop_time) = match impact {
+            Impact::PlayerHit => (SHAKE_TRAUMA_PLAYER_HIT, 0f32),
+            Impact::Kill => (0f32, HIT_STOP_TIME_KILL),
+            Impact::BossDefeated => (SHAKE_TRAUMA_BOSS_DEFEATED, HIT_STOP_TIME_BOSS_DEFEATED),
+            Impact::WaveCleared => (SHAKE_TRAUMA_WAVE_CLEARED, 0f32),
+        };
+        self.trauma = (self.trauma + trauma * settings.shake_intensity).min(1f32);
+        // hit-stops don't add up, a bunch of kills in one step freezes as long as one
+        self.hit_stop_timer = self
+            .hit_stop_timer
+            .max(hit_stop_time * settings.hit_stop_intensity);
+    }
+
+    // returns how much of dt the simulation gets to run, the rest is frozen by hit-stop
+    pub fn update(&mut self, dt: f32) -> f32 {
+        self.trauma = (self.trauma - SHAKE_DECAY * dt).max(0f32);
+        // squared so small shakes stay small, whole pixels so the low res picture stays sharp
+        let strength = self.trauma * self.trauma * SHAKE_MAX_OFFSET;
+        self.offset = vec2(
+            (gen_range(-1f32, 1f32) * strength).round(),
+            (gen_range(-1f32, 1f32) * strength).round(),
+        );
+
+        let frozen = self.hit_stop_timer.min(dt);
+        self.hit_stop_timer -= frozen;
+        dt - frozen
+    }
+
+    pub fn offset(&self) -> Vec2 {
+        self.offset
+    }
+}
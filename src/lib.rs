@@ -0,0 +1,37 @@
+pub mod animation;
+pub mod atlas;
+pub mod background;
+pub mod bullet;
+pub mod constants;
+pub mod crt;
+pub mod enemy;
+pub mod focus;
+pub mod game;
+pub mod gamepad;
+pub mod highscore;
+pub mod hot_reload;
+pub mod input;
+pub mod juice;
+pub mod particles;
+pub mod pickup;
+pub mod player;
+pub mod replay;
+pub mod resources;
+pub mod rng;
+pub mod settings;
+pub mod shaders;
+pub mod storage;
+pub mod touch;
+pub mod tuning;
+pub mod variants;
+pub mod viewport;
+pub mod wave;
+pub mod wave_file;
+pub mod world;
+
+// used to compare enums without having to match against it's values
+// example what we avoid: emotion_enum == Emotion::Happy{happines_level: 0f32, visible_on_face: false,}
+// the values needs to be constructed, but comparison is top-level
+pub fn variant_eq<T>(a: &T, b: &T) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
@@ -1,58 +1,79 @@
 use macroquad::prelude::*;
 use quad_snd::mixer::SoundMixer;
 
-use constants::*;
-use game::{GameManager, GameState, GameStateGame, GameStateIdentifier, GameStateMenu};
-use resources::load_resources;
-
-mod bullet;
-mod constants;
-mod enemy;
-mod game;
-mod player;
-mod resources;
-mod wave;
+use rusty_demon_attack::{
+    constants::*,
+    game::{
+        GameManager, GameState, GameStateControls, GameStateGame, GameStateIdentifier,
+        GameStateMenu, GameStateNameEntry, GameStatePause,
+    },
+    replay::Replay,
+    resources::load_resources,
+    settings::Settings,
+    tuning::{load_tuning, TUNING_FILE_PATH},
+    wave_file::{load_waves, WAVES_FILE_PATH},
+};
 
 fn window_conf() -> Conf {
     Conf {
         window_title: "Demottack".to_owned(),
         window_width: GAME_SIZE_X,
         window_height: GAME_SIZE_Y,
+        // switching while running doesn't work everywhere, so it's also applied on start
+        fullscreen: Settings::load().fullscreen,
         ..Default::default()
     }
 }
 
-// used to compare enums without having to match against it's values
-// example what we avoid: emotion_enum == Emotion::Happy{happines_level: 0f32, visible_on_face: false,}
-// the values needs to be constructed, but comparison is top-level
-fn variant_eq<T>(a: &T, b: &T) -> bool {
-    std::mem::discriminant(a) == std::mem::discriminant(b)
-}
-
 #[macroquad::main(window_conf)]
 async fn main() {
     let game_render_target = render_target(GAME_SIZE_X as u32, GAME_SIZE_Y as u32);
     let resources = load_resources(game_render_target).await;
+    let tuning = load_tuning(TUNING_FILE_PATH).await;
+    let waves = load_waves(WAVES_FILE_PATH).await;
     let mixer = SoundMixer::new();
 
     let game_states: Vec<(GameStateIdentifier, Box<dyn GameState>)> = vec![
-        (GameStateIdentifier::Menu, Box::new(GameStateMenu::new())),
+        (
+            GameStateIdentifier::Menu,
+            Box::new(GameStateMenu::new(&tuning)),
+        ),
+        (GameStateIdentifier::Pause, Box::new(GameStatePause::new())),
+        (
+            GameStateIdentifier::Controls,
+            Box::new(GameStateControls::new()),
+        ),
+        (
+            GameStateIdentifier::NameEntry,
+            Box::new(GameStateNameEntry::new()),
+        ),
         (
             GameStateIdentifier::Game,
-            Box::new(GameStateGame::new(&resources)),
+            Box::new(GameStateGame::new(&resources, tuning, waves)),
         ),
     ];
-    let mut game_manager = GameManager::new(game_states, resources, mixer);
+    let mut game_manager = GameManager::new(game_states, resources, mixer, Settings::load());
+
+    // rusty_demon_attack --replay replays/replay_1234.rdar
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--replay") {
+        match args.get(index + 1).map(|path| Replay::load(path)) {
+            Some(Ok(replay)) => game_manager.watch_replay(replay),
+            Some(Err(err)) => eprintln!("can't watch replay: {}", err),
+            None => eprintln!("--replay needs a path to a replay file"),
+        }
+    }
 
     loop {
         let dt = get_frame_time();
         let camera = Camera2D {
             // I have no idea why the zoom is this way lmao
             zoom: vec2(1. / GAME_SIZE_X as f32 * 2., 1. / GAME_SIZE_Y as f32 * 2.),
+            // screen shake moves it around by whole game pixels
             target: vec2(
                 (GAME_SIZE_X as f32 * 0.5f32).floor(),
                 (GAME_SIZE_Y as f32 * 0.5f32).floor(),
-            ),
+            ) + game_manager.camera_offset(),
             render_target: Some(game_render_target),
             ..Default::default()
         };
@@ -65,32 +86,15 @@ async fn main() {
         set_default_camera();
 
         // calculate game view size based on window size
-        let game_diff_w = screen_width() / GAME_SIZE_X as f32;
-        let game_diff_h = screen_height() / GAME_SIZE_Y as f32;
-        let aspect_diff = game_diff_w.min(game_diff_h);
-
-        let scaled_game_size_w = GAME_SIZE_X as f32 * aspect_diff;
-        let scaled_game_size_h = GAME_SIZE_Y as f32 * aspect_diff;
-
-        let width_padding = (screen_width() - scaled_game_size_w) * 0.5f32;
-        let height_padding = (screen_height() - scaled_game_size_h) * 0.5f32;
+        let viewport = game_manager.viewport();
 
         // draw game
         clear_background(BLACK);
 
         // fit inside window
-        draw_texture_ex(
-            game_render_target.texture,
-            width_padding,
-            height_padding,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(Vec2::new(scaled_game_size_w, scaled_game_size_h)),
-                ..Default::default()
-            },
-        );
+        game_manager.draw_game_texture(game_render_target.texture, &viewport);
 
-        game_manager.draw_unscaled();
+        game_manager.draw_unscaled(&viewport);
 
         game_manager.frame_sounds();
 
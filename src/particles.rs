@@ -0,0 +1,165 @@
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::constants::*;
+
+// a single pixel of debris, a spark or a puff of dust
+#[derive(Clone, Copy)]
+struct Particle {
+    pos: Vec2,
+    // position before the last simulation step, drawing interpolates between the two
+    prev_pos: Vec2,
+    vel: Vec2,
+    // pixels per second squared pulling it down, 0 for sparks that just fade
+    gravity: f32,
+    color: Color,
+    // seconds, the particle is free to be reused once it runs out
+    life: f32,
+    max_life: f32,
+}
+
+impl Particle {
+    fn is_alive(&self) -> bool {
+        self.life > 0f32
+    }
+}
+
+// all particles live in a pool allocated up front, emitting reuses dead (or the oldest) slots
+// so explosions never allocate mid game. Purely cosmetic, so the randomness is macroquad's
+pub struct Particles {
+    pool: Vec<Particle>,
+    // the next slot to emit into, goes around the pool
+    cursor: usize,
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Particles {
+            pool: vec![
+                Particle {
+                    pos: Vec2::ZERO,
+                    prev_pos: Vec2::ZERO,
+                    vel: Vec2::ZERO,
+                    gravity: 0f32,
+                    color: WHITE,
+                    life: 0f32,
+                    max_life: 0f32,
+                };
+                PARTICLE_POOL_SIZE
+            ],
+            cursor: 0,
+        }
+    }
+}
+
+impl Particles {
+    pub fn clear(&mut self) {
+        for particle in self.pool.iter_mut() {
+            particle.life = 0f32;
+        }
+    }
+
+    fn emit(&mut self, pos: Vec2, vel: Vec2, gravity: f32, color: Color, life: f32) {
+        // prefer a dead slot, when the pool is full the one after the newest is the oldest
+        let free_optional = (0..self.pool.len())
+            .map(|i| (self.cursor + i) % self.pool.len())
+            .find(|i| !self.pool[*i].is_alive());
+        let index = free_optional.unwrap_or(self.cursor);
+        self.pool[index] = Particle {
+            pos,
+            prev_pos: pos,
+            vel,
+            gravity,
+            color,
+            life,
+            max_life: life,
+        };
+        self.cursor = (index + 1) % self.pool.len();
+    }
+
+    // count particles flying out of pos in every direction
+    fn burst(&mut self, pos: Vec2, count: i32, speed: (f32, f32), gravity: f32, color: Color) {
+        for _ in 0..count {
+            let angle = gen_range(0f32, std::f32::consts::PI * 2f32);
+            let vel = vec2(angle.cos(), angle.sin()) * gen_range(speed.0, speed.1);
+            let life = gen_range(PARTICLE_LIFE_RANGE.x, PARTICLE_LIFE_RANGE.y);
+            self.emit(pos, vel, gravity, color, life);
+        }
+    }
+
+    // pixel debris in the demon's color, bigger demons break into more pieces
+    pub fn explosion(&mut self, pos: Vec2, size: Vec2, color: Color) {
+        let count = (size.x * size.y * PARTICLE_EXPLOSION_DENSITY) as i32;
+        for _ in 0..count.max(PARTICLE_EXPLOSION_MIN_COUNT) {
+            let offset = vec2(
+                gen_range(-0.5f32, 0.5f32) * size.x,
+                gen_range(-0.5f32, 0.5f32) * size.y,
+            );
+            self.burst(
+                pos + offset,
+                1,
+                PARTICLE_EXPLOSION_SPEED,
+                PARTICLE_GRAVITY,
+                color,
+            );
+        }
+        self.burst(
+            pos,
+            PARTICLE_FLASH_COUNT,
+            PARTICLE_EXPLOSION_SPEED,
+            0f32,
+            WHITE,
+        );
+    }
+
+    // a bullet hitting something
+    pub fn sparks(&mut self, pos: Vec2) {
+        self.burst(
+            pos,
+            PARTICLE_SPARK_COUNT,
+            PARTICLE_SPARK_SPEED,
+            PARTICLE_GRAVITY,
+            YELLOW,
+        );
+    }
+
+    // a short cone of fire where a bullet left the barrel, dir is the way the bullet flies
+    pub fn muzzle_flash(&mut self, pos: Vec2, dir: Vec2) {
+        for _ in 0..PARTICLE_MUZZLE_FLASH_COUNT {
+            let spread = vec2(-dir.y, dir.x) * gen_range(-0.5f32, 0.5f32);
+            let vel = (dir + spread) * gen_range(PARTICLE_SPARK_SPEED.0, PARTICLE_SPARK_SPEED.1);
+            let life = gen_range(PARTICLE_LIFE_RANGE.x, PARTICLE_LIFE_RANGE.y) * 0.3f32;
+            self.emit(pos, vel, 0f32, ORANGE, life);
+        }
+    }
+
+    // a homing demon crashing into the ground, pos is on the ground
+    pub fn dust(&mut self, pos: Vec2) {
+        for _ in 0..PARTICLE_DUST_COUNT {
+            let vel = vec2(
+                gen_range(-1f32, 1f32) * PARTICLE_DUST_SPEED.x,
+                -gen_range(0f32, 1f32) * PARTICLE_DUST_SPEED.y,
+            );
+            let life = gen_range(PARTICLE_LIFE_RANGE.x, PARTICLE_LIFE_RANGE.y);
+            self.emit(pos, vel, PARTICLE_GRAVITY, PARTICLE_DUST_COLOR, life);
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in self.pool.iter_mut().filter(|particle| particle.is_alive()) {
+            particle.prev_pos = particle.pos;
+            particle.vel.y += particle.gravity * dt;
+            particle.pos += particle.vel * dt;
+            particle.life -= dt;
+        }
+    }
+
+    // a game pixel each, fading out as they die
+    pub fn draw(&self, interpolation: f32) {
+        for particle in self.pool.iter().filter(|particle| particle.is_alive()) {
+            let pos = particle.prev_pos + (particle.pos - particle.prev_pos) * interpolation;
+            let mut color = particle.color;
+            color.a *= particle.life / particle.max_life;
+            draw_rectangle(pos.x.floor(), pos.y.floor(), 1f32, 1f32, color);
+        }
+    }
+}
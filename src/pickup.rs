@@ -0,0 +1,164 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    atlas::draw_region_ex,
+    constants::*,
+    resources::{Resources, TextureSlot},
+    rng::GameRng,
+    tuning::Tuning,
+};
+
+// what a killed demon can drop, every kind has a frame in pickups.png in this order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PickupKind {
+    RapidFire,
+    DoubleShot,
+    Piercing,
+    Shield,
+    ScoreMultiplier,
+    // the only one that isn't timed, it's used up right away
+    ExtraLife,
+}
+
+pub const ALL_PICKUP_KINDS: &[PickupKind] = &[
+    PickupKind::RapidFire,
+    PickupKind::DoubleShot,
+    PickupKind::Piercing,
+    PickupKind::Shield,
+    PickupKind::ScoreMultiplier,
+    PickupKind::ExtraLife,
+];
+
+const PICKUP_KIND_COUNT: usize = ALL_PICKUP_KINDS.len();
+
+// what the demons of one color drop
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DropTable {
+    // 0 to 1, how often a kill drops anything
+    pub chance: f32,
+    // what drops, weighted. (RapidFire, 2.0) drops twice as often as (Shield, 1.0)
+    pub pickups: Vec<(PickupKind, f32)>,
+}
+
+impl DropTable {
+    pub fn new(chance: f32, pickups: &[(PickupKind, f32)]) -> Self {
+        DropTable {
+            chance,
+            pickups: pickups.to_vec(),
+        }
+    }
+
+    // what a kill drops, if anything
+    pub fn roll(&self, rng: &mut GameRng) -> Option<PickupKind> {
+        if rng.gen_range(0f32, 1f32) >= self.chance {
+            return None;
+        }
+        let total_weight: f32 = self.pickups.iter().map(|(_, weight)| weight).sum();
+        if total_weight <= 0f32 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0f32, total_weight);
+        for (kind, weight) in self.pickups.iter() {
+            if pick < *weight {
+                return Some(*kind);
+            }
+            pick -= weight;
+        }
+        self.pickups.last().map(|(kind, _)| *kind)
+    }
+}
+
+// seconds left of the timed power-ups a player has caught, 0 when it's off
+#[derive(Clone, Copy, Default)]
+pub struct PowerUps {
+    timers: [f32; PICKUP_KIND_COUNT],
+}
+
+impl PowerUps {
+    pub fn is_active(&self, kind: PickupKind) -> bool {
+        self.timers[kind as usize] > 0f32
+    }
+
+    pub fn time_left(&self, kind: PickupKind) -> f32 {
+        self.timers[kind as usize]
+    }
+
+    // catching one that's already running starts it over
+    pub fn start(&mut self, kind: PickupKind, duration: f32) {
+        self.timers[kind as usize] = duration;
+    }
+
+    // true when a power-up ran out this step
+    pub fn update(&mut self, dt: f32) -> bool {
+        let mut ran_out = false;
+        for timer in self.timers.iter_mut().filter(|timer| **timer > 0f32) {
+            *timer -= dt;
+            if *timer <= 0f32 {
+                *timer = 0f32;
+                ran_out = true;
+            }
+        }
+        ran_out
+    }
+}
+
+// a power-up falling to the ground, gone if nobody catches it
+pub struct Pickup {
+    pub kind: PickupKind,
+    pos: Vec2,
+    // position before the last simulation step, drawing interpolates between the two
+    prev_pos: Vec2,
+    pub collision_rect: Rect,
+    pub is_kill: bool,
+}
+
+impl Pickup {
+    // pos is where the enemy died, the pickup is centered on it
+    pub fn new(kind: PickupKind, pos: Vec2) -> Self {
+        let pos = pos - vec2(PICKUP_SIZE * 0.5f32, 0f32);
+        Pickup {
+            kind,
+            pos,
+            prev_pos: pos,
+            collision_rect: Rect::new(pos.x, pos.y, PICKUP_SIZE, PICKUP_SIZE),
+            is_kill: false,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, ground_height: f32, tuning: &Tuning) {
+        self.prev_pos = self.pos;
+        self.pos.y += tuning.pickup_fall_speed * dt;
+        self.collision_rect.x = self.pos.x;
+        self.collision_rect.y = self.pos.y;
+        // missed, it hit the ground
+        if self.pos.y > GAME_SIZE_Y as f32 - ground_height {
+            self.is_kill = true;
+        }
+    }
+
+    // interpolation is how far we are between the last simulation step and the next, 0 -> 1
+    pub fn draw(&self, resources: &Resources, interpolation: f32) {
+        let pos = self.prev_pos + (self.pos - self.prev_pos) * interpolation;
+        draw_pickup_icon(resources, self.kind, pos);
+    }
+}
+
+// the kind's frame of the kinds clip in pickups.png, also used for the power-up timers on the HUD
+pub fn draw_pickup_icon(resources: &Resources, kind: PickupKind, pos: Vec2) {
+    let frame = resources
+        .sprite_sheet(TextureSlot::Pickups)
+        .clip("kinds")
+        .frame(kind as usize);
+    draw_region_ex(
+        resources.pickups,
+        pos.x,
+        pos.y,
+        WHITE,
+        DrawTextureParams {
+            source: Some(frame.rect),
+            ..Default::default()
+        },
+    );
+}
@@ -1,10 +1,13 @@
 use macroquad::prelude::*;
-use quad_snd::mixer::{SoundMixer, Volume};
 
 use crate::{
+    atlas::draw_region_ex,
     bullet::{Bullet, BulletHurtType},
     constants::*,
-    resources::{Resources, SoundIdentifier},
+    pickup::{PickupKind, PowerUps},
+    resources::{Resources, SoundIdentifier, TextureSlot},
+    tuning::Tuning,
+    world::{SoundQueue, SpriteMetrics, WorldInput},
 };
 
 #[derive(PartialEq)]
@@ -19,78 +22,136 @@ pub enum PlayerCommand {
 }
 
 pub struct Player {
+    // 0 for player one, 1 for player two, picks the tint and who gets credit for kills
+    pub index: usize,
+    pub score: i32,
+    // at 0 the player is out of the game, the others keep playing
+    pub lives: i32,
     pub pos: Vec2,
-    texture: Texture2D,
-    texture_explotion: Texture2D,
-    bullet_decoy_texture: Texture2D,
+    // position before the last simulation step, drawing interpolates between the two
+    prev_pos: Vec2,
+    size: Vec2,
     shoot_timer: f32,
     pub collision_rect: Rect,
     pub state: PlayerState,
+    pub power_ups: PowerUps,
 }
 
 impl Player {
-    pub fn new(
-        pos: Vec2,
-        texture: Texture2D,
-        bullet_decoy_texture: Texture2D,
-        texture_explotion: Texture2D,
-    ) -> Self {
+    pub fn new(index: usize, pos: Vec2, size: Vec2, lives: i32) -> Self {
         Player {
+            index,
+            score: 0,
+            lives,
             pos,
-            texture,
-            bullet_decoy_texture,
-            texture_explotion,
+            prev_pos: pos,
+            size,
             shoot_timer: 0f32,
             collision_rect: Rect::new(pos.x, pos.y, 7.0f32, 6.0f32),
             state: PlayerState::Normal,
+            power_ups: PowerUps::default(),
         }
     }
 
-    pub fn reset(&mut self, resources: &Resources) {
+    // standing on the ground, spread evenly over the screen (a single player stands in the middle)
+    pub fn spawn_pos(sprite_metrics: &SpriteMetrics, index: usize, player_count: usize) -> Vec2 {
         let player_spawn_y =
-            GAME_SIZE_Y as f32 - resources.ground_bg.height() - resources.player.height();
-        let player_pos = vec2(GAME_CENTER_X, player_spawn_y);
-        self.pos = player_pos;
-        self.shoot_timer = 0f32;
-        self.state = PlayerState::Normal;
+            GAME_SIZE_Y as f32 - sprite_metrics.ground_height - sprite_metrics.player.y;
+        let player_spawn_x = GAME_SIZE_X as f32 * (index + 1) as f32 / (player_count + 1) as f32;
+        vec2(player_spawn_x, player_spawn_y)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.lives > 0
+    }
+
+    pub fn tint(&self) -> Color {
+        PLAYER_TINTS[self.index % PLAYER_TINTS.len()]
+    }
+
+    // shorter with rapid fire
+    fn shoot_time(&self, tuning: &Tuning) -> f32 {
+        if self.power_ups.is_active(PickupKind::RapidFire) {
+            tuning.player_shoot_time * tuning.pickup_rapid_fire_factor
+        } else {
+            tuning.player_shoot_time
+        }
+    }
+
+    // kill scores are multiplied by this
+    pub fn score_multiplier(&self, tuning: &Tuning) -> i32 {
+        if self.power_ups.is_active(PickupKind::ScoreMultiplier) {
+            tuning.pickup_score_multiplier
+        } else {
+            1
+        }
+    }
+
+    // a shielded player isn't hurt by bullets or homing demons
+    pub fn is_shielded(&self) -> bool {
+        self.power_ups.is_active(PickupKind::Shield)
+    }
+
+    // extra lives are used up right away, everything else runs for pickup_duration
+    pub fn catch_pickup(&mut self, kind: PickupKind, tuning: &Tuning) {
+        match kind {
+            PickupKind::ExtraLife => {
+                self.lives = (self.lives + 1).min(tuning.player_lives_max);
+            }
+            _ => self.power_ups.start(kind, tuning.pickup_duration),
+        }
     }
 
     pub fn update(
         &mut self,
         dt: f32,
+        input: &WorldInput,
         bullets: &mut Vec<Bullet>,
-        resources: &Resources,
-        sound_mixer: &mut SoundMixer,
+        sounds: &mut SoundQueue,
+        tuning: &Tuning,
     ) {
+        self.prev_pos = self.pos;
         self.shoot_timer += dt;
-        if is_key_down(KEY_LEFT) {
-            self.pos.x -= PLAYER_SPEED * dt;
+        if self.power_ups.update(dt) {
+            sounds.play(SoundIdentifier::PowerUpEnd, 1.0f32);
+        }
+        if input.move_x < 0f32 {
+            self.pos.x += tuning.player_speed * input.move_x * dt;
             if self.pos.x < 0f32 {
                 self.pos.x = 0f32;
             }
         }
-        if is_key_down(KEY_RIGHT) {
-            self.pos.x += PLAYER_SPEED * dt;
-            if self.pos.x > GAME_SIZE_X as f32 - self.texture.width() {
-                self.pos.x = GAME_SIZE_X as f32 - self.texture.width();
+        if input.move_x > 0f32 {
+            self.pos.x += tuning.player_speed * input.move_x * dt;
+            if self.pos.x > GAME_SIZE_X as f32 - self.size.x {
+                self.pos.x = GAME_SIZE_X as f32 - self.size.x;
             }
         }
 
         // state specific update
         let player_command_optional = match &mut self.state {
             PlayerState::Normal => {
-                if is_key_down(KEY_SHOOT) && self.shoot_timer >= PLAYER_SHOOT_TIME {
+                if input.shoot && self.shoot_timer >= self.shoot_time(tuning) {
                     let spawn_offset = vec2(3f32, -4f32);
-                    bullets.push(Bullet::new(
-                        self.pos + spawn_offset,
-                        BulletHurtType::Enemy,
-                        resources,
-                    ));
-                    resources.play_sound(
-                        SoundIdentifier::PlayerShoot,
-                        sound_mixer,
-                        Volume(1.0f32),
-                    );
+                    let spawn_offsets = if self.power_ups.is_active(PickupKind::DoubleShot) {
+                        vec![
+                            spawn_offset - vec2(DOUBLE_SHOT_SPACING * 0.5f32, 0f32),
+                            spawn_offset + vec2(DOUBLE_SHOT_SPACING * 0.5f32, 0f32),
+                        ]
+                    } else {
+                        vec![spawn_offset]
+                    };
+                    for spawn_offset in spawn_offsets {
+                        let mut bullet = Bullet::new(
+                            self.pos + spawn_offset,
+                            BulletHurtType::Enemy,
+                            Some(self.index),
+                            tuning,
+                        );
+                        bullet.piercing = self.power_ups.is_active(PickupKind::Piercing);
+                        bullets.push(bullet);
+                    }
+                    sounds.play(SoundIdentifier::PlayerShoot, 1.0f32);
                     self.shoot_timer = 0f32;
                 }
                 None
@@ -121,64 +182,94 @@ impl Player {
         }
     }
 
-    pub fn draw(&self) {
+    // how far the explosion after a hit has played, 0 -> 1. None while the cannon is normal
+    pub fn explosion_progress_optional(&self, tuning: &Tuning) -> Option<f32> {
+        match self.state {
+            PlayerState::Normal => None,
+            PlayerState::Invisible(time_left) => Some(self.explosion_progress(tuning, time_left)),
+        }
+    }
+
+    fn explosion_progress(&self, tuning: &Tuning, time_left: f32) -> f32 {
+        (tuning.player_time_invisible - time_left) / tuning.player_time_invisible
+    }
+
+    // interpolation is how far we are between the last simulation step and the next, 0 -> 1
+    pub fn draw(&self, resources: &Resources, tuning: &Tuning, interpolation: f32) {
+        let pos = self.prev_pos + (self.pos - self.prev_pos) * interpolation;
         match self.state {
-            PlayerState::Normal => self.draw_state_normal(),
-            PlayerState::Invisible(time_left) => self.draw_state_invisible(&time_left),
+            PlayerState::Normal => self.draw_state_normal(resources, tuning, pos),
+            PlayerState::Invisible(time_left) => {
+                self.draw_state_invisible(resources, tuning, pos, &time_left)
+            }
+        }
+
+        // a bubble around the cannon, blinking when it's about to run out
+        let shield_time_left = self.power_ups.time_left(PickupKind::Shield);
+        let shield_blink =
+            shield_time_left < PICKUP_BLINK_TIME && (shield_time_left * 8f32) as i32 % 2 == 0;
+        if shield_time_left > 0f32 && !shield_blink {
+            draw_circle_lines(
+                pos.x + self.size.x * 0.5f32,
+                pos.y + self.size.y * 0.5f32,
+                self.size.x,
+                1f32,
+                Color::new(0.43f32, 0.55f32, 1f32, 0.8f32),
+            );
         }
     }
 
-    pub fn draw_state_normal(&self) {
-        draw_texture_ex(
-            self.texture,
-            self.pos.x,
-            self.pos.y,
-            WHITE,
+    pub fn draw_state_normal(&self, resources: &Resources, tuning: &Tuning, pos: Vec2) {
+        draw_region_ex(
+            resources.player,
+            pos.x,
+            pos.y,
+            self.tint(),
             DrawTextureParams {
                 //dest_size: Some(vec2(screen_width(), screen_height())),
                 ..Default::default()
             },
         );
 
-        let decoy_frame_index = ((self.shoot_timer / PLAYER_SHOOT_TIME) * 3f32) as i32;
+        // the missile's spawn clip, played over the time it takes to reload
+        let decoy_frame = resources
+            .sprite_sheet(TextureSlot::PlayerMissile)
+            .clip("spawn")
+            .frame_at_progress(self.shoot_timer / self.shoot_time(tuning));
 
-        draw_texture_ex(
-            self.bullet_decoy_texture,
-            self.pos.x + 3.,
-            self.pos.y - 1.,
-            WHITE,
+        draw_region_ex(
+            resources.player_missile,
+            pos.x + 3.,
+            pos.y - 1.,
+            self.tint(),
             DrawTextureParams {
-                source: Some(Rect::new(
-                    self.bullet_decoy_texture.width() / 3f32 * decoy_frame_index as f32,
-                    0f32,
-                    self.bullet_decoy_texture.width() / 3f32,
-                    self.bullet_decoy_texture.height(),
-                )),
+                source: Some(decoy_frame.rect),
                 ..Default::default()
             },
         );
     }
 
-    pub fn draw_state_invisible(&self, time_left: &f32) {
-        let anim_frames = 7f32;
-        let time_per_frame = PLAYER_TIME_INVISBLE / anim_frames;
-        let fraction = (PLAYER_TIME_INVISBLE - time_left) / PLAYER_TIME_INVISBLE;
-        let frame_index = (PLAYER_TIME_INVISBLE - time_left) / time_per_frame;
-        let frame_index = frame_index.floor();
-
-        draw_texture_ex(
-            self.texture_explotion,
-            self.pos.x - 5f32,
-            self.pos.y - 4f32,
-            WHITE,
+    pub fn draw_state_invisible(
+        &self,
+        resources: &Resources,
+        tuning: &Tuning,
+        pos: Vec2,
+        time_left: &f32,
+    ) {
+        let fraction = self.explosion_progress(tuning, *time_left);
+        let frame = resources
+            .sprite_sheet(TextureSlot::PlayerExplotion)
+            .clip("explode")
+            .frame_at_progress(fraction);
+
+        draw_region_ex(
+            resources.player_explotion,
+            pos.x - 5f32,
+            pos.y - 4f32,
+            self.tint(),
             DrawTextureParams {
                 rotation: fraction * std::f32::consts::PI * 2f32,
-                source: Some(Rect::new(
-                    self.texture_explotion.width() / anim_frames * frame_index,
-                    0f32,
-                    self.texture_explotion.width() / anim_frames,
-                    self.texture_explotion.height(),
-                )),
+                source: Some(frame.rect),
                 ..Default::default()
             },
         );
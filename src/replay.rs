@@ -0,0 +1,330 @@
+use std::fmt;
+
+use crate::{
+    constants::PLAYER_COUNT_MAX,
+    variants::GameVariants,
+    world::{WorldInput, WORLD_INPUT_MOVE_STEPS},
+};
+
+// A replay is the seed a game started with plus the input of every simulation tick.
+// Stepping a fresh world with the same seed and inputs plays out the exact same game.
+//
+// file layout, all numbers little endian:
+//   magic "RDAR", format version u8,
+//   build version length u8 + utf8 bytes, seed u64, player count u8, variant bits u8,
+//   starting lives u8 (0 for the tuning's), tick count u32,
+//   then runs of identical ticks: every player's input bits u8 + move i8, then run length u16
+// move is move_x in steps of 1 / WORLD_INPUT_MOVE_STEPS
+
+const REPLAY_MAGIC: &[u8; 4] = b"RDAR";
+const REPLAY_FORMAT_VERSION: u8 = 5;
+// replays only play back correctly on the build that recorded them. Bump the simulation number
+// with every change that makes the same seed and inputs play out differently
+pub const BUILD_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "+sim.8");
+
+const INPUT_BIT_SHOOT: u8 = 1;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    NotAReplay,
+    UnsupportedFormat(u8),
+    BuildMismatch(String),
+    PlayerCount(u8),
+    Truncated,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "could not access replay file: {}", err),
+            ReplayError::NotAReplay => write!(f, "file is not a replay"),
+            ReplayError::UnsupportedFormat(format) => {
+                write!(f, "unsupported replay format version {}", format)
+            }
+            ReplayError::BuildMismatch(build_version) => write!(
+                f,
+                "replay was recorded with version {}, this is version {}",
+                build_version, BUILD_VERSION
+            ),
+            ReplayError::PlayerCount(player_count) => write!(
+                f,
+                "replay has {} players, the game supports 1 to {}",
+                player_count, PLAYER_COUNT_MAX
+            ),
+            ReplayError::Truncated => write!(f, "replay file is truncated"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+#[derive(Clone)]
+pub struct Replay {
+    pub build_version: String,
+    pub seed: u64,
+    pub player_count: usize,
+    pub variants: GameVariants,
+    // one entry per simulation tick, with the input of every player
+    pub inputs: Vec<Vec<WorldInput>>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, player_count: usize, variants: GameVariants) -> Self {
+        Replay {
+            build_version: BUILD_VERSION.to_owned(),
+            seed,
+            player_count,
+            variants,
+            inputs: Vec::new(),
+        }
+    }
+
+    fn input_to_bytes(input: &WorldInput) -> [u8; 2] {
+        let mut bits = 0u8;
+        if input.shoot {
+            bits |= INPUT_BIT_SHOOT;
+        }
+        let move_steps = (input.move_x * WORLD_INPUT_MOVE_STEPS).round() as i8;
+        [bits, move_steps as u8]
+    }
+
+    fn input_from_bytes(bytes: [u8; 2]) -> WorldInput {
+        let move_steps = bytes[1] as i8;
+        WorldInput::new(
+            move_steps as f32 / WORLD_INPUT_MOVE_STEPS,
+            bytes[0] & INPUT_BIT_SHOOT != 0,
+        )
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32 + self.inputs.len() / 8);
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.push(REPLAY_FORMAT_VERSION);
+        let build_version = &self.build_version.as_bytes()[..self.build_version.len().min(255)];
+        bytes.push(build_version.len() as u8);
+        bytes.extend_from_slice(build_version);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.player_count as u8);
+        bytes.push(self.variants.to_bits());
+        bytes.push(self.variants.starting_lives_byte());
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_le_bytes());
+
+        // inputs rarely change between ticks, so store runs of equal input
+        let mut run_optional: Option<(Vec<u8>, u16)> = None;
+        for tick_inputs in self.inputs.iter() {
+            // always player_count inputs, a missing one is stored as nothing pressed
+            let input_bytes: Vec<u8> = (0..self.player_count)
+                .flat_map(|i| {
+                    let input = tick_inputs.get(i).copied().unwrap_or_default();
+                    Self::input_to_bytes(&input).to_vec()
+                })
+                .collect();
+            run_optional = match run_optional {
+                Some((run_input, run_length))
+                    if run_input == input_bytes && run_length < u16::MAX =>
+                {
+                    Some((run_input, run_length + 1))
+                }
+                Some((run_input, run_length)) => {
+                    bytes.extend_from_slice(&run_input);
+                    bytes.extend_from_slice(&run_length.to_le_bytes());
+                    Some((input_bytes, 1))
+                }
+                None => Some((input_bytes, 1)),
+            };
+        }
+        if let Some((run_input, run_length)) = run_optional {
+            bytes.extend_from_slice(&run_input);
+            bytes.extend_from_slice(&run_length.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = ByteReader { bytes, cursor: 0 };
+        if reader.take(4).map_err(|_| ReplayError::NotAReplay)? != REPLAY_MAGIC {
+            return Err(ReplayError::NotAReplay);
+        }
+        let format_version = reader.take(1)?[0];
+        if format_version != REPLAY_FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedFormat(format_version));
+        }
+        let build_version_len = reader.take(1)?[0] as usize;
+        let build_version = String::from_utf8_lossy(reader.take(build_version_len)?).into_owned();
+        if build_version != BUILD_VERSION {
+            return Err(ReplayError::BuildMismatch(build_version));
+        }
+        let mut seed_bytes = [0u8; 8];
+        seed_bytes.copy_from_slice(reader.take(8)?);
+        let player_count = reader.take(1)?[0];
+        if player_count == 0 || player_count as usize > PLAYER_COUNT_MAX {
+            return Err(ReplayError::PlayerCount(player_count));
+        }
+        let player_count = player_count as usize;
+        let variant_bytes = reader.take(2)?;
+        let variants = GameVariants::from_bits(variant_bytes[0], variant_bytes[1]);
+        let mut tick_count_bytes = [0u8; 4];
+        tick_count_bytes.copy_from_slice(reader.take(4)?);
+        let tick_count = u32::from_le_bytes(tick_count_bytes) as usize;
+
+        // the tick count isn't trusted, every run takes at least 4 bytes
+        let mut inputs = Vec::with_capacity(tick_count.min(reader.remaining() / 4));
+        while inputs.len() < tick_count {
+            let input_bytes = reader.take(player_count * 2)?;
+            let input: Vec<WorldInput> = input_bytes
+                .chunks(2)
+                .map(|chunk| Self::input_from_bytes([chunk[0], chunk[1]]))
+                .collect();
+            let run_length_bytes = reader.take(2)?;
+            let run_length = u16::from_le_bytes([run_length_bytes[0], run_length_bytes[1]]);
+            for _ in 0..run_length {
+                inputs.push(input.clone());
+            }
+        }
+        inputs.truncate(tick_count);
+
+        Ok(Replay {
+            build_version,
+            seed: u64::from_le_bytes(seed_bytes),
+            player_count,
+            variants,
+            inputs,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ReplayError> {
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, ReplayError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.cursor
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], ReplayError> {
+        if self.cursor + count > self.bytes.len() {
+            return Err(ReplayError::Truncated);
+        }
+        let taken = &self.bytes[self.cursor..self.cursor + count];
+        self.cursor += count;
+        Ok(taken)
+    }
+}
+
+// watching a replay, feeds the recorded inputs back one tick at a time
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub tick: usize,
+    pub paused: bool,
+    // how many times faster than real time the replay runs
+    pub speed: f32,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayback {
+            replay,
+            tick: 0,
+            paused: false,
+            speed: 1f32,
+        }
+    }
+
+    // every player's input for the next tick
+    pub fn next_input(&mut self) -> Option<Vec<WorldInput>> {
+        let input_optional = self.replay.inputs.get(self.tick).cloned();
+        if input_optional.is_some() {
+            self.tick += 1;
+        }
+        input_optional
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.inputs.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_replay() -> Replay {
+        let variants = GameVariants {
+            starting_lives_optional: Some(3),
+            tracer_shots: true,
+            one_hit: true,
+            ..GameVariants::default()
+        };
+        let mut replay = Replay::new(1234, 2, variants);
+        for tick in 0..300 {
+            // long runs of the same input with some changes in between, analog moves included
+            let move_x = (tick / 50) as f32 / 2.5f32 - 1f32;
+            replay.inputs.push(vec![
+                WorldInput::new(move_x, tick % 7 == 0),
+                WorldInput::new(-move_x, tick % 11 == 0),
+            ]);
+        }
+        replay
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = test_replay();
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.build_version, replay.build_version);
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.player_count, replay.player_count);
+        assert_eq!(loaded.variants, replay.variants);
+        assert_eq!(loaded.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let bytes = test_replay().to_bytes();
+        for len in 0..bytes.len() {
+            assert!(Replay::from_bytes(&bytes[..len]).is_err(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn oversized_tick_count_is_an_error() {
+        let mut bytes = Replay::new(1, 1, GameVariants::default()).to_bytes();
+        let tick_count_start = bytes.len() - 4;
+        bytes[tick_count_start..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(ReplayError::Truncated)
+        ));
+    }
+    #[test]
+    fn bad_player_count_is_an_error() {
+        for player_count in [0, PLAYER_COUNT_MAX + 1].iter() {
+            let mut bytes = Replay::new(1, 1, GameVariants::default()).to_bytes();
+            let player_count_index = bytes.len() - 7;
+            bytes[player_count_index] = *player_count as u8;
+            assert!(matches!(
+                Replay::from_bytes(&bytes),
+                Err(ReplayError::PlayerCount(_))
+            ));
+        }
+    }
+}
@@ -1,76 +1,161 @@
 use macroquad::prelude::*;
 use quad_snd::{
-    decoder::read_wav_ext,
+    decoder::{self, read_wav_ext},
     mixer::{PlaybackStyle, SoundMixer},
     mixer::{Sound, Volume},
 };
 use std::collections::HashMap;
 
-use crate::enemy::{EnemyColor, EnemyType};
+use crate::{
+    animation::{load_sprite_sheet, SpriteSheet},
+    atlas::{Atlas, AtlasRegion},
+    enemy::{EnemyColor, EnemyType},
+    shaders::{load_crt_material, load_flash_material},
+    world::SpriteMetrics,
+};
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub enum SoundIdentifier {
     EnemyShoot,
     EnemyOuch,
+    // an armored enemy hit without dying
+    EnemyHit,
     PlayerOuch,
     PlayerShoot,
     SpawnMini,
     Spawn,
     Warning,
     WaveCleared,
+    PowerUp,
+    // a power-up ran out
+    PowerUpEnd,
+}
+
+// where in Resources (and in the atlas) a texture file was loaded to, so it can be reloaded in place
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureSlot {
+    DemonMissile,
+    PlayerMissile,
+    Player,
+    PlayerExplotion,
+    GroundBg,
+    Life,
+    DemonBossBody,
+    DemonCracks,
+    Pickups,
+    Border,
+    // index into the sprite variants of that enemy type and color
+    Enemy(EnemyType, EnemyColor, usize),
+}
+
+impl TextureSlot {
+    // bosses look the same in every color, so their sprites are only kept under one
+    pub fn enemy(enemy_type: EnemyType, enemy_color: EnemyColor, index: usize) -> Self {
+        let enemy_color = match enemy_type {
+            EnemyType::Boss => EnemyColor::Red,
+            _ => enemy_color,
+        };
+        TextureSlot::Enemy(enemy_type, enemy_color, index)
+    }
 }
 
+// the sprites are regions of one atlas texture, see atlas.rs
 pub struct Resources {
-    pub demons_normal_purple: Vec<Texture2D>,
-    pub demons_normal_green: Vec<Texture2D>,
-    pub demons_normal_red: Vec<Texture2D>,
-    pub demons_mini_purple: Vec<Texture2D>,
-    pub demons_mini_green: Vec<Texture2D>,
-    pub demons_mini_red: Vec<Texture2D>,
-
-    pub demon_missile: Texture2D,
-    pub player_missile: Texture2D,
-    pub player: Texture2D,
-    pub player_explotion: Texture2D,
-    pub ground_bg: Texture2D,
-    pub life: Texture2D,
+    pub demons_normal_purple: Vec<AtlasRegion>,
+    pub demons_normal_green: Vec<AtlasRegion>,
+    pub demons_normal_red: Vec<AtlasRegion>,
+    pub demons_mini_purple: Vec<AtlasRegion>,
+    pub demons_mini_green: Vec<AtlasRegion>,
+    pub demons_mini_red: Vec<AtlasRegion>,
+    // boss wings, the body is the same for every boss
+    pub demons_boss: Vec<AtlasRegion>,
+    pub demon_boss_body: AtlasRegion,
+    // drawn over the wings of armored enemies as they lose health, a frame per stage
+    pub demon_cracks: AtlasRegion,
+    // a frame per PickupKind
+    pub pickups: AtlasRegion,
+    // tiled around the game instead of black bars when turned on in the options
+    pub border: AtlasRegion,
+
+    pub demon_missile: AtlasRegion,
+    pub player_missile: AtlasRegion,
+    pub player: AtlasRegion,
+    pub player_explotion: AtlasRegion,
+    pub ground_bg: AtlasRegion,
+    pub life: AtlasRegion,
 
     pub font: Font,
+    // enemies are drawn with it for a moment after a hit, None when the shader didn't compile
+    pub flash_material_optional: Option<Material>,
+    // None when the CRT shader didn't compile, the game is drawn without it then
+    pub crt_material_optional: Option<Material>,
 
     pub sounds: HashMap<SoundIdentifier, Sound>,
+
+    // every sprite png packed together, the regions above point into it
+    pub atlas: Atlas,
+    // every texture file that was loaded and where it went
+    pub texture_paths: Vec<(String, TextureSlot)>,
+    // the frames and clips of every loaded texture, from the sidecar files next to the pngs
+    pub sprite_sheets: HashMap<TextureSlot, SpriteSheet>,
 }
 
 impl Resources {
+    // the regions of every slot are taken from the atlas
     pub fn new(
-        demon_missile: Texture2D,
-        player_missile: Texture2D,
-        player: Texture2D,
-        player_explotion: Texture2D,
-        ground_bg: Texture2D,
-        life: Texture2D,
+        atlas: Atlas,
         font: Font,
+        flash_material_optional: Option<Material>,
+        crt_material_optional: Option<Material>,
     ) -> Self {
+        let region = |slot: TextureSlot| atlas.region(slot).unwrap();
+        // the variants of one enemy type and color, in the order they were loaded
+        let enemy_regions = |enemy_type: EnemyType, enemy_color: EnemyColor| {
+            (0..)
+                .map_while(|index| atlas.region(TextureSlot::enemy(enemy_type, enemy_color, index)))
+                .collect::<Vec<AtlasRegion>>()
+        };
         Resources {
-            demons_normal_purple: Vec::<Texture2D>::new(),
-            demons_normal_green: Vec::<Texture2D>::new(),
-            demons_normal_red: Vec::<Texture2D>::new(),
-            demons_mini_purple: Vec::<Texture2D>::new(),
-            demons_mini_green: Vec::<Texture2D>::new(),
-            demons_mini_red: Vec::<Texture2D>::new(),
-            demon_missile,
-            player_missile,
-            player,
-            player_explotion,
-            ground_bg,
-            life,
+            demons_normal_purple: enemy_regions(EnemyType::Normal, EnemyColor::Purple),
+            demons_normal_green: enemy_regions(EnemyType::Normal, EnemyColor::Green),
+            demons_normal_red: enemy_regions(EnemyType::Normal, EnemyColor::Red),
+            demons_mini_purple: enemy_regions(EnemyType::Mini, EnemyColor::Purple),
+            demons_mini_green: enemy_regions(EnemyType::Mini, EnemyColor::Green),
+            demons_mini_red: enemy_regions(EnemyType::Mini, EnemyColor::Red),
+            demons_boss: enemy_regions(EnemyType::Boss, EnemyColor::Red),
+            demon_boss_body: region(TextureSlot::DemonBossBody),
+            demon_cracks: region(TextureSlot::DemonCracks),
+            pickups: region(TextureSlot::Pickups),
+            border: region(TextureSlot::Border),
+            demon_missile: region(TextureSlot::DemonMissile),
+            player_missile: region(TextureSlot::PlayerMissile),
+            player: region(TextureSlot::Player),
+            player_explotion: region(TextureSlot::PlayerExplotion),
+            ground_bg: region(TextureSlot::GroundBg),
+            life: region(TextureSlot::Life),
             font,
+            flash_material_optional,
+            crt_material_optional,
             sounds: HashMap::new(),
+            atlas,
+            texture_paths: Vec::new(),
+            sprite_sheets: HashMap::new(),
         }
     }
 
     pub fn load_sound(&mut self, bytes: &[u8], identifier: SoundIdentifier) {
-        let sound = read_wav_ext(bytes, PlaybackStyle::Once).unwrap();
+        self.try_load_sound(bytes, identifier).unwrap();
+    }
+
+    // replaces the sound if it was loaded before
+    pub fn try_load_sound(
+        &mut self,
+        bytes: &[u8],
+        identifier: SoundIdentifier,
+    ) -> Result<(), decoder::Error> {
+        let sound = read_wav_ext(bytes, PlaybackStyle::Once)?;
         self.sounds.insert(identifier, sound);
+        Ok(())
     }
 
     pub fn play_sound(&self, identifier: SoundIdentifier, mixer: &mut SoundMixer, volume: Volume) {
@@ -79,15 +164,12 @@ impl Resources {
         }
     }
 
-    pub async fn load_texture(
+    fn enemy_regions_mut(
         &mut self,
-        file_name: &str,
-        enemy_color: EnemyColor,
         enemy_type: EnemyType,
-    ) -> Result<(), FileError> {
-        let texture: Texture2D = load_texture(file_name).await?;
-        texture.set_filter(FilterMode::Nearest);
-        let texture_vec = match enemy_type {
+        enemy_color: EnemyColor,
+    ) -> &mut Vec<AtlasRegion> {
+        match enemy_type {
             EnemyType::Normal => match enemy_color {
                 EnemyColor::Purple => &mut self.demons_normal_purple,
                 EnemyColor::Green => &mut self.demons_normal_green,
@@ -98,26 +180,114 @@ impl Resources {
                 EnemyColor::Green => &mut self.demons_mini_green,
                 EnemyColor::Red => &mut self.demons_mini_red,
             },
+            EnemyType::Boss => &mut self.demons_boss,
+        }
+    }
+
+    fn region_mut(&mut self, slot: TextureSlot) -> Option<&mut AtlasRegion> {
+        let region = match slot {
+            TextureSlot::DemonMissile => &mut self.demon_missile,
+            TextureSlot::PlayerMissile => &mut self.player_missile,
+            TextureSlot::Player => &mut self.player,
+            TextureSlot::PlayerExplotion => &mut self.player_explotion,
+            TextureSlot::GroundBg => &mut self.ground_bg,
+            TextureSlot::Life => &mut self.life,
+            TextureSlot::DemonBossBody => &mut self.demon_boss_body,
+            TextureSlot::DemonCracks => &mut self.demon_cracks,
+            TextureSlot::Pickups => &mut self.pickups,
+            TextureSlot::Border => &mut self.border,
+            TextureSlot::Enemy(enemy_type, enemy_color, index) => {
+                return self
+                    .enemy_regions_mut(enemy_type, enemy_color)
+                    .get_mut(index)
+            }
         };
-        texture_vec.push(texture);
-        Ok(())
+        Some(region)
     }
-    pub fn rand_enemy_normal(&self, enemy_color: EnemyColor) -> Texture2D {
-        let normal_list = match enemy_color {
-            EnemyColor::Purple => &self.demons_normal_purple,
-            EnemyColor::Green => &self.demons_normal_green,
-            EnemyColor::Red => &self.demons_normal_red,
+
+    // swap in a reloaded png, the atlas is packed again so every region moves
+    pub fn replace_image(&mut self, slot: TextureSlot, image: Image) {
+        self.atlas.replace_image(slot, image);
+        for slot in self.atlas.slots() {
+            let region_optional = self.atlas.region(slot);
+            if let (Some(region), Some(old_region)) = (region_optional, self.region_mut(slot)) {
+                *old_region = region;
+            }
+        }
+    }
+
+    // the size a sprite sheet's frames are cut from
+    pub fn texture_size(&self, slot: TextureSlot) -> Vec2 {
+        self.atlas
+            .region(slot)
+            .map(|region| region.size())
+            .unwrap_or(Vec2::ZERO)
+    }
+
+    pub fn sprite_sheet(&self, slot: TextureSlot) -> &SpriteSheet {
+        &self.sprite_sheets[&slot]
+    }
+
+    pub fn enemy_sprite_sheet(
+        &self,
+        enemy_type: EnemyType,
+        enemy_color: EnemyColor,
+        sprite_variant: usize,
+    ) -> &SpriteSheet {
+        self.sprite_sheet(TextureSlot::enemy(enemy_type, enemy_color, sprite_variant))
+    }
+
+    pub fn enemy_texture(
+        &self,
+        enemy_type: EnemyType,
+        enemy_color: EnemyColor,
+        sprite_variant: usize,
+    ) -> AtlasRegion {
+        let texture_vec = match enemy_type {
+            EnemyType::Normal => match enemy_color {
+                EnemyColor::Purple => &self.demons_normal_purple,
+                EnemyColor::Green => &self.demons_normal_green,
+                EnemyColor::Red => &self.demons_normal_red,
+            },
+            EnemyType::Mini => match enemy_color {
+                EnemyColor::Purple => &self.demons_mini_purple,
+                EnemyColor::Green => &self.demons_mini_green,
+                EnemyColor::Red => &self.demons_mini_red,
+            },
+            EnemyType::Boss => &self.demons_boss,
         };
-        normal_list[rand::gen_range(0, normal_list.len())]
+        texture_vec[sprite_variant]
     }
 
-    pub fn rand_enemy_mini(&self, enemy_color: EnemyColor) -> Texture2D {
-        let mini_list = match enemy_color {
-            EnemyColor::Purple => &self.demons_mini_purple,
-            EnemyColor::Green => &self.demons_mini_green,
-            EnemyColor::Red => &self.demons_mini_red,
+    // the sizes the simulation needs, measured from the loaded textures
+    pub fn sprite_metrics(&self) -> SpriteMetrics {
+        let sizes = |texture_vec: &Vec<AtlasRegion>| {
+            texture_vec
+                .iter()
+                .map(|region| region.size())
+                .collect::<Vec<Vec2>>()
         };
-        mini_list[rand::gen_range(0, mini_list.len())]
+        SpriteMetrics {
+            player: self.player.size(),
+            ground_height: self.ground_bg.height(),
+            demons_normal_purple: sizes(&self.demons_normal_purple),
+            demons_normal_green: sizes(&self.demons_normal_green),
+            demons_normal_red: sizes(&self.demons_normal_red),
+            demons_mini_purple: sizes(&self.demons_mini_purple),
+            demons_mini_green: sizes(&self.demons_mini_green),
+            demons_mini_red: sizes(&self.demons_mini_red),
+            demons_boss: (0..self.demons_boss.len())
+                .map(|sprite_variant| {
+                    let wing_sheet =
+                        self.enemy_sprite_sheet(EnemyType::Boss, EnemyColor::Red, sprite_variant);
+                    let body_sheet = self.sprite_sheet(TextureSlot::DemonBossBody);
+                    SpriteMetrics::boss_size(
+                        wing_sheet.clip("flap").frame(0).rect.size(),
+                        body_sheet.clip("mouth_closed").frame(0).rect.size(),
+                    )
+                })
+                .collect(),
+        }
     }
 }
 
@@ -130,86 +300,134 @@ const SOUND_BYTES_PLAYER_SHOOT: &[u8] =
 const SOUND_BYTES_PLAYER_OUCH: &[u8] =
     include_bytes!("../resources/sounds/player_ouch.wav");
 const SOUND_BYTES_ENEMY_OUCH: &[u8] = include_bytes!("../resources/sounds/enemy_ouch.wav");
+const SOUND_BYTES_ENEMY_HIT: &[u8] = include_bytes!("../resources/sounds/enemy_hit.wav");
 const SOUND_BYTES_SPAWN_MINI: &[u8] = include_bytes!("../resources/sounds/spawn_mini.wav");
 const SOUND_BYTES_WARNING: &[u8] = include_bytes!("../resources/sounds/warning.wav");
 const SOUND_BYTES_WAVE_CLEARED: &[u8] =
     include_bytes!("../resources/sounds/wave_cleared.wav");
+const SOUND_BYTES_POWER_UP: &[u8] = include_bytes!("../resources/sounds/power_up.wav");
+const SOUND_BYTES_POWER_UP_END: &[u8] = include_bytes!("../resources/sounds/power_up_end.wav");
+
+// the files the sounds above are built from, watched for hot reloading
+pub const SOUND_PATHS: &[(SoundIdentifier, &str)] = &[
+    (
+        SoundIdentifier::EnemyShoot,
+        "resources/sounds/enemy_shoot.wav",
+    ),
+    (
+        SoundIdentifier::PlayerShoot,
+        "resources/sounds/player_shoot.wav",
+    ),
+    (SoundIdentifier::Spawn, "resources/sounds/spawn.wav"),
+    (
+        SoundIdentifier::PlayerOuch,
+        "resources/sounds/player_ouch.wav",
+    ),
+    (
+        SoundIdentifier::EnemyOuch,
+        "resources/sounds/enemy_ouch.wav",
+    ),
+    (SoundIdentifier::EnemyHit, "resources/sounds/enemy_hit.wav"),
+    (
+        SoundIdentifier::SpawnMini,
+        "resources/sounds/spawn_mini.wav",
+    ),
+    (SoundIdentifier::Warning, "resources/sounds/warning.wav"),
+    (
+        SoundIdentifier::WaveCleared,
+        "resources/sounds/wave_cleared.wav",
+    ),
+    (SoundIdentifier::PowerUp, "resources/sounds/power_up.wav"),
+    (
+        SoundIdentifier::PowerUpEnd,
+        "resources/sounds/power_up_end.wav",
+    ),
+];
 
 pub async fn load_resources(game_render_target: RenderTarget) -> Resources {
-    let texture_player: Texture2D = load_texture("resources/player.png").await.unwrap();
-    let texture_player_explotion: Texture2D = load_texture("resources/player_explotion.png")
-        .await
-        .unwrap();
-    let texture_player_missile: Texture2D =
-        load_texture("resources/player_missile.png").await.unwrap();
-    let texture_demon_missile: Texture2D =
-        load_texture("resources/demon_missile.png").await.unwrap();
-    let texture_ground_bg: Texture2D = load_texture("resources/ground_bg.png").await.unwrap();
-    let texture_life: Texture2D = load_texture("resources/life.png").await.unwrap();
-
-    // set all textures filter mode to nearest
-    for texture in [
-        texture_player,
-        texture_player_explotion,
-        texture_player_missile,
-        texture_demon_missile,
-        texture_ground_bg,
-        texture_life,
-        game_render_target.texture,
+    game_render_target.texture.set_filter(FilterMode::Nearest);
+
+    let mut texture_paths: Vec<(String, TextureSlot)> = [
+        ("resources/player.png", TextureSlot::Player),
+        (
+            "resources/player_explotion.png",
+            TextureSlot::PlayerExplotion,
+        ),
+        ("resources/player_missile.png", TextureSlot::PlayerMissile),
+        ("resources/demon_missile.png", TextureSlot::DemonMissile),
+        ("resources/ground_bg.png", TextureSlot::GroundBg),
+        ("resources/life.png", TextureSlot::Life),
+        ("resources/demon_boss_body.png", TextureSlot::DemonBossBody),
+        ("resources/demon_cracks.png", TextureSlot::DemonCracks),
+        ("resources/pickups.png", TextureSlot::Pickups),
+        ("resources/border.png", TextureSlot::Border),
     ]
     .iter()
+    .map(|(path, slot)| (path.to_string(), *slot))
+    .collect();
     {
-        texture.set_filter(FilterMode::Nearest);
+        use EnemyColor::{Green, Purple, Red};
+        use EnemyType::{Boss, Mini, Normal};
+        // the sprite variants of each enemy type and color are numbered in this order
+        let mut variant_counts = HashMap::<(EnemyType, EnemyColor), usize>::new();
+        for (path, enemy_color, enemy_type) in [
+            ("resources/demon_mini_green_1.png", Green, Mini),
+            ("resources/demon_mini_red_1.png", Red, Mini),
+            ("resources/demon_mini_purple_1.png", Purple, Mini),
+            ("resources/demon_normal_green_1.png", Green, Normal),
+            ("resources/demon_normal_green_2.png", Green, Normal),
+            ("resources/demon_normal_purple_1.png", Purple, Normal),
+            ("resources/demon_normal_purple_2.png", Purple, Normal),
+            ("resources/demon_normal_red_1.png", Red, Normal),
+            // bosses look the same in every color, their color only goes to the minis they summon
+            ("resources/demon_boss_wing.png", Red, Boss),
+        ]
+        .iter()
+        {
+            let variant_count = variant_counts
+                .entry((*enemy_type, *enemy_color))
+                .or_insert(0);
+            let slot = TextureSlot::enemy(*enemy_type, *enemy_color, *variant_count);
+            *variant_count += 1;
+            texture_paths.push((path.to_string(), slot));
+        }
     }
 
+    // packed into one texture so drawing sprites doesn't switch textures
+    let mut images = Vec::new();
+    for (path, slot) in texture_paths.iter() {
+        images.push((*slot, load_image(path).await.unwrap()));
+    }
+    let atlas = Atlas::new(images);
+
     let font = load_ttf_font("resources/Kenney Pixel Square.ttf")
         .await
         .unwrap();
-    let mut resources = Resources::new(
-        texture_demon_missile,
-        texture_player_missile,
-        texture_player,
-        texture_player_explotion,
-        texture_ground_bg,
-        texture_life,
-        font,
-    );
+    let flash_material_optional = match load_flash_material() {
+        Ok(flash_material) => Some(flash_material),
+        Err(err) => {
+            eprintln!(
+                "flash shader didn't compile, hits are shown without it: {}",
+                err
+            );
+            None
+        }
+    };
+    let crt_material_optional = match load_crt_material() {
+        Ok(crt_material) => Some(crt_material),
+        Err(err) => {
+            eprintln!("CRT shader didn't compile, drawing without it: {}", err);
+            None
+        }
+    };
+    let mut resources = Resources::new(atlas, font, flash_material_optional, crt_material_optional);
+    resources.texture_paths = texture_paths;
 
-    {
-        use EnemyColor::{Green, Purple, Red};
-        use EnemyType::{Mini, Normal};
-        resources
-            .load_texture("resources/demon_mini_green_1.png", Green, Mini)
-            .await
-            .unwrap();
-        resources
-            .load_texture("resources/demon_mini_red_1.png", Red, Mini)
-            .await
-            .unwrap();
-        resources
-            .load_texture("resources/demon_mini_purple_1.png", Purple, Mini)
-            .await
-            .unwrap();
-        resources
-            .load_texture("resources/demon_normal_green_1.png", Green, Normal)
-            .await
-            .unwrap();
-        resources
-            .load_texture("resources/demon_normal_green_2.png", Green, Normal)
-            .await
-            .unwrap();
-        resources
-            .load_texture("resources/demon_normal_purple_1.png", Purple, Normal)
-            .await
-            .unwrap();
-        resources
-            .load_texture("resources/demon_normal_purple_2.png", Purple, Normal)
-            .await
-            .unwrap();
-        resources
-            .load_texture("resources/demon_normal_red_1.png", Red, Normal)
-            .await
-            .unwrap();
+    // any of the pngs can have a sidecar file cutting it into frames and clips
+    for (path, slot) in resources.texture_paths.clone() {
+        let texture_size = resources.texture_size(slot);
+        let sprite_sheet = load_sprite_sheet(&path, texture_size).await;
+        resources.sprite_sheets.insert(slot, sprite_sheet);
     }
     {
         use SoundIdentifier::*;
@@ -218,9 +436,12 @@ pub async fn load_resources(game_render_target: RenderTarget) -> Resources {
         resources.load_sound(SOUND_BYTES_SPAWN, Spawn);
         resources.load_sound(SOUND_BYTES_PLAYER_OUCH, PlayerOuch);
         resources.load_sound(SOUND_BYTES_ENEMY_OUCH, EnemyOuch);
+        resources.load_sound(SOUND_BYTES_ENEMY_HIT, EnemyHit);
         resources.load_sound(SOUND_BYTES_SPAWN_MINI, SpawnMini);
         resources.load_sound(SOUND_BYTES_WARNING, Warning);
         resources.load_sound(SOUND_BYTES_WAVE_CLEARED, WaveCleared);
+        resources.load_sound(SOUND_BYTES_POWER_UP, PowerUp);
+        resources.load_sound(SOUND_BYTES_POWER_UP_END, PowerUpEnd);
     }
     resources
 }
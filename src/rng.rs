@@ -0,0 +1,61 @@
+// Small seedable random number generator (splitmix64).
+// All gameplay randomness goes through the GameRng owned by the world,
+// so the same seed and the same inputs always play out the same game.
+// Purely cosmetic randomness (like flickering spawn frames) can keep using macroquad::rand.
+#[derive(Clone)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // 0 -> 1, excluding 1
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // same semantics as macroquad::rand::gen_range, high is exclusive for integers
+    pub fn gen_range<T: RandomRange>(&mut self, low: T, high: T) -> T {
+        T::gen_range(self, low, high)
+    }
+}
+
+pub trait RandomRange {
+    fn gen_range(rng: &mut GameRng, low: Self, high: Self) -> Self;
+}
+
+impl RandomRange for f32 {
+    fn gen_range(rng: &mut GameRng, low: Self, high: Self) -> Self {
+        low + (high - low) * rng.next_f32()
+    }
+}
+
+impl RandomRange for i32 {
+    fn gen_range(rng: &mut GameRng, low: Self, high: Self) -> Self {
+        if high <= low {
+            return low;
+        }
+        let range = (high as i64 - low as i64) as u64;
+        (low as i64 + (rng.next_u64() % range) as i64) as i32
+    }
+}
+
+impl RandomRange for usize {
+    fn gen_range(rng: &mut GameRng, low: Self, high: Self) -> Self {
+        if high <= low {
+            return low;
+        }
+        low + (rng.next_u64() % (high - low) as u64) as usize
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::*, crt::CrtPreset, input::Bindings, storage, variants::GameVariants,
+    viewport::ViewportMode,
+};
+
+// where the settings are kept, see storage.rs
+const SETTINGS_STORAGE_KEY: &str = "settings";
+
+// player preferences, changed from the options menu and kept between runs
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // pause the game when the window loses focus
+    pub pause_on_focus_loss: bool,
+    // player one's
+    pub bindings: Bindings,
+    pub player_two_bindings: Bindings,
+    // the variants picked in the menu last time
+    pub variants: GameVariants,
+    // 0 -> 1, 0 turns them off for players sensitive to motion
+    pub shake_intensity: f32,
+    pub hit_stop_intensity: f32,
+    // the post-processing used when the game is scaled up to the window
+    pub crt_preset: CrtPreset,
+    // how the game is fit into the window
+    pub viewport_mode: ViewportMode,
+    pub fullscreen: bool,
+    // the border art instead of black bars around the game
+    pub show_border: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            pause_on_focus_loss: true,
+            bindings: Bindings::default(),
+            player_two_bindings: Bindings::default_player_two(),
+            variants: GameVariants::default(),
+            shake_intensity: 1f32,
+            hit_stop_intensity: 1f32,
+            crt_preset: CrtPreset::Off,
+            viewport_mode: ViewportMode::AspectFit,
+            fullscreen: false,
+            show_border: false,
+        }
+    }
+}
+
+impl Settings {
+    // missing settings are the defaults, broken ones are reported and replaced by the defaults
+    pub fn load() -> Self {
+        let mut settings = match storage::load_string(SETTINGS_STORAGE_KEY) {
+            Some(source) => ron::de::from_str(&source).unwrap_or_else(|err| {
+                eprintln!("settings are not valid RON, using defaults: {}", err);
+                Settings::default()
+            }),
+            None => Settings::default(),
+        };
+        // a hand edited file can't make the game shake or freeze more than the options can
+        settings.shake_intensity = clamp_intensity(settings.shake_intensity);
+        settings.hit_stop_intensity = clamp_intensity(settings.hit_stop_intensity);
+        settings
+    }
+
+    // player is 0 for player one, 1 for player two
+    pub fn bindings_for(&self, player: usize) -> &Bindings {
+        match player {
+            0 => &self.bindings,
+            _ => &self.player_two_bindings,
+        }
+    }
+
+    pub fn bindings_for_mut(&mut self, player: usize) -> &mut Bindings {
+        match player {
+            0 => &mut self.bindings,
+            _ => &mut self.player_two_bindings,
+        }
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|source| storage::save_string(SETTINGS_STORAGE_KEY, &source));
+        if let Err(err) = result {
+            eprintln!("failed to save settings: {}", err);
+        }
+    }
+}
+
+// 0 up to the strongest of JUICE_INTENSITY_STEPS, NaN ends up 0
+fn clamp_intensity(intensity: f32) -> f32 {
+    let strongest = JUICE_INTENSITY_STEPS.iter().cloned().fold(0f32, f32::max);
+    intensity.max(0f32).min(strongest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intensities_are_clamped_to_the_option_steps() {
+        assert_eq!(clamp_intensity(0.5f32), 0.5f32);
+        assert_eq!(clamp_intensity(-1f32), 0f32);
+        assert_eq!(clamp_intensity(100f32), 1f32);
+        assert_eq!(clamp_intensity(f32::NAN), 0f32);
+    }
+}
@@ -0,0 +1,129 @@
+use macroquad::{
+    material::{load_material, Material, MaterialParams},
+    window::miniquad::{
+        BlendFactor, BlendState, BlendValue, Equation, PipelineParams, UniformType,
+    },
+};
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+
+varying lowp vec2 uv;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    uv = texcoord;
+}"#;
+
+// every pixel of the sprite turns white, transparent pixels stay transparent
+const FLASH_FRAGMENT_SHADER: &str = r#"#version 100
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = vec4(1.0, 1.0, 1.0, texture2D(Texture, uv).a);
+}"#;
+
+// the low res game as an old CRT would show it, every effect is scaled by its uniform, 0 is off
+const CRT_FRAGMENT_SHADER: &str = r#"#version 100
+precision mediump float;
+
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+// game pixels
+uniform vec2 TextureSize;
+uniform float Scanlines;
+uniform float Curvature;
+uniform float Bloom;
+uniform float ColorBleed;
+uniform float Vignette;
+
+void main() {
+    vec2 coord = uv;
+    // bulge the picture out like the glass of the tube, the corners fall off the screen
+    vec2 centered = coord * 2.0 - 1.0;
+    vec2 bend = abs(centered.yx) / vec2(5.0, 4.0);
+    centered += centered * bend * bend * Curvature;
+    coord = centered * 0.5 + 0.5;
+    if (coord.x < 0.0 || coord.x > 1.0 || coord.y < 0.0 || coord.y > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec2 texel = 1.0 / TextureSize;
+    vec3 color = texture2D(Texture, coord).rgb;
+
+    // red and blue land a bit off to the sides
+    color.r = mix(color.r, texture2D(Texture, coord - vec2(texel.x, 0.0)).r, ColorBleed);
+    color.b = mix(color.b, texture2D(Texture, coord + vec2(texel.x, 0.0)).b, ColorBleed);
+
+    // bright pixels glow into their neighbours
+    vec3 glow = texture2D(Texture, coord + vec2(texel.x, 0.0)).rgb
+        + texture2D(Texture, coord - vec2(texel.x, 0.0)).rgb
+        + texture2D(Texture, coord + vec2(0.0, texel.y)).rgb
+        + texture2D(Texture, coord - vec2(0.0, texel.y)).rgb;
+    color += glow * 0.25 * Bloom;
+
+    // dark gaps between the rows of game pixels
+    float row = abs(fract(coord.y * TextureSize.y) - 0.5) * 2.0;
+    color *= 1.0 - Scanlines * row * row;
+
+    // darker towards the edges
+    vec2 edge = coord * (1.0 - coord.yx);
+    float vignette = pow(clamp(edge.x * edge.y * 15.0, 0.0, 1.0), 0.25);
+    color *= mix(1.0, vignette, Vignette);
+
+    gl_FragColor = vec4(color, 1.0);
+}"#;
+
+fn alpha_blend() -> PipelineParams {
+    PipelineParams {
+        color_blend: Some(BlendState::new(
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        )),
+        ..Default::default()
+    }
+}
+
+// draws sprites as a white silhouette, used when something gets hit. Shaders can fail to compile
+// on some drivers, the error is returned
+pub fn load_flash_material() -> Result<Material, String> {
+    load_material(
+        VERTEX_SHADER,
+        FLASH_FRAGMENT_SHADER,
+        MaterialParams {
+            pipeline_params: alpha_blend(),
+            ..Default::default()
+        },
+    )
+    .map_err(|err| format!("{:?}", err))
+}
+
+// used when upscaling the game to the window, see crt.rs. Like the flash shader it can fail to
+// compile, the error is returned so the game can fall back to drawing without it
+pub fn load_crt_material() -> Result<Material, String> {
+    load_material(
+        VERTEX_SHADER,
+        CRT_FRAGMENT_SHADER,
+        MaterialParams {
+            uniforms: vec![
+                ("TextureSize".to_owned(), UniformType::Float2),
+                ("Scanlines".to_owned(), UniformType::Float1),
+                ("Curvature".to_owned(), UniformType::Float1),
+                ("Bloom".to_owned(), UniformType::Float1),
+                ("ColorBleed".to_owned(), UniformType::Float1),
+                ("Vignette".to_owned(), UniformType::Float1),
+            ],
+            ..Default::default()
+        },
+    )
+    .map_err(|err| format!("{:?}", err))
+}
@@ -0,0 +1,63 @@
+// Small text files the game keeps between runs (high scores, settings).
+// On desktop every key is a file in SAVE_DIRECTORY, on wasm it's the browser's localStorage
+// through utils/wasm/storage.js.
+
+use crate::constants::SAVE_DIRECTORY;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn key_path(key: &str) -> std::path::PathBuf {
+    std::path::Path::new(SAVE_DIRECTORY).join(format!("{}.ron", key))
+}
+
+// None if nothing was saved under the key yet
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_string(key: &str) -> Option<String> {
+    std::fs::read_to_string(key_path(key)).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_string(key: &str, value: &str) -> Result<(), String> {
+    std::fs::create_dir_all(SAVE_DIRECTORY).map_err(|err| err.to_string())?;
+    std::fs::write(key_path(key), value).map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn storage_set(key: *const u8, key_len: u32, value: *const u8, value_len: u32);
+    // -1 if the key isn't stored
+    fn storage_get_len(key: *const u8, key_len: u32) -> i32;
+    fn storage_get(key: *const u8, key_len: u32, buffer: *mut u8, buffer_len: u32);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_string(key: &str) -> Option<String> {
+    let key = format!("{}/{}", SAVE_DIRECTORY, key);
+    let len = unsafe { storage_get_len(key.as_ptr(), key.len() as u32) };
+    if len < 0 {
+        return None;
+    }
+    let mut buffer = vec![0u8; len as usize];
+    unsafe {
+        storage_get(
+            key.as_ptr(),
+            key.len() as u32,
+            buffer.as_mut_ptr(),
+            buffer.len() as u32,
+        );
+    }
+    String::from_utf8(buffer).ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_string(key: &str, value: &str) -> Result<(), String> {
+    let key = format!("{}/{}", SAVE_DIRECTORY, key);
+    unsafe {
+        storage_set(
+            key.as_ptr(),
+            key.len() as u32,
+            value.as_ptr(),
+            value.len() as u32,
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use macroquad::prelude::*;
+
+use crate::{constants::*, viewport::Viewport};
+
+// which on screen buttons are held
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct TouchButtons {
+    pub left: bool,
+    pub right: bool,
+    pub fire: bool,
+    pub pause: bool,
+}
+
+// the buttons are placed in game coordinates, so they scale with the game and stay clear of the black bars
+fn left_rect() -> Rect {
+    Rect::new(
+        TOUCH_BUTTON_MARGIN,
+        GAME_SIZE_Y as f32 - TOUCH_BUTTON_SIZE - TOUCH_BUTTON_MARGIN,
+        TOUCH_BUTTON_SIZE,
+        TOUCH_BUTTON_SIZE,
+    )
+}
+
+fn right_rect() -> Rect {
+    Rect::new(
+        TOUCH_BUTTON_MARGIN * 2f32 + TOUCH_BUTTON_SIZE,
+        GAME_SIZE_Y as f32 - TOUCH_BUTTON_SIZE - TOUCH_BUTTON_MARGIN,
+        TOUCH_BUTTON_SIZE,
+        TOUCH_BUTTON_SIZE,
+    )
+}
+
+fn fire_rect() -> Rect {
+    Rect::new(
+        GAME_SIZE_X as f32 - TOUCH_BUTTON_SIZE - TOUCH_BUTTON_MARGIN,
+        GAME_SIZE_Y as f32 - TOUCH_BUTTON_SIZE - TOUCH_BUTTON_MARGIN,
+        TOUCH_BUTTON_SIZE,
+        TOUCH_BUTTON_SIZE,
+    )
+}
+
+// smaller, in the top right corner so it isn't hit by accident
+fn pause_rect() -> Rect {
+    let size = TOUCH_BUTTON_SIZE * 0.5f32;
+    Rect::new(
+        GAME_SIZE_X as f32 - size - TOUCH_BUTTON_MARGIN,
+        TOUCH_BUTTON_MARGIN,
+        size,
+        size,
+    )
+}
+
+// Reads the touch screen once per frame.
+// The on screen buttons only show up after the first touch, so mouse and keyboard players never see them.
+pub struct TouchControls {
+    pub enabled: bool,
+    pub down: TouchButtons,
+    previous_down: TouchButtons,
+    // a finger touched the screen this frame, anywhere
+    pub tapped: bool,
+}
+
+impl TouchControls {
+    pub fn new() -> Self {
+        TouchControls {
+            enabled: false,
+            down: TouchButtons::default(),
+            previous_down: TouchButtons::default(),
+            tapped: false,
+        }
+    }
+
+    pub fn update(&mut self, viewport: &Viewport) {
+        self.previous_down = self.down;
+        self.down = TouchButtons::default();
+        self.tapped = false;
+        for touch in touches() {
+            self.enabled = true;
+            match touch.phase {
+                TouchPhase::Started => self.tapped = true,
+                TouchPhase::Ended | TouchPhase::Cancelled => continue,
+                _ => {}
+            }
+            let pos = viewport.window_to_game(touch.position);
+            self.down.left |= left_rect().contains(pos);
+            self.down.right |= right_rect().contains(pos);
+            self.down.fire |= fire_rect().contains(pos);
+            self.down.pause |= pause_rect().contains(pos);
+        }
+    }
+
+    // true on the frame the pause button is touched
+    pub fn pause_pressed(&self) -> bool {
+        self.down.pause && !self.previous_down.pause
+    }
+}
+
+impl Default for TouchControls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// draws the buttons over the game in window pixels, held buttons light up
+pub fn draw_touch_controls(down: TouchButtons, font: Font, viewport: &Viewport) {
+    let buttons = [
+        (left_rect(), "<", down.left),
+        (right_rect(), ">", down.right),
+        (fire_rect(), "FIRE", down.fire),
+        (pause_rect(), "II", down.pause),
+    ];
+    for (rect, label, held) in buttons.iter() {
+        let pos = viewport.game_to_window(vec2(rect.x, rect.y));
+        let size = vec2(rect.w, rect.h) * viewport.scale;
+        let alpha = if *held { 0.6f32 } else { 0.25f32 };
+        draw_rectangle(
+            pos.x,
+            pos.y,
+            size.x,
+            size.y,
+            Color::new(1f32, 1f32, 1f32, alpha),
+        );
+        draw_rectangle_lines(pos.x, pos.y, size.x, size.y, 2f32, WHITE);
+
+        let font_size = viewport.font_size(8f32);
+        let text_size = measure_text(label, Some(font), font_size, 1f32);
+        draw_text_ex(
+            label,
+            pos.x + (size.x - text_size.width) * 0.5f32,
+            pos.y + (size.y - text_size.height) * 0.5f32 + text_size.offset_y,
+            TextParams {
+                font,
+                font_size,
+                font_scale: 1f32,
+                color: WHITE,
+                font_scale_aspect: 1f32,
+            },
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{constants::*, enemy::EnemyColor, pickup::DropTable};
+
+// where designers tweak the balance, values missing from the file use the defaults in constants.rs
+pub const TUNING_FILE_PATH: &str = "resources/tuning.ron";
+
+// every balance value the simulation uses
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tuning {
+    pub score_normal: i32,
+    pub score_mini: i32,
+    pub score_boss: i32,
+    pub score_kill_all: i32,
+    pub score_survived_all: i32,
+
+    pub player_speed: f32,
+    pub player_shoot_time: f32,
+    pub player_bullet_speed: f32,
+    pub player_lives_start: i32,
+    pub player_lives_max: i32,
+    pub player_time_invisible: f32,
+
+    pub enemy_speed: f32,
+    // (min, max)
+    pub enemy_angle_speed_range: (f32, f32),
+    // (sideways, down)
+    pub enemy_speed_homing: (f32, f32),
+    pub enemy_bullet_speed: f32,
+    // radians per second, only with the guided demon shots variant
+    pub enemy_bullet_guided_turn_rate: f32,
+    pub enemy_shoot_time: f32,
+    pub enemy_shoot_burst_time: f32,
+    pub enemy_max_burst_count: i32,
+    // (min, max)
+    pub enemy_mini_homing_time_range: (f32, f32),
+    // 0 to 1
+    pub enemy_armored_chance: f32,
+    pub enemy_armored_health: i32,
+
+    pub enemy_spawn_starting_count: i32,
+    pub enemy_spawn_max_count: i32,
+    pub time_until_max_difficulty: f32,
+    pub enemy_spawn_time: f32,
+
+    // 0 never spawns a boss
+    pub boss_wave_interval: i32,
+    pub boss_health: i32,
+    pub boss_speed: f32,
+    pub boss_attack_time: f32,
+    pub boss_spread_count: i32,
+    pub boss_summon_count: i32,
+
+    pub pickup_fall_speed: f32,
+    pub pickup_duration: f32,
+    pub pickup_rapid_fire_factor: f32,
+    pub pickup_score_multiplier: i32,
+    pub drops_purple: DropTable,
+    pub drops_green: DropTable,
+    pub drops_red: DropTable,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning {
+            score_normal: SCORE_NORMAL,
+            score_mini: SCORE_MINI,
+            score_boss: SCORE_BOSS,
+            score_kill_all: SCORE_KILL_ALL,
+            score_survived_all: SCORE_SURVIVED_ALL,
+
+            player_speed: PLAYER_SPEED,
+            player_shoot_time: PLAYER_SHOOT_TIME,
+            player_bullet_speed: PLAYER_BULLET_SPEED,
+            player_lives_start: PLAYER_LIVES_START,
+            player_lives_max: PLAYER_LIVES_MAX,
+            player_time_invisible: PLAYER_TIME_INVISBLE,
+
+            enemy_speed: ENEMY_SPEED,
+            enemy_angle_speed_range: (ENEMY_ANGLE_SPEED_RANGE.x, ENEMY_ANGLE_SPEED_RANGE.y),
+            enemy_speed_homing: (ENEMY_SPEED_HOMING.x, ENEMY_SPEED_HOMING.y),
+            enemy_bullet_speed: ENEMY_BULLET_SPEED,
+            enemy_bullet_guided_turn_rate: ENEMY_BULLET_GUIDED_TURN_RATE,
+            enemy_shoot_time: ENEMY_SHOOT_TIME,
+            enemy_shoot_burst_time: ENEMY_SHOOT_BURST_TIME,
+            enemy_max_burst_count: ENEMY_MAX_BURST_COUNT,
+            enemy_mini_homing_time_range: (
+                ENEMY_MINI_HOMING_TIME_RANGE.x,
+                ENEMY_MINI_HOMING_TIME_RANGE.y,
+            ),
+            enemy_armored_chance: ENEMY_ARMORED_CHANCE,
+            enemy_armored_health: ENEMY_ARMORED_HEALTH,
+
+            enemy_spawn_starting_count: ENEMY_SPAWN_STARTING_COUNT,
+            enemy_spawn_max_count: ENEMY_SPAWN_MAX_COUNT,
+            time_until_max_difficulty: TIME_UNTIL_MAX_DIFFICULTY,
+            enemy_spawn_time: ENEMY_SPAWN_TIME,
+
+            boss_wave_interval: BOSS_WAVE_INTERVAL,
+            boss_health: BOSS_HEALTH,
+            boss_speed: BOSS_SPEED,
+            boss_attack_time: BOSS_ATTACK_TIME,
+            boss_spread_count: BOSS_SPREAD_COUNT,
+            boss_summon_count: BOSS_SUMMON_COUNT,
+
+            pickup_fall_speed: PICKUP_FALL_SPEED,
+            pickup_duration: PICKUP_DURATION,
+            pickup_rapid_fire_factor: PICKUP_RAPID_FIRE_FACTOR,
+            pickup_score_multiplier: PICKUP_SCORE_MULTIPLIER,
+            drops_purple: DropTable::new(DROP_CHANCE_PURPLE, DROPS_PURPLE),
+            drops_green: DropTable::new(DROP_CHANCE_GREEN, DROPS_GREEN),
+            drops_red: DropTable::new(DROP_CHANCE_RED, DROPS_RED),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Load(String),
+    Parse(ron::Error),
+    // every value that didn't pass validation
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TuningError::Load(reason) => write!(f, "could not load tuning file: {}", reason),
+            TuningError::Parse(err) => write!(f, "tuning file is not valid RON: {}", err),
+            TuningError::Invalid(problems) => {
+                write!(f, "tuning file has invalid values:")?;
+                for problem in problems.iter() {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Tuning {
+    // what the demons of the color drop when killed
+    pub fn drop_table(&self, enemy_color: EnemyColor) -> &DropTable {
+        match enemy_color {
+            EnemyColor::Purple => &self.drops_purple,
+            EnemyColor::Green => &self.drops_green,
+            EnemyColor::Red => &self.drops_red,
+        }
+    }
+
+    pub fn from_ron_str(source: &str) -> Result<Self, TuningError> {
+        let tuning: Tuning = ron::de::from_str(source).map_err(TuningError::Parse)?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    pub fn validate(&self) -> Result<(), TuningError> {
+        let mut problems = Vec::<String>::new();
+        let mut positive = |name: &str, value: f32| {
+            if value.is_nan() || value <= 0f32 {
+                problems.push(format!("{} must be greater than 0, got {}", name, value));
+            }
+        };
+        positive("player_speed", self.player_speed);
+        positive("player_shoot_time", self.player_shoot_time);
+        positive("player_bullet_speed", self.player_bullet_speed);
+        positive("player_time_invisible", self.player_time_invisible);
+        positive("enemy_speed", self.enemy_speed);
+        positive("enemy_bullet_speed", self.enemy_bullet_speed);
+        positive(
+            "enemy_bullet_guided_turn_rate",
+            self.enemy_bullet_guided_turn_rate,
+        );
+        positive("enemy_shoot_time", self.enemy_shoot_time);
+        positive("enemy_shoot_burst_time", self.enemy_shoot_burst_time);
+        positive("time_until_max_difficulty", self.time_until_max_difficulty);
+        positive("enemy_spawn_time", self.enemy_spawn_time);
+        positive("boss_speed", self.boss_speed);
+        positive("boss_attack_time", self.boss_attack_time);
+        positive("pickup_fall_speed", self.pickup_fall_speed);
+        positive("pickup_duration", self.pickup_duration);
+        positive("pickup_rapid_fire_factor", self.pickup_rapid_fire_factor);
+
+        let mut at_least = |name: &str, value: i32, min: i32| {
+            if value < min {
+                problems.push(format!("{} must be at least {}, got {}", name, min, value));
+            }
+        };
+        at_least("score_normal", self.score_normal, 0);
+        at_least("score_mini", self.score_mini, 0);
+        at_least("score_boss", self.score_boss, 0);
+        at_least("score_kill_all", self.score_kill_all, 0);
+        at_least("score_survived_all", self.score_survived_all, 0);
+        at_least("player_lives_start", self.player_lives_start, 1);
+        at_least(
+            "player_lives_max",
+            self.player_lives_max,
+            self.player_lives_start,
+        );
+        at_least("enemy_max_burst_count", self.enemy_max_burst_count, 1);
+        at_least("enemy_armored_health", self.enemy_armored_health, 1);
+        at_least(
+            "enemy_spawn_starting_count",
+            self.enemy_spawn_starting_count,
+            1,
+        );
+        at_least(
+            "enemy_spawn_max_count",
+            self.enemy_spawn_max_count,
+            self.enemy_spawn_starting_count,
+        );
+
+        at_least("boss_wave_interval", self.boss_wave_interval, 0);
+        at_least("boss_health", self.boss_health, 1);
+        at_least("boss_spread_count", self.boss_spread_count, 1);
+        at_least("boss_summon_count", self.boss_summon_count, 0);
+        at_least("pickup_score_multiplier", self.pickup_score_multiplier, 1);
+
+        let mut range = |name: &str, (min, max): (f32, f32)| {
+            if min.is_nan() || max.is_nan() || min < 0f32 || min > max {
+                problems.push(format!(
+                    "{} must be (min, max) with 0 <= min <= max, got ({}, {})",
+                    name, min, max
+                ));
+            }
+        };
+        range("enemy_angle_speed_range", self.enemy_angle_speed_range);
+        range(
+            "enemy_mini_homing_time_range",
+            self.enemy_mini_homing_time_range,
+        );
+        if self.enemy_armored_chance.is_nan()
+            || self.enemy_armored_chance < 0f32
+            || self.enemy_armored_chance > 1f32
+        {
+            problems.push(format!(
+                "enemy_armored_chance must be between 0 and 1, got {}",
+                self.enemy_armored_chance
+            ));
+        }
+        for (name, drop_table) in [
+            ("drops_purple", &self.drops_purple),
+            ("drops_green", &self.drops_green),
+            ("drops_red", &self.drops_red),
+        ]
+        .iter()
+        {
+            if drop_table.chance.is_nan() || drop_table.chance < 0f32 || drop_table.chance > 1f32 {
+                problems.push(format!(
+                    "{} chance must be between 0 and 1, got {}",
+                    name, drop_table.chance
+                ));
+            }
+            for (kind, weight) in drop_table.pickups.iter() {
+                if weight.is_nan() || *weight < 0f32 {
+                    problems.push(format!(
+                        "{} weight of {:?} can't be negative, got {}",
+                        name, kind, weight
+                    ));
+                }
+            }
+        }
+        let (homing_sideways, homing_down) = self.enemy_speed_homing;
+        if homing_sideways.is_nan()
+            || homing_sideways < 0f32
+            || homing_down.is_nan()
+            || homing_down <= 0f32
+        {
+            problems.push(format!(
+                "enemy_speed_homing must be (sideways >= 0, down > 0), got ({}, {})",
+                homing_sideways, homing_down
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(TuningError::Invalid(problems))
+        }
+    }
+}
+
+// loads the tuning file, any problem is reported and the compiled defaults are used instead
+pub async fn load_tuning(path: &str) -> Tuning {
+    let result = match macroquad::file::load_string(path).await {
+        Ok(source) => Tuning::from_ron_str(&source),
+        Err(err) => Err(TuningError::Load(format!("{:?}", err))),
+    };
+    match result {
+        Ok(tuning) => tuning,
+        Err(err) => {
+            eprintln!("{}: {}\nusing default tuning", path, err);
+            Tuning::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(source: &str) -> Vec<String> {
+        match Tuning::from_ron_str(source) {
+            Err(TuningError::Invalid(problems)) => problems,
+            other => panic!("expected invalid values, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn default_round_trip() {
+        let source =
+            ron::ser::to_string_pretty(&Tuning::default(), ron::ser::PrettyConfig::default())
+                .unwrap();
+        assert_eq!(Tuning::from_ron_str(&source).unwrap(), Tuning::default());
+    }
+
+    #[test]
+    fn shipped_file_is_valid() {
+        Tuning::from_ron_str(include_str!("../resources/tuning.ron")).unwrap();
+    }
+
+    #[test]
+    fn missing_values_are_the_defaults() {
+        let tuning = Tuning::from_ron_str("(player_speed: 120.0)").unwrap();
+        assert_eq!(tuning.player_speed, 120f32);
+        assert_eq!(tuning.enemy_speed, Tuning::default().enemy_speed);
+    }
+
+    #[test]
+    fn out_of_range_value_is_reported() {
+        let problems = problems("(player_speed: -1.0)");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("player_speed"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn every_problem_is_reported_together() {
+        let problems = problems(
+            "(
+                player_speed: 0.0,
+                player_lives_start: 5,
+                player_lives_max: 3,
+                enemy_angle_speed_range: (2.0, 1.0),
+            )",
+        );
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        for name in [
+            "player_speed",
+            "player_lives_max",
+            "enemy_angle_speed_range",
+        ]
+        .iter()
+        {
+            assert!(
+                problems.iter().any(|problem| problem.starts_with(name)),
+                "{} in {:?}",
+                name,
+                problems
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_field_is_a_parse_error() {
+        assert!(matches!(
+            Tuning::from_ron_str("(player_sped: 1.0)"),
+            Err(TuningError::Parse(_))
+        ));
+    }
+}
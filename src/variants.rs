@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+
+const VARIANT_BIT_TRACER_SHOTS: u8 = 1;
+const VARIANT_BIT_GUIDED_DEMON_SHOTS: u8 = 2;
+const VARIANT_BIT_FAST_DEMON_SHOTS: u8 = 4;
+const VARIANT_BIT_NO_EXTRA_LIVES: u8 = 8;
+const VARIANT_BIT_ONE_HIT: u8 = 16;
+const VARIANT_BIT_CAMPAIGN: u8 = 32;
+
+// rule changes picked in the menu, like the game numbers and difficulty switches on the Atari.
+// They change how the world plays, so replays store them next to the seed, and every
+// combination gets its own high score table
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameVariants {
+    // None starts with the tuning's player_lives_start
+    pub starting_lives_optional: Option<i32>,
+    // the player's missiles follow the cannon sideways while in flight
+    pub tracer_shots: bool,
+    // the demons' missiles steer toward the closest player
+    pub guided_demon_shots: bool,
+    // the demons' missiles fly VARIANT_FAST_DEMON_SHOTS_FACTOR times faster
+    pub fast_demon_shots: bool,
+    // clearing a wave doesn't give lives and no extra life power-ups drop
+    pub no_extra_lives: bool,
+    // getting hit takes every life at once
+    pub one_hit: bool,
+    // the game ends after CAMPAIGN_WAVE_COUNT waves instead of going on forever
+    pub campaign: bool,
+}
+
+impl GameVariants {
+    pub fn to_bits(&self) -> u8 {
+        let mut bits = 0u8;
+        for (on, bit) in [
+            (self.tracer_shots, VARIANT_BIT_TRACER_SHOTS),
+            (self.guided_demon_shots, VARIANT_BIT_GUIDED_DEMON_SHOTS),
+            (self.fast_demon_shots, VARIANT_BIT_FAST_DEMON_SHOTS),
+            (self.no_extra_lives, VARIANT_BIT_NO_EXTRA_LIVES),
+            (self.one_hit, VARIANT_BIT_ONE_HIT),
+            (self.campaign, VARIANT_BIT_CAMPAIGN),
+        ]
+        .iter()
+        {
+            if *on {
+                bits |= bit;
+            }
+        }
+        bits
+    }
+
+    // starting lives are stored apart from the bits, 0 for the tuning's
+    pub fn from_bits(bits: u8, starting_lives: u8) -> Self {
+        GameVariants {
+            starting_lives_optional: if starting_lives == 0 {
+                None
+            } else {
+                Some(starting_lives as i32)
+            },
+            tracer_shots: bits & VARIANT_BIT_TRACER_SHOTS != 0,
+            guided_demon_shots: bits & VARIANT_BIT_GUIDED_DEMON_SHOTS != 0,
+            fast_demon_shots: bits & VARIANT_BIT_FAST_DEMON_SHOTS != 0,
+            no_extra_lives: bits & VARIANT_BIT_NO_EXTRA_LIVES != 0,
+            one_hit: bits & VARIANT_BIT_ONE_HIT != 0,
+            campaign: bits & VARIANT_BIT_CAMPAIGN != 0,
+        }
+    }
+
+    pub fn starting_lives_byte(&self) -> u8 {
+        self.starting_lives_optional.unwrap_or(0) as u8
+    }
+
+    // cycles the tuning's default, then 1 to the tuning's lives_max lives
+    pub fn next_starting_lives(&mut self, forward: bool, lives_max: i32) {
+        let lives = self.starting_lives_optional.unwrap_or(0).min(lives_max);
+        let lives = if forward {
+            (lives + 1) % (lives_max + 1)
+        } else {
+            (lives + lives_max) % (lives_max + 1)
+        };
+        self.starting_lives_optional = if lives == 0 { None } else { Some(lives) };
+    }
+
+    // short description for the HUD and the high score table, NORMAL without any variants
+    pub fn label(&self) -> String {
+        let mut parts = Vec::<String>::new();
+        if let Some(lives) = self.starting_lives_optional {
+            parts.push(format!("{}UP", lives));
+        }
+        for (on, name) in [
+            (self.tracer_shots, "TRACER"),
+            (self.guided_demon_shots, "GUIDED"),
+            (self.fast_demon_shots, "FAST"),
+            (self.no_extra_lives, "NO XL"),
+            (self.one_hit, "1HIT"),
+        ]
+        .iter()
+        {
+            if *on {
+                parts.push((*name).to_owned());
+            }
+        }
+        if self.campaign {
+            parts.push(format!("{}W", CAMPAIGN_WAVE_COUNT));
+        }
+        if parts.is_empty() {
+            "NORMAL".to_owned()
+        } else {
+            parts.join(" ")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_round_trip() {
+        let variants = GameVariants {
+            starting_lives_optional: Some(2),
+            tracer_shots: true,
+            fast_demon_shots: true,
+            campaign: true,
+            ..GameVariants::default()
+        };
+        let loaded = GameVariants::from_bits(variants.to_bits(), variants.starting_lives_byte());
+        assert_eq!(loaded, variants);
+    }
+
+    #[test]
+    fn starting_lives_cycle_up_to_the_max() {
+        let mut variants = GameVariants::default();
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            variants.next_starting_lives(true, 3);
+            seen.push(variants.starting_lives_optional);
+        }
+        assert_eq!(seen, vec![Some(1), Some(2), Some(3), None]);
+        variants.next_starting_lives(false, 3);
+        assert_eq!(variants.starting_lives_optional, Some(3));
+    }
+
+    #[test]
+    fn starting_lives_above_a_lowered_max_wrap_around() {
+        let mut variants = GameVariants {
+            starting_lives_optional: Some(7),
+            ..GameVariants::default()
+        };
+        variants.next_starting_lives(true, 3);
+        assert_eq!(variants.starting_lives_optional, None);
+    }
+}
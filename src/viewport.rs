@@ -0,0 +1,183 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    atlas::{draw_region_ex, AtlasRegion},
+    constants::*,
+};
+
+// how the game's render target is fit into the window, picked in the options
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ViewportMode {
+    // as big as it fits keeping the aspect ratio, pixels can end up uneven
+    #[default]
+    AspectFit,
+    // the biggest whole multiple that fits, every game pixel is the same size on screen
+    Integer,
+    // fills the whole window, squashed or stretched
+    Stretch,
+}
+
+pub const ALL_VIEWPORT_MODES: &[ViewportMode] = &[
+    ViewportMode::AspectFit,
+    ViewportMode::Integer,
+    ViewportMode::Stretch,
+];
+
+impl ViewportMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViewportMode::AspectFit => "FIT",
+            ViewportMode::Integer => "PIXEL PERFECT",
+            ViewportMode::Stretch => "STRETCH",
+        }
+    }
+
+    // the one after this in ALL_VIEWPORT_MODES, back to the first after the last
+    pub fn next(&self) -> Self {
+        let index = ALL_VIEWPORT_MODES
+            .iter()
+            .position(|mode| mode == self)
+            .unwrap_or(0);
+        ALL_VIEWPORT_MODES[(index + 1) % ALL_VIEWPORT_MODES.len()]
+    }
+}
+
+// where the game's render target sits in the window, with bars (or the border art) filling the rest.
+// Used to draw it, to place the HUD text and to turn window positions into game positions
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    // window pixels per game pixel, only differs between x and y when stretched
+    pub scale: Vec2,
+    // size of the bars, left and top
+    pub padding: Vec2,
+}
+
+impl Viewport {
+    // for the window's current size
+    pub fn current(mode: ViewportMode) -> Self {
+        Self::for_window(mode, vec2(screen_width(), screen_height()))
+    }
+
+    fn for_window(mode: ViewportMode, window_size: Vec2) -> Self {
+        let game_diff_w = window_size.x / GAME_SIZE_X as f32;
+        let game_diff_h = window_size.y / GAME_SIZE_Y as f32;
+        let fit = game_diff_w.min(game_diff_h);
+        let scale = match mode {
+            ViewportMode::AspectFit => vec2(fit, fit),
+            // a window smaller than the game falls back to fitting, it'd be cut off otherwise
+            ViewportMode::Integer if fit >= 1f32 => vec2(fit.floor(), fit.floor()),
+            ViewportMode::Integer => vec2(fit, fit),
+            ViewportMode::Stretch => vec2(game_diff_w, game_diff_h),
+        };
+
+        let scaled_game_size = vec2(GAME_SIZE_X as f32, GAME_SIZE_Y as f32) * scale;
+        Viewport {
+            scale,
+            // whole pixels, so integer scaling stays sharp
+            padding: ((window_size - scaled_game_size) * 0.5f32).floor(),
+        }
+    }
+
+    // the game's size in window pixels
+    pub fn size(&self) -> Vec2 {
+        vec2(GAME_SIZE_X as f32, GAME_SIZE_Y as f32) * self.scale
+    }
+
+    // the smaller of the two scales, so text stays readable and unsquashed when stretched
+    pub fn text_scale(&self) -> f32 {
+        self.scale.x.min(self.scale.y)
+    }
+
+    // a font size given in game pixels
+    pub fn font_size(&self, game_font_size: f32) -> u16 {
+        (self.text_scale() * game_font_size) as u16
+    }
+
+    pub fn window_to_game(&self, pos: Vec2) -> Vec2 {
+        (pos - self.padding) / self.scale
+    }
+
+    pub fn game_to_window(&self, pos: Vec2) -> Vec2 {
+        pos * self.scale + self.padding
+    }
+
+    // tiles the border art over the whole window, the game is drawn over it afterwards.
+    // The tiles are scaled like the game so their pixels match
+    pub fn draw_border(&self, texture: AtlasRegion) {
+        let tile_size = vec2(texture.width(), texture.height()) * self.text_scale().max(1f32);
+        let columns = (screen_width() / tile_size.x).ceil() as i32;
+        let rows = (screen_height() / tile_size.y).ceil() as i32;
+        for row in 0..rows {
+            for column in 0..columns {
+                draw_region_ex(
+                    texture,
+                    column as f32 * tile_size.x,
+                    row as f32 * tile_size.y,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(tile_size),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+}
+
+// only works where the platform lets a running window switch, on Linux and macOS it takes a restart
+pub fn apply_fullscreen(fullscreen: bool) {
+    unsafe {
+        get_internal_gl().quad_context.set_fullscreen(fullscreen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_size() -> Vec2 {
+        vec2(GAME_SIZE_X as f32, GAME_SIZE_Y as f32)
+    }
+
+    #[test]
+    fn modes_cycle_back_to_the_first() {
+        let mut mode = ViewportMode::default();
+        for _ in 0..ALL_VIEWPORT_MODES.len() {
+            mode = mode.next();
+        }
+        assert_eq!(mode, ViewportMode::default());
+    }
+
+    #[test]
+    fn integer_scaling_uses_whole_multiples() {
+        let window_size = game_size() * 2.5f32;
+        let viewport = Viewport::for_window(ViewportMode::Integer, window_size);
+        assert_eq!(viewport.scale, vec2(2f32, 2f32));
+        assert_eq!(viewport.padding, (game_size() * 0.25f32).floor());
+        let fit = Viewport::for_window(ViewportMode::AspectFit, window_size);
+        assert_eq!(fit.scale, vec2(2.5f32, 2.5f32));
+    }
+
+    #[test]
+    fn integer_scaling_fits_a_window_smaller_than_the_game() {
+        let viewport = Viewport::for_window(ViewportMode::Integer, game_size() * 0.5f32);
+        assert_eq!(viewport.scale, vec2(0.5f32, 0.5f32));
+    }
+
+    #[test]
+    fn stretch_fills_the_window() {
+        let window_size = game_size() * vec2(3f32, 2f32);
+        let viewport = Viewport::for_window(ViewportMode::Stretch, window_size);
+        assert_eq!(viewport.size(), window_size);
+        assert_eq!(viewport.padding, Vec2::ZERO);
+    }
+
+    #[test]
+    fn window_and_game_positions_convert_back_and_forth() {
+        let viewport =
+            Viewport::for_window(ViewportMode::AspectFit, game_size() * vec2(4f32, 3f32));
+        let pos = vec2(12f32, 34f32);
+        assert_eq!(viewport.window_to_game(viewport.game_to_window(pos)), pos);
+    }
+}
@@ -1,11 +1,14 @@
 use macroquad::prelude::*;
-use quad_snd::mixer::{SoundMixer, Volume};
 
 use crate::{
     constants::*,
     enemy::{Enemy, EnemyColor, EnemyDeathMethod, EnemyType},
-    resources::{Resources, SoundIdentifier},
+    resources::SoundIdentifier,
+    rng::GameRng,
+    tuning::Tuning,
     variant_eq,
+    wave_file::{WaveBonus, WaveDefinition, WaveSpawn},
+    world::{SoundQueue, SpriteMetrics},
 };
 
 pub struct WaveManagerStateSpawning {
@@ -24,7 +27,8 @@ pub enum WaveManagerCommand {
 
 // used to get information from gamestate
 pub enum WaveManagerMessage {
-    LevelCleared,
+    // with the bonus of the wave that was cleared
+    LevelCleared(WaveBonus),
 }
 
 // the reason the last enemy died
@@ -37,37 +41,84 @@ pub enum LastEnemyDeathReason {
 pub struct WaveManager {
     pub state: WaveManagerState,
     pub last_enemy_death_reason: LastEnemyDeathReason,
+    // the wave being spawned or fought, starts at 1
+    pub wave: i32,
+    // authored waves played in order, after the last one waves are generated
+    pub waves: Vec<WaveDefinition>,
+    // hot reloaded waves, they take over once the wave being played is cleared
+    pending_waves_optional: Option<Vec<WaveDefinition>>,
     internal_timer: f32,
 }
 
 impl WaveManager {
-    pub fn new() -> Self {
-        let enemies_left = ENEMY_SPAWN_STARTING_COUNT;
+    pub fn new(tuning: &Tuning, waves: Vec<WaveDefinition>) -> Self {
+        let enemies_left =
+            Self::get_enemy_spawn_count(Self::wave_definition(&waves, 1), 1, &0f32, tuning);
         WaveManager {
             state: WaveManagerState::Spawning(WaveManagerStateSpawning {
                 spawn_timer: 0f32,
                 enemies_left,
             }),
             last_enemy_death_reason: LastEnemyDeathReason::Environment,
+            wave: 1,
+            waves,
+            pending_waves_optional: None,
             internal_timer: 0f32,
         }
     }
 
-    pub fn reset(&mut self) {
-        let enemies_left = ENEMY_SPAWN_STARTING_COUNT;
+    pub fn reset(&mut self, tuning: &Tuning) {
+        if let Some(waves) = self.pending_waves_optional.take() {
+            self.waves = waves;
+        }
+        let enemies_left =
+            Self::get_enemy_spawn_count(Self::wave_definition(&self.waves, 1), 1, &0f32, tuning);
         self.state = WaveManagerState::Spawning(WaveManagerStateSpawning {
             spawn_timer: 0f32,
             enemies_left,
         });
         self.last_enemy_death_reason = LastEnemyDeathReason::Environment;
+        self.wave = 1;
         self.internal_timer = 0f32;
     }
 
-    fn get_enemy_spawn_count(time: &f32) -> i32 {
-        let fraction = time / TIME_UNTIL_MAX_DIFFICULTY;
+    // the wave being played keeps its definition, the new waves are used from the next one
+    pub fn reload_waves(&mut self, waves: Vec<WaveDefinition>) {
+        self.pending_waves_optional = Some(waves);
+    }
+
+    // None when the wave isn't authored and gets generated
+    fn wave_definition(waves: &[WaveDefinition], wave: i32) -> Option<&WaveDefinition> {
+        waves.get((wave - 1).max(0) as usize)
+    }
+
+    // every boss_wave_interval waves the generated wave is a boss, authored waves are played as written
+    pub fn is_boss_wave(
+        wave_definition_optional: Option<&WaveDefinition>,
+        wave: i32,
+        tuning: &Tuning,
+    ) -> bool {
+        wave_definition_optional.is_none()
+            && tuning.boss_wave_interval > 0
+            && wave % tuning.boss_wave_interval == 0
+    }
+
+    fn get_enemy_spawn_count(
+        wave_definition_optional: Option<&WaveDefinition>,
+        wave: i32,
+        time: &f32,
+        tuning: &Tuning,
+    ) -> i32 {
+        if let Some(wave_definition) = wave_definition_optional {
+            return wave_definition.spawns.len() as i32;
+        }
+        if Self::is_boss_wave(wave_definition_optional, wave, tuning) {
+            return 1;
+        }
+        let fraction = time / tuning.time_until_max_difficulty;
         let spawn_countf32 = lininterp::lerp(
-            &(ENEMY_SPAWN_STARTING_COUNT as f32),
-            &(ENEMY_SPAWN_MAX_COUNT as f32),
+            &(tuning.enemy_spawn_starting_count as f32),
+            &(tuning.enemy_spawn_max_count as f32),
             &fraction,
         );
         spawn_countf32 as i32
@@ -77,19 +128,31 @@ impl WaveManager {
         &mut self,
         dt: f32,
         enemies: &mut Vec<Enemy>,
-        resources: &Resources,
-        sound_mixer: &mut SoundMixer,
+        sprite_metrics: &SpriteMetrics,
+        sounds: &mut SoundQueue,
+        rng: &mut GameRng,
+        tuning: &Tuning,
     ) -> Option<WaveManagerMessage> {
         self.internal_timer += dt;
         let state_command_optional = match &mut self.state {
             WaveManagerState::Spawning(game_state_spawning) => Self::update_state_spawning(
                 game_state_spawning,
+                Self::wave_definition(&self.waves, self.wave),
+                self.wave,
                 dt,
                 enemies,
-                resources,
-                sound_mixer,
+                sprite_metrics,
+                sounds,
+                rng,
+                tuning,
+            ),
+            WaveManagerState::Battle => Self::update_state_battle(
+                enemies,
+                Self::wave_definition(&self.waves, self.wave + 1),
+                self.wave + 1,
+                &self.internal_timer,
+                tuning,
             ),
-            WaveManagerState::Battle => Self::update_state_battle(enemies, &self.internal_timer),
         };
 
         if let Some(state_command) = state_command_optional {
@@ -105,7 +168,23 @@ impl WaveManager {
                         }),
                     );
                     if cleared_screen {
-                        return Some(WaveManagerMessage::LevelCleared);
+                        let bonus = Self::wave_definition(&self.waves, self.wave)
+                            .map(|wave_definition| wave_definition.bonus.clone())
+                            .unwrap_or_default();
+                        self.wave += 1;
+                        if let Some(waves) = self.pending_waves_optional.take() {
+                            self.waves = waves;
+                            // the next wave was counted from the old waves
+                            if let WaveManagerState::Spawning(spawning_state) = &mut self.state {
+                                spawning_state.enemies_left = Self::get_enemy_spawn_count(
+                                    Self::wave_definition(&self.waves, self.wave),
+                                    self.wave,
+                                    &self.internal_timer,
+                                    tuning,
+                                );
+                            }
+                        }
+                        return Some(WaveManagerMessage::LevelCleared(bonus));
                     }
                 }
             }
@@ -114,11 +193,19 @@ impl WaveManager {
     }
 
     fn update_state_battle(
-        enemies: &mut Vec<Enemy>,
+        enemies: &mut [Enemy],
+        next_wave_definition_optional: Option<&WaveDefinition>,
+        next_wave: i32,
         internal_time: &f32,
+        tuning: &Tuning,
     ) -> Option<WaveManagerCommand> {
         if enemies.is_empty() {
-            let enemies_left = Self::get_enemy_spawn_count(internal_time);
+            let enemies_left = Self::get_enemy_spawn_count(
+                next_wave_definition_optional,
+                next_wave,
+                internal_time,
+                tuning,
+            );
             return Some(WaveManagerCommand::ChangeState(WaveManagerState::Spawning(
                 WaveManagerStateSpawning {
                     enemies_left,
@@ -129,24 +216,57 @@ impl WaveManager {
         None
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn update_state_spawning(
         game_state_spawning: &mut WaveManagerStateSpawning,
+        wave_definition_optional: Option<&WaveDefinition>,
+        wave: i32,
         dt: f32,
         enemies: &mut Vec<Enemy>,
-        resources: &Resources,
-        sound_mixer: &mut SoundMixer,
+        sprite_metrics: &SpriteMetrics,
+        sounds: &mut SoundQueue,
+        rng: &mut GameRng,
+        tuning: &Tuning,
     ) -> Option<WaveManagerCommand> {
+        let spawn_time = wave_definition_optional
+            .and_then(|wave_definition| wave_definition.spawn_interval)
+            .unwrap_or(tuning.enemy_spawn_time);
         game_state_spawning.spawn_timer += dt;
-        if game_state_spawning.spawn_timer > ENEMY_SPAWN_TIME {
+        if game_state_spawning.spawn_timer > spawn_time {
+            // authored spawns are counted down from the front of the list
+            let wave_spawn_optional = wave_definition_optional.and_then(|wave_definition| {
+                let index = wave_definition.spawns.len() as i32 - game_state_spawning.enemies_left;
+                wave_definition.spawns.get(index.max(0) as usize)
+            });
             game_state_spawning.enemies_left -= 1;
-            game_state_spawning.spawn_timer -= ENEMY_SPAWN_TIME;
-            spawn_enemy(
-                enemies,
-                resources,
-                SpawnBlueprint::Normal,
-                EnemyColor::random(),
-            );
-            resources.play_sound(SoundIdentifier::Spawn, sound_mixer, Volume(0.4f32));
+            game_state_spawning.spawn_timer -= spawn_time;
+            match wave_spawn_optional {
+                Some(wave_spawn) => {
+                    spawn_wave_enemy(enemies, sprite_metrics, wave_spawn, rng, tuning)
+                }
+                None if Self::is_boss_wave(wave_definition_optional, wave, tuning) => {
+                    spawn_enemy(
+                        enemies,
+                        sprite_metrics,
+                        SpawnBlueprint::Boss,
+                        EnemyColor::Red,
+                        rng,
+                        tuning,
+                    );
+                }
+                None => {
+                    let enemy_color = EnemyColor::random(rng);
+                    spawn_enemy(
+                        enemies,
+                        sprite_metrics,
+                        SpawnBlueprint::Normal,
+                        enemy_color,
+                        rng,
+                        tuning,
+                    );
+                }
+            }
+            sounds.play(SoundIdentifier::Spawn, 0.4f32);
         }
         if game_state_spawning.enemies_left <= 0 {
             return Some(WaveManagerCommand::ChangeState(WaveManagerState::Battle));
@@ -158,47 +278,270 @@ impl WaveManager {
 pub enum SpawnBlueprint {
     Normal,
     Mini(Vec2),
+    Boss,
+}
+
+// bosses take a beating, everything else dies in one hit unless it's armored
+fn starting_health(enemy_type: EnemyType, tuning: &Tuning) -> i32 {
+    match enemy_type {
+        EnemyType::Boss => tuning.boss_health,
+        EnemyType::Normal | EnemyType::Mini => 1,
+    }
 }
 
 // construct an enemy with randomized features based on a blueprint
 pub fn spawn_enemy(
     enemies: &mut Vec<Enemy>,
-    resources: &Resources,
+    sprite_metrics: &SpriteMetrics,
     spawn_blueprint: SpawnBlueprint,
     enemy_color: EnemyColor,
+    rng: &mut GameRng,
+    tuning: &Tuning,
 ) {
-    let health = 1;
     let enemy = match spawn_blueprint {
         SpawnBlueprint::Normal => {
-            let spawn_offset = vec2(
-                rand::gen_range(-100f32, 100f32),
-                rand::gen_range(-60f32, 10f32),
-            );
-            let spawn_pos = vec2(GAME_CENTER_X, GAME_CENTER_Y) + spawn_offset;
-            let death_method = if rand::gen_range(0f32, 1f32) > 0.5f32 {
-                let spawn_amount = rand::gen_range(1, 2 + 1);
-                EnemyDeathMethod::SpawnChildren(spawn_amount)
+            let spawn_pos = random_spawn_pos(rng);
+            let death_method = random_death_method(rng);
+            let armored = rng.gen_range(0f32, 1f32) < tuning.enemy_armored_chance;
+            let health = if armored {
+                tuning.enemy_armored_health
             } else {
-                EnemyDeathMethod::None
+                starting_health(EnemyType::Normal, tuning)
             };
 
             Enemy::new(
                 spawn_pos,
-                resources.rand_enemy_normal(enemy_color),
+                sprite_metrics,
                 health,
                 death_method,
                 EnemyType::Normal,
                 enemy_color,
+                rng,
+                tuning,
             )
         }
         SpawnBlueprint::Mini(pos) => Enemy::new(
             pos,
-            resources.rand_enemy_mini(enemy_color),
-            health,
+            sprite_metrics,
+            starting_health(EnemyType::Mini, tuning),
             EnemyDeathMethod::None,
             EnemyType::Mini,
             enemy_color,
+            rng,
+            tuning,
+        ),
+        SpawnBlueprint::Boss => Enemy::new(
+            BOSS_POS,
+            sprite_metrics,
+            starting_health(EnemyType::Boss, tuning),
+            EnemyDeathMethod::None,
+            EnemyType::Boss,
+            enemy_color,
+            rng,
+            tuning,
         ),
     };
     enemies.push(enemy);
 }
+
+// an enemy from a wave file, anything the wave leaves out is randomized like procedural spawns
+pub fn spawn_wave_enemy(
+    enemies: &mut Vec<Enemy>,
+    sprite_metrics: &SpriteMetrics,
+    wave_spawn: &WaveSpawn,
+    rng: &mut GameRng,
+    tuning: &Tuning,
+) {
+    let health = wave_spawn
+        .health
+        .unwrap_or_else(|| starting_health(wave_spawn.enemy_type, tuning));
+    let enemy_color = wave_spawn.color.unwrap_or_else(|| EnemyColor::random(rng));
+    let spawn_pos = match (wave_spawn.pos, wave_spawn.enemy_type) {
+        (Some((x, y)), _) => vec2(x, y),
+        (None, EnemyType::Boss) => BOSS_POS,
+        (None, _) => random_spawn_pos(rng),
+    };
+    let death_method = wave_spawn
+        .death_method
+        .unwrap_or_else(|| random_death_method(rng));
+    enemies.push(Enemy::new(
+        spawn_pos,
+        sprite_metrics,
+        health,
+        death_method,
+        wave_spawn.enemy_type,
+        enemy_color,
+        rng,
+        tuning,
+    ));
+}
+
+fn random_spawn_pos(rng: &mut GameRng) -> Vec2 {
+    let spawn_offset = vec2(rng.gen_range(-100f32, 100f32), rng.gen_range(-60f32, 10f32));
+    vec2(GAME_CENTER_X, GAME_CENTER_Y) + spawn_offset
+}
+
+fn random_death_method(rng: &mut GameRng) -> EnemyDeathMethod {
+    if rng.gen_range(0f32, 1f32) > 0.5f32 {
+        let spawn_amount = rng.gen_range(1, 2 + 1);
+        EnemyDeathMethod::SpawnChildren(spawn_amount)
+    } else {
+        EnemyDeathMethod::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(color: Option<EnemyColor>, pos: Option<(f32, f32)>) -> WaveSpawn {
+        WaveSpawn {
+            enemy_type: EnemyType::Normal,
+            color,
+            pos,
+            death_method: None,
+            health: None,
+        }
+    }
+
+    fn wave(spawn_count: usize) -> WaveDefinition {
+        WaveDefinition {
+            spawn_interval: None,
+            bonus: WaveBonus::default(),
+            spawns: vec![spawn(None, None); spawn_count],
+        }
+    }
+
+    fn enemies_left(wave_manager: &WaveManager) -> i32 {
+        match &wave_manager.state {
+            WaveManagerState::Spawning(spawning_state) => spawning_state.enemies_left,
+            WaveManagerState::Battle => panic!("expected to be spawning"),
+        }
+    }
+
+    #[test]
+    fn waves_are_generated_after_the_authored_ones() {
+        let tuning = Tuning::default();
+        let waves = vec![wave(3)];
+        assert_eq!(enemies_left(&WaveManager::new(&tuning, waves.clone())), 3);
+        let generated_count = WaveManager::get_enemy_spawn_count(
+            WaveManager::wave_definition(&waves, 2),
+            2,
+            &0f32,
+            &tuning,
+        );
+        assert_eq!(generated_count, tuning.enemy_spawn_starting_count);
+    }
+
+    #[test]
+    fn every_interval_a_generated_wave_is_a_boss() {
+        let tuning = Tuning {
+            boss_wave_interval: 5,
+            ..Tuning::default()
+        };
+        let boss_waves: Vec<i32> = (1..=15)
+            .filter(|wave| WaveManager::is_boss_wave(None, *wave, &tuning))
+            .collect();
+        assert_eq!(boss_waves, vec![5, 10, 15]);
+        assert_eq!(
+            WaveManager::get_enemy_spawn_count(None, 5, &0f32, &tuning),
+            1
+        );
+    }
+
+    #[test]
+    fn authored_waves_are_never_boss_waves() {
+        let tuning = Tuning {
+            boss_wave_interval: 5,
+            ..Tuning::default()
+        };
+        assert!(!WaveManager::is_boss_wave(Some(&wave(3)), 5, &tuning));
+        let no_bosses = Tuning {
+            boss_wave_interval: 0,
+            ..Tuning::default()
+        };
+        assert!(!WaveManager::is_boss_wave(None, 5, &no_bosses));
+    }
+
+    #[test]
+    fn without_authored_waves_every_wave_is_generated() {
+        let tuning = Tuning::default();
+        let wave_manager = WaveManager::new(&tuning, Vec::new());
+        assert_eq!(
+            enemies_left(&wave_manager),
+            tuning.enemy_spawn_starting_count
+        );
+    }
+
+    #[test]
+    fn reloaded_waves_start_with_the_next_game() {
+        let tuning = Tuning::default();
+        let mut wave_manager = WaveManager::new(&tuning, vec![wave(3)]);
+        wave_manager.reload_waves(vec![wave(5)]);
+        assert_eq!(wave_manager.waves, vec![wave(3)]);
+        wave_manager.reset(&tuning);
+        assert_eq!(wave_manager.waves, vec![wave(5)]);
+        assert_eq!(enemies_left(&wave_manager), 5);
+    }
+
+    #[test]
+    fn omitted_spawn_fields_are_picked_randomly() {
+        let tuning = Tuning::default();
+        let mut enemies = Vec::new();
+        for seed in 0..32 {
+            spawn_wave_enemy(
+                &mut enemies,
+                &SpriteMetrics::default(),
+                &spawn(None, None),
+                &mut GameRng::new(seed),
+                &tuning,
+            );
+        }
+        let shared = enemies.iter().map(|enemy| &enemy.state_shared);
+        let colors: Vec<EnemyColor> = shared.clone().map(|shared| shared.enemy_color).collect();
+        assert!(colors.iter().any(|color| *color != colors[0]));
+        let positions: Vec<Vec2> = shared.clone().map(|shared| shared.pos).collect();
+        assert!(positions.iter().any(|pos| *pos != positions[0]));
+        assert!(shared
+            .clone()
+            .any(|shared| shared.death_method == EnemyDeathMethod::None));
+        assert!(shared
+            .clone()
+            .any(|shared| matches!(shared.death_method, EnemyDeathMethod::SpawnChildren(_))));
+    }
+
+    #[test]
+    fn given_spawn_fields_are_kept() {
+        let mut enemies = Vec::new();
+        spawn_wave_enemy(
+            &mut enemies,
+            &SpriteMetrics::default(),
+            &spawn(Some(EnemyColor::Red), Some((50f32, 40f32))),
+            &mut GameRng::new(1),
+            &Tuning::default(),
+        );
+        let shared = &enemies[0].state_shared;
+        assert_eq!(shared.enemy_color, EnemyColor::Red);
+        assert_eq!(shared.pos, vec2(50f32, 40f32));
+    }
+    #[test]
+    fn wave_spawns_are_armored_only_when_given_more_health() {
+        let mut enemies = Vec::new();
+        for health in [None, Some(3)].iter() {
+            spawn_wave_enemy(
+                &mut enemies,
+                &SpriteMetrics::default(),
+                &WaveSpawn {
+                    health: *health,
+                    ..spawn(None, None)
+                },
+                &mut GameRng::new(1),
+                &Tuning::default(),
+            );
+        }
+        assert_eq!(enemies[0].state_shared.health, 1);
+        assert!(!enemies[0].is_armored());
+        assert_eq!(enemies[1].state_shared.health, 3);
+        assert!(enemies[1].is_armored());
+    }
+}
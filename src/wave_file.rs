@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{
+    constants::*,
+    enemy::{EnemyColor, EnemyDeathMethod, EnemyType},
+};
+
+// the authored waves, played in order before the procedural waves take over
+pub const WAVES_FILE_PATH: &str = "resources/waves.ron";
+
+// one enemy of an authored wave
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WaveSpawn {
+    pub enemy_type: EnemyType,
+    // None picks a random color
+    #[serde(default)]
+    pub color: Option<EnemyColor>,
+    // None picks a random position around the center, like procedural waves do
+    #[serde(default)]
+    pub pos: Option<(f32, f32)>,
+    // None picks randomly between exploding into minis or not
+    #[serde(default)]
+    pub death_method: Option<EnemyDeathMethod>,
+    // hits it takes, more than 1 is an armored demon. None is 1, or boss_health from the tuning for bosses
+    #[serde(default)]
+    pub health: Option<i32>,
+}
+
+// awarded on top of the normal wave clear score and life
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaveBonus {
+    pub score: i32,
+    pub lives: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WaveDefinition {
+    // seconds between spawns, None uses enemy_spawn_time from the tuning
+    #[serde(default)]
+    pub spawn_interval: Option<f32>,
+    #[serde(default)]
+    pub bonus: WaveBonus,
+    // spawned in order
+    pub spawns: Vec<WaveSpawn>,
+}
+
+#[derive(Debug)]
+pub enum WaveFileError {
+    Load(String),
+    Parse(ron::Error),
+    // every value that didn't pass validation
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for WaveFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WaveFileError::Load(reason) => write!(f, "could not load wave file: {}", reason),
+            WaveFileError::Parse(err) => write!(f, "wave file is not valid RON: {}", err),
+            WaveFileError::Invalid(problems) => {
+                write!(f, "wave file has invalid values:")?;
+                for problem in problems.iter() {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn waves_from_ron_str(source: &str) -> Result<Vec<WaveDefinition>, WaveFileError> {
+    let waves: Vec<WaveDefinition> = ron::de::from_str(source).map_err(WaveFileError::Parse)?;
+    validate_waves(&waves)?;
+    Ok(waves)
+}
+
+pub fn validate_waves(waves: &[WaveDefinition]) -> Result<(), WaveFileError> {
+    let mut problems = Vec::<String>::new();
+    for (i, wave) in waves.iter().enumerate() {
+        // waves are numbered from 1 in messages, like they are in game
+        let wave_number = i + 1;
+        if wave.spawns.is_empty() {
+            problems.push(format!("wave {} has no spawns", wave_number));
+        }
+        if let Some(spawn_interval) = wave.spawn_interval {
+            if spawn_interval.is_nan() || spawn_interval <= 0f32 {
+                problems.push(format!(
+                    "wave {} spawn_interval must be greater than 0, got {}",
+                    wave_number, spawn_interval
+                ));
+            }
+        }
+        if wave.bonus.score < 0 || wave.bonus.lives < 0 {
+            problems.push(format!(
+                "wave {} bonus can't be negative, got score {} lives {}",
+                wave_number, wave.bonus.score, wave.bonus.lives
+            ));
+        }
+        for (j, spawn) in wave.spawns.iter().enumerate() {
+            if let Some((x, y)) = spawn.pos {
+                let inside_x = x >= 0f32 && x <= GAME_SIZE_X as f32;
+                let inside_y = y >= 0f32 && y <= GAME_SIZE_Y as f32;
+                if !inside_x || !inside_y {
+                    problems.push(format!(
+                        "wave {} spawn {} pos must be inside the {}x{} screen, got ({}, {})",
+                        wave_number,
+                        j + 1,
+                        GAME_SIZE_X,
+                        GAME_SIZE_Y,
+                        x,
+                        y
+                    ));
+                }
+            }
+            if let Some(health) = spawn.health {
+                if health < 1 {
+                    problems.push(format!(
+                        "wave {} spawn {} health must be at least 1, got {}",
+                        wave_number,
+                        j + 1,
+                        health
+                    ));
+                }
+            }
+            if let Some(EnemyDeathMethod::SpawnChildren(count)) = spawn.death_method {
+                if count < 1 {
+                    problems.push(format!(
+                        "wave {} spawn {} must spawn at least 1 child, got {}",
+                        wave_number,
+                        j + 1,
+                        count
+                    ));
+                }
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(WaveFileError::Invalid(problems))
+    }
+}
+
+// loads the wave file, any problem is reported and every wave is generated procedurally instead
+pub async fn load_waves(path: &str) -> Vec<WaveDefinition> {
+    let result = match macroquad::file::load_string(path).await {
+        Ok(source) => waves_from_ron_str(&source),
+        Err(err) => Err(WaveFileError::Load(format!("{:?}", err))),
+    };
+    match result {
+        Ok(waves) => waves,
+        Err(err) => {
+            eprintln!("{}: {}\nusing procedural waves only", path, err);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(source: &str) -> Vec<String> {
+        match waves_from_ron_str(source) {
+            Err(WaveFileError::Invalid(problems)) => problems,
+            other => panic!("expected invalid waves, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn shipped_file_is_valid() {
+        assert!(!waves_from_ron_str(include_str!("../resources/waves.ron"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn omitted_fields_are_none() {
+        let waves = waves_from_ron_str("[(spawns: [(enemy_type: Normal)])]").unwrap();
+        assert_eq!(waves.len(), 1);
+        assert_eq!(waves[0].spawn_interval, None);
+        assert_eq!(waves[0].bonus, WaveBonus::default());
+        let spawn = &waves[0].spawns[0];
+        assert_eq!(spawn.color, None);
+        assert_eq!(spawn.pos, None);
+        assert_eq!(spawn.death_method, None);
+    }
+
+    #[test]
+    fn every_problem_is_reported_together() {
+        let problems = problems(
+            "[
+                (spawns: []),
+                (
+                    spawn_interval: Some(0.0),
+                    bonus: (score: -5),
+                    spawns: [
+                        (enemy_type: Normal, pos: Some((-1.0, 20.0))),
+                        (enemy_type: Mini, death_method: Some(SpawnChildren(0))),
+                    ],
+                ),
+            ]",
+        );
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+        assert!(problems[0].starts_with("wave 1 has no spawns"));
+        assert!(problems[1..]
+            .iter()
+            .all(|problem| problem.starts_with("wave 2")));
+        assert!(problems[3].contains("spawn 1"));
+        assert!(problems[4].contains("spawn 2"));
+    }
+
+    #[test]
+    fn unknown_field_is_a_parse_error() {
+        assert!(matches!(
+            waves_from_ron_str("[(spawns: [(enemy_type: Normal, colour: Some(Red))])]"),
+            Err(WaveFileError::Parse(_))
+        ));
+    }
+}
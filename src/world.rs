@@ -0,0 +1,711 @@
+use macroquad::prelude::*;
+
+use crate::{
+    bullet::{Bullet, BulletHurtType},
+    constants::*,
+    enemy::{Enemy, EnemyDeathMethod, EnemyMessage, EnemyStateHoming},
+    enemy::{EnemyColor, EnemyState, EnemyType},
+    particles::Particles,
+    pickup::{Pickup, PickupKind},
+    player::{Player, PlayerCommand, PlayerState},
+    resources::SoundIdentifier,
+    rng::GameRng,
+    tuning::Tuning,
+    variant_eq,
+    variants::GameVariants,
+    wave::{spawn_enemy, LastEnemyDeathReason, SpawnBlueprint, WaveManager, WaveManagerMessage},
+    wave_file::WaveDefinition,
+};
+
+// The world is the whole game simulation.
+// It never reads input devices, draws or plays audio, so it can run without a window.
+// Whoever owns the world feeds it a WorldInput every step, then draws it and plays the sounds it requested.
+
+// how many steps move_x has in each direction, replays store it as a whole number of steps
+pub const WORLD_INPUT_MOVE_STEPS: f32 = 127f32;
+
+// what one player is pressing during one simulation step
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct WorldInput {
+    // -1 is full speed left, 1 full speed right, anything between is a partly pushed stick
+    pub move_x: f32,
+    pub shoot: bool,
+}
+
+impl WorldInput {
+    // rounds move_x to WORLD_INPUT_MOVE_STEPS, so a replay plays back exactly what was recorded
+    pub fn new(move_x: f32, shoot: bool) -> Self {
+        let move_x = move_x.clamp(-1f32, 1f32);
+        WorldInput {
+            move_x: (move_x * WORLD_INPUT_MOVE_STEPS).round() / WORLD_INPUT_MOVE_STEPS,
+            shoot,
+        }
+    }
+}
+
+pub struct SoundRequest {
+    pub identifier: SoundIdentifier,
+    pub volume: f32,
+}
+
+// sounds requested by the simulation, played (or ignored) by whoever presents the world
+#[derive(Default)]
+pub struct SoundQueue {
+    requests: Vec<SoundRequest>,
+}
+
+impl SoundQueue {
+    pub fn play(&mut self, identifier: SoundIdentifier, volume: f32) {
+        self.requests.push(SoundRequest { identifier, volume });
+    }
+
+    pub fn drain(&mut self) -> std::vec::Drain<'_, SoundRequest> {
+        self.requests.drain(..)
+    }
+}
+
+// moments that should be felt, shown as screen shake and hit-stop by whoever presents the world
+#[derive(Clone, Copy, PartialEq)]
+pub enum Impact {
+    PlayerHit,
+    // an enemy shot dead
+    Kill,
+    // a boss finished its defeat sequence
+    BossDefeated,
+    WaveCleared,
+}
+
+#[derive(Default)]
+pub struct ImpactQueue {
+    impacts: Vec<Impact>,
+}
+
+impl ImpactQueue {
+    pub fn push(&mut self, impact: Impact) {
+        self.impacts.push(impact);
+    }
+
+    pub fn drain(&mut self) -> std::vec::Drain<'_, Impact> {
+        self.impacts.drain(..)
+    }
+}
+
+// used to get information from the world
+pub enum WorldMessage {
+    GameOver,
+    // the last wave of the campaign variant was cleared
+    CampaignCleared,
+}
+
+// sprite sizes the simulation needs for collision and movement bounds.
+// taken from the loaded textures when running with a window, Default matches the shipped pngs
+#[derive(Clone, PartialEq)]
+pub struct SpriteMetrics {
+    pub player: Vec2,
+    pub ground_height: f32,
+    pub demons_normal_purple: Vec<Vec2>,
+    pub demons_normal_green: Vec<Vec2>,
+    pub demons_normal_red: Vec<Vec2>,
+    pub demons_mini_purple: Vec<Vec2>,
+    pub demons_mini_green: Vec<Vec2>,
+    pub demons_mini_red: Vec<Vec2>,
+    // the whole boss, wings and body together
+    pub demons_boss: Vec<Vec2>,
+}
+
+impl SpriteMetrics {
+    // every enemy sprite variant of a type and color, an enemy refers to its sprite by index into this list
+    pub fn enemy_sizes(&self, enemy_type: EnemyType, enemy_color: EnemyColor) -> &Vec<Vec2> {
+        match enemy_type {
+            EnemyType::Normal => match enemy_color {
+                EnemyColor::Purple => &self.demons_normal_purple,
+                EnemyColor::Green => &self.demons_normal_green,
+                EnemyColor::Red => &self.demons_normal_red,
+            },
+            EnemyType::Mini => match enemy_color {
+                EnemyColor::Purple => &self.demons_mini_purple,
+                EnemyColor::Green => &self.demons_mini_green,
+                EnemyColor::Red => &self.demons_mini_red,
+            },
+            // bosses look the same in every color
+            EnemyType::Boss => &self.demons_boss,
+        }
+    }
+
+    // a boss is its body with a wing on each side, overlapping by a pixel.
+    // takes the size of a single frame of each
+    pub fn boss_size(wing_frame_size: Vec2, body_frame_size: Vec2) -> Vec2 {
+        vec2(
+            body_frame_size.x + (wing_frame_size.x - 1f32) * 2f32,
+            body_frame_size
+                .y
+                .max(wing_frame_size.y + BOSS_WING_OFFSET_Y),
+        )
+    }
+}
+
+impl Default for SpriteMetrics {
+    fn default() -> Self {
+        let demon = vec2(24f32, 7f32);
+        SpriteMetrics {
+            player: vec2(7f32, 5f32),
+            ground_height: 24f32,
+            demons_normal_purple: vec![demon, demon],
+            demons_normal_green: vec![demon, demon],
+            demons_normal_red: vec![vec2(40f32, 7f32)],
+            demons_mini_purple: vec![demon],
+            demons_mini_green: vec![demon],
+            demons_mini_red: vec![demon],
+            demons_boss: vec![Self::boss_size(vec2(14f32, 10f32), vec2(12f32, 14f32))],
+        }
+    }
+}
+
+pub struct World {
+    pub wave_manager: WaveManager,
+    pub bullets: Vec<Bullet>,
+    pub enemies: Vec<Enemy>,
+    // power-ups dropped by killed enemies
+    pub pickups: Vec<Pickup>,
+    // only for show, nothing in the game depends on them
+    pub particles: Particles,
+    // one in a normal game, two in co-op. Players stay in the list after losing their last life
+    pub players: Vec<Player>,
+    pub sprite_metrics: SpriteMetrics,
+    // the rule changes picked in the menu for this game
+    pub variants: GameVariants,
+    pub tuning: Tuning,
+    pub sounds: SoundQueue,
+    pub impacts: ImpactQueue,
+    // seconds simulated since reset, used instead of get_time() so the world runs without a window
+    pub time: f32,
+    // the seed the current game started with, same seed and same inputs gives the same game
+    pub seed: u64,
+    pub rng: GameRng,
+    // the id the next new enemy gets
+    next_enemy_id: u32,
+}
+
+impl World {
+    pub fn new(
+        sprite_metrics: SpriteMetrics,
+        tuning: Tuning,
+        waves: Vec<WaveDefinition>,
+        player_count: usize,
+        variants: GameVariants,
+        seed: u64,
+    ) -> Self {
+        let players = Self::new_players(&sprite_metrics, &tuning, &variants, player_count);
+        World {
+            wave_manager: WaveManager::new(&tuning, waves),
+            bullets: Vec::<Bullet>::new(),
+            enemies: Vec::<Enemy>::new(),
+            pickups: Vec::<Pickup>::new(),
+            particles: Particles::default(),
+            players,
+            sprite_metrics,
+            variants,
+            tuning,
+            sounds: SoundQueue::default(),
+            impacts: ImpactQueue::default(),
+            time: 0f32,
+            seed,
+            rng: GameRng::new(seed),
+            next_enemy_id: 0,
+        }
+    }
+
+    fn new_players(
+        sprite_metrics: &SpriteMetrics,
+        tuning: &Tuning,
+        variants: &GameVariants,
+        player_count: usize,
+    ) -> Vec<Player> {
+        (0..player_count)
+            .map(|index| {
+                Player::new(
+                    index,
+                    Player::spawn_pos(sprite_metrics, index, player_count),
+                    sprite_metrics.player,
+                    variants
+                        .starting_lives_optional
+                        .unwrap_or(tuning.player_lives_start),
+                )
+            })
+            .collect()
+    }
+
+    pub fn reset(&mut self, player_count: usize, variants: GameVariants, seed: u64) {
+        self.variants = variants;
+        self.wave_manager.reset(&self.tuning);
+        self.players = Self::new_players(
+            &self.sprite_metrics,
+            &self.tuning,
+            &self.variants,
+            player_count,
+        );
+        self.enemies.clear();
+        self.bullets.clear();
+        self.pickups.clear();
+        self.particles.clear();
+        self.sounds = SoundQueue::default();
+        self.impacts = ImpactQueue::default();
+        self.time = 0f32;
+        self.seed = seed;
+        self.rng = GameRng::new(seed);
+        self.next_enemy_id = 0;
+    }
+
+    // everyone's score added up, what a co-op game is ranked by
+    pub fn score(&self) -> i32 {
+        self.players.iter().map(|player| player.score).sum()
+    }
+
+    fn spawn_minis(&mut self, pos: Vec2, amount: i32, enemy_color: EnemyColor) {
+        self.sounds.play(SoundIdentifier::SpawnMini, 1.0f32);
+        let spawn_width = 20f32;
+        let step = 1. / (amount as f32);
+        for i in 0..amount {
+            let spawn_pos = pos + vec2(step * spawn_width * i as f32, 0f32);
+            spawn_enemy(
+                &mut self.enemies,
+                &self.sprite_metrics,
+                SpawnBlueprint::Mini(spawn_pos),
+                enemy_color,
+                &mut self.rng,
+                &self.tuning,
+            );
+        }
+    }
+
+    // inputs[i] is player i's input, a missing input means nothing is pressed
+    pub fn step(&mut self, inputs: &[WorldInput], dt: f32) -> Option<WorldMessage> {
+        self.time += dt;
+
+        let manager_message_optional = self.wave_manager.update(
+            dt,
+            &mut self.enemies,
+            &self.sprite_metrics,
+            &mut self.sounds,
+            &mut self.rng,
+            &self.tuning,
+        );
+        if let Some(manager_message) = manager_message_optional {
+            match manager_message {
+                WaveManagerMessage::LevelCleared(bonus) => {
+                    let score_add = match self.wave_manager.last_enemy_death_reason {
+                        LastEnemyDeathReason::Environment => self.tuning.score_survived_all,
+                        LastEnemyDeathReason::Player => self.tuning.score_kill_all,
+                    };
+                    // everyone still playing gets the reward, players that are out stay out
+                    for player in self.players.iter_mut().filter(|player| player.is_alive()) {
+                        if !self.variants.no_extra_lives {
+                            player.lives += 1 + bonus.lives;
+                            player.lives = player.lives.min(self.tuning.player_lives_max);
+                        }
+                        player.score += score_add + bonus.score;
+                    }
+                    self.sounds.play(SoundIdentifier::WaveCleared, 0.6f32);
+                    self.impacts.push(Impact::WaveCleared);
+                    if self.variants.campaign && self.wave_manager.wave > CAMPAIGN_WAVE_COUNT {
+                        return Some(WorldMessage::CampaignCleared);
+                    }
+                }
+            }
+        }
+
+        let player_positions: Vec<Vec2> = self
+            .players
+            .iter()
+            .filter(|player| player.is_alive())
+            .map(|player| player.pos)
+            .collect();
+        // bosses summon minis, they're spawned after the loop like the minis of dying enemies
+        let mut enemy_messages = Vec::<EnemyMessage>::new();
+        let first_new_bullet = self.bullets.len();
+        for enemy in self.enemies.iter_mut() {
+            let enemy_message_optional = enemy.update(
+                dt,
+                self.time,
+                &mut self.bullets,
+                &player_positions,
+                &mut self.wave_manager,
+                &mut self.sounds,
+                &mut self.rng,
+                &self.tuning,
+            );
+            enemy_messages.extend(enemy_message_optional);
+        }
+        for bullet in self.bullets[first_new_bullet..].iter_mut() {
+            if self.variants.fast_demon_shots {
+                bullet.speed_up(VARIANT_FAST_DEMON_SHOTS_FACTOR);
+            }
+            self.particles.muzzle_flash(bullet.pos(), bullet.dir());
+        }
+        for enemy_message in enemy_messages {
+            match enemy_message {
+                EnemyMessage::SummonMinis(pos, amount, enemy_color) => {
+                    self.spawn_minis(pos, amount, enemy_color)
+                }
+            }
+        }
+
+        for bullet in self.bullets.iter_mut() {
+            bullet.update(dt);
+            if bullet.hurt_type == BulletHurtType::Player && self.variants.guided_demon_shots {
+                if let Some(target) = Enemy::closest_position(&player_positions, bullet.pos()) {
+                    bullet.steer_towards(target, self.tuning.enemy_bullet_guided_turn_rate * dt);
+                }
+            }
+        }
+
+        self.particles.update(dt);
+
+        // bullets hurting players
+        for bullet in self
+            .bullets
+            .iter_mut()
+            .filter(|b| b.hurt_type == BulletHurtType::Player)
+        {
+            for player in self.players.iter_mut().filter(|player| player.is_alive()) {
+                if !bullet.overlaps(&player.collision_rect) || player.state != PlayerState::Normal {
+                    continue;
+                }
+                // a bullet only hurts one player
+                bullet.is_kill = true;
+                self.particles.sparks(bullet.pos());
+                if player.is_shielded() {
+                    self.sounds.play(SoundIdentifier::EnemyHit, 1.0f32);
+                    break;
+                }
+                player.lives -= if self.variants.one_hit {
+                    player.lives
+                } else {
+                    1
+                };
+                self.sounds.play(SoundIdentifier::PlayerOuch, 1.0f32);
+                self.impacts.push(Impact::PlayerHit);
+                // CHANGE PLAYER STATE
+                player.process_command_optional(Some(PlayerCommand::ChangeState(
+                    PlayerState::Invisible(self.tuning.player_time_invisible),
+                )));
+                break;
+            }
+        }
+
+        // homing enemies hurting players
+        for enemy in self
+            .enemies
+            .iter_mut()
+            // filter enemies containing homing state, variant_eq is used so we can disregard homing data
+            .filter(|e| variant_eq(&e.state, &EnemyState::Homing(EnemyStateHoming {})))
+        {
+            for player in self.players.iter_mut().filter(|player| player.is_alive()) {
+                if !enemy.overlaps(&player.collision_rect) {
+                    continue;
+                }
+                let player_invisible = variant_eq(&player.state, &PlayerState::Invisible(0f32));
+                // it crashes into the shield and dies without hurting anyone
+                if !player_invisible && player.is_shielded() {
+                    self.sounds.play(SoundIdentifier::EnemyOuch, 1.0f32);
+                    enemy.state_shared.health = 0;
+                    break;
+                }
+                if !player_invisible {
+                    player.lives -= if self.variants.one_hit {
+                        player.lives
+                    } else {
+                        1
+                    };
+                    self.sounds.play(SoundIdentifier::PlayerOuch, 1.0f32);
+                    self.impacts.push(Impact::PlayerHit);
+                    player.process_command_optional(Some(PlayerCommand::ChangeState(
+                        PlayerState::Invisible(self.tuning.player_time_invisible),
+                    )));
+                    enemy.state_shared.health = 0;
+                    break;
+                }
+            }
+        }
+
+        if self.players.iter().all(|player| !player.is_alive()) {
+            return Some(WorldMessage::GameOver);
+        }
+
+        // enemies killed this step, their death method is applied after the collision loops
+        // because it spawns new enemies while we are iterating over them
+        // the last field is who shot it
+        let mut death_methods =
+            Vec::<(Vec2, EnemyDeathMethod, EnemyType, EnemyColor, Option<usize>)>::with_capacity(4);
+
+        // enemies spawned since the last step
+        for enemy in self
+            .enemies
+            .iter_mut()
+            .filter(|enemy| enemy.id_optional.is_none())
+        {
+            enemy.id_optional = Some(self.next_enemy_id);
+            self.next_enemy_id += 1;
+        }
+
+        // bullets hurting enemies
+        for bullet in self
+            .bullets
+            .iter_mut()
+            .filter(|b| b.hurt_type == BulletHurtType::Enemy)
+        {
+            for enemy in self.enemies.iter_mut() {
+                if enemy.is_hittable() && enemy.overlaps(&bullet.collision_rect) && !bullet.is_kill
+                {
+                    let enemy_id = enemy.id_optional.unwrap_or_default();
+                    if bullet.hit_enemy_ids.contains(&enemy_id) {
+                        continue;
+                    }
+                    bullet.hit_enemy_ids.push(enemy_id);
+                    enemy.hit();
+                    self.particles.sparks(bullet.pos());
+                    self.wave_manager.last_enemy_death_reason = LastEnemyDeathReason::Player;
+                    // death
+                    if enemy.state_shared.health <= 0 {
+                        self.sounds.play(SoundIdentifier::EnemyOuch, 1.0f32);
+                        self.impacts.push(Impact::Kill);
+                        death_methods.push((
+                            enemy.state_shared.pos,
+                            enemy.state_shared.death_method,
+                            enemy.state_shared.enemy_type,
+                            enemy.state_shared.enemy_color,
+                            bullet.owner_optional,
+                        ));
+                    } else {
+                        self.sounds.play(SoundIdentifier::EnemyHit, 1.0f32);
+                    }
+                    // can only hurt one enemy unless it's piercing, flag for deletion
+                    bullet.is_kill = !bullet.piercing;
+                }
+            }
+        }
+
+        for (pos, death_method, enemy_type, enemy_color, owner_optional) in death_methods.iter() {
+            let score_add = match enemy_type {
+                EnemyType::Normal => self.tuning.score_normal,
+                EnemyType::Mini => self.tuning.score_mini,
+                EnemyType::Boss => self.tuning.score_boss,
+            };
+            // fields instead of a closure over self, so the tuning can be read while the player is borrowed
+            if let Some(owner) = owner_optional {
+                if let Some(player) = self.players.get_mut(*owner) {
+                    player.score += score_add * player.score_multiplier(&self.tuning);
+                }
+            }
+            if let Some(kind) = self.tuning.drop_table(*enemy_color).roll(&mut self.rng) {
+                if kind != PickupKind::ExtraLife || !self.variants.no_extra_lives {
+                    self.pickups.push(Pickup::new(kind, *pos));
+                }
+            }
+            match death_method {
+                EnemyDeathMethod::None => {}
+                EnemyDeathMethod::SpawnChildren(amount) => {
+                    self.spawn_minis(*pos, *amount, *enemy_color)
+                }
+            }
+        }
+
+        // falling pickups, caught by the first living player they touch
+        for pickup in self.pickups.iter_mut() {
+            pickup.update(dt, self.sprite_metrics.ground_height, &self.tuning);
+            for player in self.players.iter_mut().filter(|player| player.is_alive()) {
+                if !pickup.is_kill && pickup.collision_rect.overlaps(&player.collision_rect) {
+                    player.catch_pickup(pickup.kind, &self.tuning);
+                    self.sounds.play(SoundIdentifier::PowerUp, 1.0f32);
+                    pickup.is_kill = true;
+                }
+            }
+        }
+        self.pickups.retain(|pickup| !pickup.is_kill);
+
+        // remove bullets that hit something
+        self.bullets.retain(|e| !e.is_kill);
+        // homing demons that miss crash into the ground and raise dust, they die out of view.
+        // Every other dead demon blows up
+        let ground_y = GAME_SIZE_Y as f32 - self.sprite_metrics.ground_height;
+        for enemy in self.enemies.iter() {
+            if enemy.is_homing() && enemy.crossed_y(ground_y) {
+                self.particles
+                    .dust(vec2(enemy.state_shared.pos.x, ground_y));
+            }
+            let crashed = enemy.is_homing() && enemy.state_shared.pos.y > ground_y;
+            if enemy.is_dead() && enemy.state_shared.enemy_type == EnemyType::Boss {
+                self.impacts.push(Impact::BossDefeated);
+            }
+            if enemy.is_dead() && !crashed {
+                self.particles.explosion(
+                    enemy.center(),
+                    enemy.size(),
+                    enemy.state_shared.enemy_color.particle_color(),
+                );
+            }
+        }
+        // remove dead enemies
+        self.enemies.retain(|e| !e.is_dead());
+
+        let first_new_bullet = self.bullets.len();
+        for player in self.players.iter_mut().filter(|player| player.is_alive()) {
+            let input = inputs.get(player.index).copied().unwrap_or_default();
+            player.update(
+                dt,
+                &input,
+                &mut self.bullets,
+                &mut self.sounds,
+                &self.tuning,
+            );
+        }
+        for bullet in self.bullets[first_new_bullet..].iter() {
+            self.particles.muzzle_flash(bullet.pos(), bullet.dir());
+        }
+
+        // tracer shots stay lined up with where their cannon is now, including the ones just fired
+        if self.variants.tracer_shots {
+            let players = &self.players;
+            for bullet in self
+                .bullets
+                .iter_mut()
+                .filter(|b| b.hurt_type == BulletHurtType::Enemy)
+            {
+                // the shot of a player that's out keeps flying straight
+                let owner_optional = bullet
+                    .owner_optional
+                    .and_then(|owner| players.get(owner))
+                    .filter(|owner| owner.is_alive());
+                if let Some(owner) = owner_optional {
+                    bullet.follow_owner(owner.pos.x);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ten minutes of game, nobody survives that long without shooting
+    const MAX_TICKS: usize = SIMULATION_TICK_RATE as usize * 60 * 10;
+
+    fn new_world(player_count: usize, seed: u64) -> World {
+        World::new(
+            SpriteMetrics::default(),
+            Tuning::default(),
+            Vec::new(),
+            player_count,
+            GameVariants::default(),
+            seed,
+        )
+    }
+
+    // steps until the game ends or MAX_TICKS, returns how it ended and the ticks it took
+    fn play(
+        world: &mut World,
+        input_for_tick: impl Fn(usize) -> Vec<WorldInput>,
+    ) -> (Option<WorldMessage>, usize) {
+        for tick in 0..MAX_TICKS {
+            let message_optional = world.step(&input_for_tick(tick), SIMULATION_DT);
+            // nobody plays them
+            world.sounds = SoundQueue::default();
+            if message_optional.is_some() {
+                return (message_optional, tick + 1);
+            }
+        }
+        (None, MAX_TICKS)
+    }
+
+    #[test]
+    fn headless_game_ends_in_game_over() {
+        let mut world = new_world(1, 1234);
+        let (message_optional, _) = play(&mut world, |_| Vec::new());
+        assert!(matches!(message_optional, Some(WorldMessage::GameOver)));
+        assert!(world.players[0].lives <= 0);
+    }
+
+    #[test]
+    fn co_op_game_ends_when_every_player_is_out() {
+        let mut world = new_world(2, 1234);
+        let (message_optional, _) = play(&mut world, |_| Vec::new());
+        assert!(matches!(message_optional, Some(WorldMessage::GameOver)));
+        assert!(world.players.iter().all(|player| player.lives <= 0));
+    }
+
+    // sweeps back and forth shooting in bursts, so the game depends on what the demons do
+    fn scripted_input(tick: usize) -> Vec<WorldInput> {
+        let move_x = if tick % 480 < 240 { 1f32 } else { -1f32 };
+        vec![WorldInput::new(move_x, tick % 30 < 15)]
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_the_same_game() {
+        let mut world_a = new_world(1, 42);
+        let mut world_b = new_world(1, 42);
+        let (_, ticks_a) = play(&mut world_a, scripted_input);
+        let (_, ticks_b) = play(&mut world_b, scripted_input);
+        assert_eq!(ticks_a, ticks_b);
+        assert_eq!(world_a.players[0].score, world_b.players[0].score);
+        assert_eq!(world_a.players[0].lives, world_b.players[0].lives);
+    }
+
+    #[test]
+    fn different_seeds_play_different_games() {
+        let enemy_positions = |seed: u64| {
+            let mut world = new_world(1, seed);
+            for tick in 0..SIMULATION_TICK_RATE as usize * 5 {
+                world.step(&scripted_input(tick), SIMULATION_DT);
+            }
+            world
+                .enemies
+                .iter()
+                .map(|enemy| enemy.state_shared.pos)
+                .collect::<Vec<Vec2>>()
+        };
+        assert_ne!(enemy_positions(42), enemy_positions(43));
+    }
+
+    // a second in, so the cannon is ready to fire
+    fn tracer_world() -> World {
+        let mut world = new_world(1, 1234);
+        world.variants.tracer_shots = true;
+        for _ in 0..SIMULATION_TICK_RATE as usize {
+            world.step(&[], SIMULATION_DT);
+        }
+        world
+    }
+
+    // how far right of the cannon the player's shot is after a tick of the given input
+    fn tracer_offset_after_firing(move_x: f32) -> f32 {
+        let mut world = tracer_world();
+        world.step(&[WorldInput::new(move_x, true)], SIMULATION_DT);
+        let bullet = world
+            .bullets
+            .iter()
+            .find(|bullet| bullet.hurt_type == BulletHurtType::Enemy)
+            .unwrap();
+        bullet.pos().x - world.players[0].pos.x
+    }
+
+    #[test]
+    fn tracer_shots_stay_lined_up_with_the_cannon() {
+        let standing = tracer_offset_after_firing(0f32);
+        assert_eq!(tracer_offset_after_firing(1f32), standing);
+        assert_eq!(tracer_offset_after_firing(-1f32), standing);
+
+        let mut world = tracer_world();
+        world.step(&[WorldInput::new(0f32, true)], SIMULATION_DT);
+        for _ in 0..20 {
+            world.step(&[WorldInput::new(1f32, false)], SIMULATION_DT);
+        }
+        let bullet = world
+            .bullets
+            .iter()
+            .find(|bullet| bullet.hurt_type == BulletHurtType::Enemy)
+            .unwrap();
+        assert_eq!(bullet.pos().x - world.players[0].pos.x, standing);
+    }
+}
@@ -10,6 +10,9 @@ cp target/wasm32-unknown-unknown/release/rusty_demon_attack.wasm static/
 cp utils/wasm/index.html static/
 cp utils/wasm/gl.js static/
 cp utils/wasm/audio.js static/
+cp utils/wasm/storage.js static/
+cp utils/wasm/focus.js static/
+cp utils/wasm/gamepad.js static/
 mkdir static/resources
 cp -ar resources static/
 ls -lh static
\ No newline at end of file
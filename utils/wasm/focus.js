@@ -0,0 +1,8 @@
+// lets the game pause itself when the tab is hidden or the page loses focus, used by src/focus.rs
+register_plugin = function (importObject) {
+    importObject.env.focus_window_has_focus = function () {
+        return !document.hidden && document.hasFocus() ? 1 : 0;
+    }
+}
+
+miniquad_add_plugin({ register_plugin });
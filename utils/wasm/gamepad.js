@@ -0,0 +1,34 @@
+// reads the browser's gamepads for src/gamepad.rs, buttons and axes use the standard mapping
+register_plugin = function (importObject) {
+    function gamepad(index) {
+        var gamepads = navigator.getGamepads ? navigator.getGamepads() : [];
+        return gamepads[index];
+    }
+
+    importObject.env.gamepad_count = function () {
+        return navigator.getGamepads ? navigator.getGamepads().length : 0;
+    }
+
+    importObject.env.gamepad_connected = function (index) {
+        var pad = gamepad(index);
+        return pad && pad.connected ? 1 : 0;
+    }
+
+    importObject.env.gamepad_button = function (index, button) {
+        var pad = gamepad(index);
+        if (!pad || button >= pad.buttons.length) {
+            return 0;
+        }
+        return pad.buttons[button].value;
+    }
+
+    importObject.env.gamepad_axis = function (index, axis) {
+        var pad = gamepad(index);
+        if (!pad || axis >= pad.axes.length) {
+            return 0;
+        }
+        return pad.axes[axis];
+    }
+}
+
+miniquad_add_plugin({ register_plugin });
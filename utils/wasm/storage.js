@@ -0,0 +1,33 @@
+// localStorage for the wasm build, used by src/storage.rs
+var storage_prefix = "rusty_demon_attack/";
+
+register_plugin = function (importObject) {
+    importObject.env.storage_set = function (key_ptr, key_len, value_ptr, value_len) {
+        var key = UTF8ToString(key_ptr, key_len);
+        var value = new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, value_ptr, value_len));
+        try {
+            window.localStorage.setItem(storage_prefix + key, value);
+        } catch (e) {
+            console.error("could not save " + key + ": " + e);
+        }
+    }
+
+    importObject.env.storage_get_len = function (key_ptr, key_len) {
+        var key = UTF8ToString(key_ptr, key_len);
+        var value = window.localStorage.getItem(storage_prefix + key);
+        if (value === null) {
+            return -1;
+        }
+        return new TextEncoder().encode(value).length;
+    }
+
+    importObject.env.storage_get = function (key_ptr, key_len, buffer_ptr, buffer_len) {
+        var key = UTF8ToString(key_ptr, key_len);
+        var value = window.localStorage.getItem(storage_prefix + key);
+        var bytes = new TextEncoder().encode(value === null ? "" : value);
+        var buffer = new Uint8Array(wasm_memory.buffer, buffer_ptr, buffer_len);
+        buffer.set(bytes.subarray(0, buffer_len));
+    }
+}
+
+miniquad_add_plugin({ register_plugin });